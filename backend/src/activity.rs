@@ -0,0 +1,207 @@
+use crate::models::{AppState, GameType, InactivityEntry};
+use crate::utils::get_cache_prefix;
+
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+// Achievements that progress without the player doing anything
+const PASSIVE_ACHIEVEMENTS: [&str; 1] = ["Unbreakable"];
+
+// Counters of a player profile that only go up through the player's own actions.
+// Trophies can also rise through defense wins, which is rare enough to be ignored.
+fn activity_signals(game: GameType, profile: &Value) -> BTreeMap<&'static str, i64> {
+    let fields: &[&'static str] = match game {
+        GameType::ClashOfClans => &["donations", "attackWins", "trophies", "builderBaseTrophies"],
+        GameType::ClashRoyale => &[
+            "battleCount",
+            "wins",
+            "donations",
+            "totalDonations",
+            "trophies",
+        ],
+    };
+
+    let mut signals: BTreeMap<&'static str, i64> = fields
+        .iter()
+        .filter_map(|f| Some((*f, profile.get(*f)?.as_i64()?)))
+        .collect();
+
+    let achievements: i64 = profile["achievements"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|a| {
+            a.get("name")
+                .and_then(|n| n.as_str())
+                .is_none_or(|n| !PASSIVE_ACHIEVEMENTS.contains(&n))
+        })
+        .filter_map(|a| a.get("value").and_then(|v| v.as_i64()))
+        .sum();
+    if achievements > 0 {
+        signals.insert("achievements", achievements);
+    }
+    signals
+}
+
+// A signal that went up since the previous fetch, preferring the plain counters over
+// the achievement total. Decreases (season resets, trophy losses on defense) are no
+// activity.
+fn increased_signal(
+    previous: &HashMap<String, i64>,
+    current: &BTreeMap<&'static str, i64>,
+) -> Option<&'static str> {
+    current
+        .iter()
+        .filter(|(name, value)| previous.get(**name).is_some_and(|prev| *value > prev))
+        .min_by_key(|(name, _)| **name == "achievements")
+        .map(|(name, _)| *name)
+}
+
+// Record a fetched `/players/{tag}` body. The player counts as active at the time of
+// the first fetch in which one of its activity counters went up.
+pub async fn record_player_fetch(
+    data: &AppState,
+    game: GameType,
+    body: &[u8],
+) -> Result<(), sqlx::Error> {
+    let Ok(profile) = serde_json::from_slice::<Value>(body) else {
+        return Ok(());
+    };
+    let Some(tag) = profile.get("tag").and_then(|v| v.as_str()) else {
+        return Ok(());
+    };
+    let prefix = get_cache_prefix(game);
+    let signals = activity_signals(game, &profile);
+
+    let previous: HashMap<String, i64> = sqlx::query_scalar::<_, String>(
+        "SELECT signals FROM player_activity WHERE game = $1 AND player_tag = $2",
+    )
+    .bind(prefix)
+    .bind(tag)
+    .fetch_optional(&data.db_pool)
+    .await?
+    .and_then(|s| serde_json::from_str(&s).ok())
+    .unwrap_or_default();
+    let increased = increased_signal(&previous, &signals);
+
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query(
+        "INSERT INTO player_activity (game, player_tag, signals, first_seen_at, last_seen_at)
+         VALUES ($1, $2, $3, $4, $4)
+         ON CONFLICT (game, player_tag) DO UPDATE SET signals = $3, last_seen_at = $4,
+             last_active_at = CASE WHEN $5::TEXT IS NULL THEN player_activity.last_active_at ELSE $4 END,
+             last_signal = COALESCE($5, player_activity.last_signal)",
+    )
+    .bind(prefix)
+    .bind(tag)
+    .bind(serde_json::to_string(&signals).unwrap_or_default())
+    .bind(now)
+    .bind(increased)
+    .execute(&data.db_pool)
+    .await?;
+    Ok(())
+}
+
+// Inactivity of all members of a Supercell clan body, most inactive first. Members
+// that never changed since they were first fetched count as inactive since then.
+pub async fn inactivity_report(
+    data: &AppState,
+    game: GameType,
+    clan: &Value,
+    warn_days: i64,
+    inactive_days: i64,
+) -> Result<Vec<InactivityEntry>, sqlx::Error> {
+    let members: Vec<&Value> = clan["memberList"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .collect();
+    let tags: Vec<String> = members
+        .iter()
+        .filter_map(|m| m.get("tag").and_then(|v| v.as_str()).map(str::to_string))
+        .collect();
+
+    let activity: HashMap<String, (i64, i64, Option<i64>, Option<String>)> =
+        sqlx::query_as::<_, (String, i64, i64, Option<i64>, Option<String>)>(
+            "SELECT player_tag, first_seen_at, last_seen_at, last_active_at, last_signal
+             FROM player_activity WHERE game = $1 AND player_tag = ANY($2)",
+        )
+        .bind(get_cache_prefix(game))
+        .bind(&tags)
+        .fetch_all(&data.db_pool)
+        .await?
+        .into_iter()
+        .map(|(tag, first, last, active, signal)| (tag, (first, last, active, signal)))
+        .collect();
+
+    let now = chrono::Utc::now().timestamp();
+    let str_field = |m: &Value, f: &str| m.get(f).and_then(|v| v.as_str()).map(str::to_string);
+    let mut entries: Vec<InactivityEntry> = members
+        .iter()
+        .filter_map(|m| {
+            let tag = str_field(m, "tag")?;
+            let known = activity.get(&tag);
+            let inactive_for_days =
+                known.map(|(first, _, active, _)| (now - active.unwrap_or(*first)).max(0) / 86400);
+            let status = match inactive_for_days {
+                None => "unknown",
+                Some(d) if d >= inactive_days => "inactive",
+                Some(d) if d >= warn_days => "warning",
+                Some(_) => "active",
+            };
+            Some(InactivityEntry {
+                name: str_field(m, "name").unwrap_or_else(|| tag.clone()),
+                role: str_field(m, "role"),
+                first_seen_at: known.map(|k| k.0),
+                last_seen_at: known.map(|k| k.1),
+                last_active_at: known.and_then(|k| k.2),
+                last_signal: known.and_then(|k| k.3.clone()),
+                inactive_for_days,
+                status: status.to_string(),
+                tag,
+            })
+        })
+        .collect();
+
+    entries.sort_by(|a, b| {
+        b.inactive_for_days
+            .cmp(&a.inactive_for_days)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn only_increased_active_counters_count_as_activity() {
+        let profile = |donations: i64, defenses: i64| {
+            json!({ "donations": donations, "trophies": 5000, "achievements": [
+                { "name": "Unbreakable", "value": defenses },
+                { "name": "Friend in Need", "value": 1000 + donations },
+            ] })
+        };
+        let stored = |p: &Value| -> HashMap<String, i64> {
+            activity_signals(GameType::ClashOfClans, p)
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect()
+        };
+        let before = stored(&profile(100, 10));
+
+        // Defense wins only
+        let after = activity_signals(GameType::ClashOfClans, &profile(100, 11));
+        assert_eq!(increased_signal(&before, &after), None);
+        // Season reset
+        let after = activity_signals(GameType::ClashOfClans, &profile(0, 10));
+        assert_eq!(increased_signal(&before, &after), None);
+        // Donated
+        let after = activity_signals(GameType::ClashOfClans, &profile(120, 10));
+        assert_eq!(increased_signal(&before, &after), Some("donations"));
+        // First fetch
+        assert_eq!(increased_signal(&HashMap::new(), &after), None);
+    }
+}
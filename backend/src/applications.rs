@@ -0,0 +1,332 @@
+use crate::auth::AuthenticatedUser;
+use crate::background::family_clan_tags;
+use crate::errors::ApiError;
+use crate::models::{AppState, Application, ApplicationEvent, GameType};
+use crate::utils::{get_cache_prefix, normalize_tag};
+
+use serde_json::Value;
+
+// Applications that still wait for a decision. Waitlisted ones are assigned to a clan
+// and wait for a free spot.
+pub const OPEN_STATUSES: [&str; 2] = ["pending", "waitlisted"];
+pub const STATUSES: [&str; 5] = ["pending", "waitlisted", "accepted", "rejected", "withdrawn"];
+
+const SELECT_APPLICATIONS: &str =
+    "SELECT id, game, player_tag, player_name, discord_id, message, status,
+     suggested_clan, assigned_clan, created_at, updated_at FROM applications";
+
+// Applicants can only apply with their own accounts (linked in a bot or verified on the
+// website), so nobody can block someone else's account with an open application
+pub async fn is_own_account(
+    data: &AppState,
+    game: GameType,
+    user: &AuthenticatedUser,
+    player_tag: &str,
+) -> Result<bool, sqlx::Error> {
+    let tag = normalize_tag(player_tag);
+    let linked = match game {
+        GameType::ClashOfClans => &user.linked_players,
+        GameType::ClashRoyale => &user.linked_cr_players,
+    };
+    if linked.iter().any(|t| normalize_tag(t) == tag) {
+        return Ok(true);
+    }
+    if game != GameType::ClashOfClans {
+        return Ok(false);
+    }
+    let links = crate::links::links_of(data, &user.claims.sub).await?;
+    Ok(links.iter().any(|l| l.player_tag == tag))
+}
+
+// Create an application for a fetched Supercell profile. CoC applicants are checked
+// against the requirements of all family clans; the best fitting eligible clan is
+// suggested and the full result kept for the reviewers.
+pub async fn submit(
+    data: &AppState,
+    game: GameType,
+    profile: &Value,
+    discord_id: &str,
+    message: Option<&str>,
+) -> Result<Application, ApiError> {
+    let tag = profile
+        .get("tag")
+        .and_then(|v| v.as_str())
+        .map(normalize_tag)
+        .ok_or_else(|| ApiError::Internal("Profile without tag".to_string()))?;
+    let name = profile
+        .get("name")
+        .and_then(|v| v.as_str())
+        .unwrap_or(&tag)
+        .to_string();
+
+    let (suggested, eligibility) = match game {
+        GameType::ClashOfClans => {
+            let clans = crate::requirements::family_eligibility(data, profile).await?;
+            (
+                clans
+                    .iter()
+                    .find(|c| c.eligible)
+                    .map(|c| c.clan_tag.clone()),
+                serde_json::to_string(&clans).ok(),
+            )
+        }
+        GameType::ClashRoyale => (None, None),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = data.db_pool.begin().await?;
+    let open: Option<i64> = sqlx::query_scalar(
+        "SELECT id FROM applications WHERE game = $1 AND player_tag = $2 AND status = ANY($3)",
+    )
+    .bind(get_cache_prefix(game))
+    .bind(&tag)
+    .bind(&OPEN_STATUSES[..])
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(id) = open {
+        return Err(ApiError::Conflict(format!(
+            "{} already has an open application (#{})",
+            tag, id
+        )));
+    }
+
+    let application = sqlx::query_as::<_, Application>(&format!(
+        "WITH inserted AS (
+             INSERT INTO applications (game, player_tag, player_name, discord_id, message, status,
+                 suggested_clan, eligibility, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, 'pending', $6, $7, $8, $8)
+             RETURNING *
+         ) {}",
+        SELECT_APPLICATIONS.replace("FROM applications", "FROM inserted")
+    ))
+    .bind(get_cache_prefix(game))
+    .bind(&tag)
+    .bind(&name)
+    .bind(discord_id)
+    .bind(message)
+    .bind(&suggested)
+    .bind(&eligibility)
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| match &e {
+        // A concurrent submit won the race for the open application
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            ApiError::Conflict(format!("{} already has an open application", tag))
+        }
+        _ => ApiError::from(e),
+    })?;
+    add_event(
+        &mut tx,
+        application.id,
+        "submitted",
+        discord_id,
+        suggested.as_deref(),
+        message,
+        now,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(application)
+}
+
+async fn add_event(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    application_id: i64,
+    action: &str,
+    actor: &str,
+    clan_tag: Option<&str>,
+    note: Option<&str>,
+    now: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO application_events (application_id, action, actor, clan_tag, note, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(application_id)
+    .bind(action)
+    .bind(actor)
+    .bind(clan_tag)
+    .bind(note)
+    .bind(now)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+// Applications with one of `statuses`, oldest first (the queue order)
+pub async fn list(
+    data: &AppState,
+    game: Option<GameType>,
+    statuses: &[&str],
+) -> Result<Vec<Application>, sqlx::Error> {
+    sqlx::query_as::<_, Application>(&format!(
+        "{} WHERE ($1::TEXT IS NULL OR game = $1) AND status = ANY($2) ORDER BY created_at ASC, id ASC",
+        SELECT_APPLICATIONS
+    ))
+    .bind(game.map(get_cache_prefix))
+    .bind(statuses)
+    .fetch_all(&data.db_pool)
+    .await
+}
+
+pub async fn list_for_user(
+    data: &AppState,
+    discord_id: &str,
+) -> Result<Vec<Application>, sqlx::Error> {
+    sqlx::query_as::<_, Application>(&format!(
+        "{} WHERE discord_id = $1 ORDER BY created_at DESC, id DESC",
+        SELECT_APPLICATIONS
+    ))
+    .bind(discord_id)
+    .fetch_all(&data.db_pool)
+    .await
+}
+
+pub async fn get(data: &AppState, id: i64) -> Result<Option<Application>, sqlx::Error> {
+    sqlx::query_as::<_, Application>(&format!("{} WHERE id = $1", SELECT_APPLICATIONS))
+        .bind(id)
+        .fetch_optional(&data.db_pool)
+        .await
+}
+
+// Eligibility snapshot taken on submission (CoC only)
+pub async fn eligibility(data: &AppState, id: i64) -> Result<Option<Value>, sqlx::Error> {
+    let snapshot: Option<Option<String>> =
+        sqlx::query_scalar("SELECT eligibility FROM applications WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&data.db_pool)
+            .await?;
+    Ok(snapshot
+        .flatten()
+        .and_then(|s| serde_json::from_str(&s).ok()))
+}
+
+pub async fn events(data: &AppState, id: i64) -> Result<Vec<ApplicationEvent>, sqlx::Error> {
+    sqlx::query_as::<_, ApplicationEvent>(
+        "SELECT id, action, actor, clan_tag, note, created_at FROM application_events
+         WHERE application_id = $1 ORDER BY created_at ASC, id ASC",
+    )
+    .bind(id)
+    .fetch_all(&data.db_pool)
+    .await
+}
+
+// New status and clan of an open application after `action`
+fn next_status(
+    action: &str,
+    clan_tag: Option<&str>,
+    assigned_clan: Option<String>,
+) -> Result<(&'static str, Option<String>), ApiError> {
+    let clan = clan_tag.map(normalize_tag);
+    let (status, clan) = match action {
+        "assign" => ("waitlisted", clan),
+        "accept" => ("accepted", clan.or(assigned_clan)),
+        "reject" => return Ok(("rejected", None)),
+        "withdraw" => return Ok(("withdrawn", None)),
+        other => {
+            return Err(ApiError::BadRequest(format!(
+                "Unknown action '{}' (expected accept, reject or assign)",
+                other
+            )));
+        }
+    };
+    if clan.is_none() {
+        return Err(ApiError::BadRequest(format!(
+            "'{}' needs a clan_tag",
+            action
+        )));
+    }
+    Ok((status, clan))
+}
+
+// Apply a reviewer decision or a withdrawal by the applicant. Only open applications
+// can change; every change is recorded in `application_events`.
+//
+// - assign: waitlist for a family clan (can be repeated to move to another clan)
+// - accept: into the given clan, or the assigned one
+// - reject / withdraw: close without a clan
+pub async fn transition(
+    data: &AppState,
+    id: i64,
+    action: &str,
+    clan_tag: Option<&str>,
+    note: Option<&str>,
+    actor: &str,
+) -> Result<Application, ApiError> {
+    let mut tx = data.db_pool.begin().await?;
+    let current: Option<(String, String, Option<String>)> = sqlx::query_as(
+        "SELECT game, status, assigned_clan FROM applications WHERE id = $1 FOR UPDATE",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some((game, status, assigned_clan)) = current else {
+        return Err(ApiError::NotFound("Application not found".to_string()));
+    };
+    if !OPEN_STATUSES.contains(&status.as_str()) {
+        return Err(ApiError::Conflict(format!(
+            "Application is already {}",
+            status
+        )));
+    }
+
+    let (new_status, clan) = next_status(action, clan_tag, assigned_clan)?;
+    if let Some(clan) = &clan {
+        let game = if game == "cr" {
+            GameType::ClashRoyale
+        } else {
+            GameType::ClashOfClans
+        };
+        let family = family_clan_tags(data, game)
+            .await
+            .map_err(ApiError::Internal)?;
+        if !family.iter().any(|t| normalize_tag(t) == *clan) {
+            return Err(ApiError::BadRequest(format!(
+                "{} is not a family clan",
+                clan
+            )));
+        }
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query(
+        "UPDATE applications SET status = $2, assigned_clan = COALESCE($3, assigned_clan), updated_at = $4
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(new_status)
+    .bind(&clan)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+    add_event(&mut tx, id, action, actor, clan.as_deref(), note, now).await?;
+    tx.commit().await?;
+
+    get(data, id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Application not found".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_defaults_to_the_assigned_clan() {
+        let assigned = Some("#AAA".to_string());
+        let (status, clan) = next_status("accept", None, assigned.clone()).unwrap();
+        assert_eq!((status, clan.as_deref()), ("accepted", Some("#AAA")));
+
+        let (_, clan) = next_status("accept", Some("bbb"), assigned.clone()).unwrap();
+        assert_eq!(clan.as_deref(), Some("#BBB"));
+        assert!(next_status("accept", None, None).is_err());
+        assert!(next_status("assign", None, assigned.clone()).is_err());
+
+        // Closing keeps the assigned clan for the history
+        assert_eq!(
+            next_status("reject", None, assigned).unwrap(),
+            ("rejected", None)
+        );
+    }
+}
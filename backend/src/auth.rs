@@ -1,4 +1,6 @@
-use crate::models::{AppState, ErrorResponse};
+use crate::errors::ApiError;
+use crate::fixtures::{self, ExternalApi};
+use crate::models::AppState;
 use actix_web::{
     HttpRequest, HttpResponse, Responder,
     cookie::{Cookie, time::Duration},
@@ -65,6 +67,16 @@ pub fn has_required_role(user_role: Option<&str>, required_role: &str) -> bool {
     user_priority >= required_priority
 }
 
+#[utoipa::path(
+    get,
+    path = "/auth/discord/login",
+    tag = "auth",
+    description = "Redirects to the Discord OAuth consent page.",
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 302, description = "Redirect to Discord"),
+    )
+)]
 pub async fn discord_login(data: web::Data<AppState>) -> impl Responder {
     let (auth_url, _csrf_token): (reqwest::Url, CsrfToken) = data
         .oauth_client
@@ -77,48 +89,62 @@ pub async fn discord_login(data: web::Data<AppState>) -> impl Responder {
         .finish()
 }
 
+#[utoipa::path(
+    get,
+    path = "/auth/discord/callback",
+    tag = "auth",
+    description = "OAuth callback. Sets the `auth_token` cookie and redirects to the frontend.",
+    params(("code" = String, Query, description = "OAuth authorization code")),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 302, description = "Redirect to the frontend"),
+        (status = 400, description = "Token exchange failed", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell or bot API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn discord_callback(
     data: web::Data<AppState>,
     query: web::Query<AuthRequest>,
-) -> impl Responder {
-    let code = AuthorizationCode::new(query.code.clone());
-    let token: oauth2::basic::BasicTokenResponse = match data
-        .oauth_client
-        .exchange_code(code)
-        .request_async(&data.client)
-        .await
-    {
-        Ok(token) => token,
-        Err(e) => {
-            error!("Token exchange error: {:?}", e);
-            return HttpResponse::BadRequest().json(ErrorResponse {
-                error: "Failed to exchange token".into(),
-            });
-        }
+) -> Result<HttpResponse, ApiError> {
+    // The token exchange is a POST and always goes to Discord; replay mode skips it
+    // and logs in as the user of the recorded users/@me fixture.
+    let access_token = if data.fixtures.is_replay() {
+        String::new()
+    } else {
+        let code = AuthorizationCode::new(query.code.clone());
+        let token: oauth2::basic::BasicTokenResponse = match data
+            .oauth_client
+            .exchange_code(code)
+            .request_async(&data.client)
+            .await
+        {
+            Ok(token) => token,
+            Err(e) => {
+                error!("Token exchange error: {:?}", e);
+                return Err(ApiError::BadRequest("Failed to exchange token".into()));
+            }
+        };
+        token.access_token().secret().clone()
     };
 
-    let client = reqwest::Client::new();
-    let user_info: DiscordUser = match client
-        .get("https://discord.com/api/users/@me")
-        .header(
-            "Authorization",
-            format!("Bearer {}", token.access_token().secret()),
-        )
-        .send()
-        .await
-    {
-        Ok(res) => match res.json().await {
-            Ok(user) => user,
+    let user_info: DiscordUser =
+        match fixtures::get(&data, ExternalApi::Discord, "/users/@me", &access_token).await {
+            Ok(res) => match serde_json::from_slice(&res.body) {
+                Ok(user) => user,
+                Err(e) => {
+                    return Err(ApiError::Internal(format!(
+                        "User info parse error: {:?}",
+                        e
+                    )));
+                }
+            },
             Err(e) => {
-                error!("User info parse error: {:?}", e);
-                return HttpResponse::InternalServerError().finish();
+                return Err(ApiError::UpstreamUnavailable(format!(
+                    "Discord user info fetch failed: {}",
+                    e
+                )));
             }
-        },
-        Err(e) => {
-            error!("User info fetch error: {:?}", e);
-            return HttpResponse::InternalServerError().finish();
-        }
-    };
+        };
 
     // Fetch extra metadata from internal APIs
     let mut final_is_admin = false;
@@ -128,17 +154,17 @@ pub async fn discord_callback(
     let mut cr_linked = Vec::new();
 
     // 1. Fetch from CoC Upstream
-    match client
-        .get(format!(
-            "{}/api/users/{}",
-            data.upstream_coc_url, user_info.id
-        ))
-        .header("Authorization", format!("Bearer {}", data.coc_api_token))
-        .send()
-        .await
+    let users_path = format!("/api/users/{}", user_info.id);
+    match fixtures::get(
+        &data,
+        ExternalApi::UpstreamCoc,
+        &users_path,
+        &data.coc_api_token,
+    )
+    .await
     {
-        Ok(res) if res.status().is_success() => {
-            if let Ok(m) = res.json::<UserMetadata>().await {
+        Ok(res) if res.is_success() => {
+            if let Ok(m) = serde_json::from_slice::<UserMetadata>(&res.body) {
                 final_is_admin = m.admin;
                 final_highest_role = m.highest_role.unwrap_or_else(|| "NOTINCLAN".to_string());
                 final_nickname = m.nickname;
@@ -146,11 +172,11 @@ pub async fn discord_callback(
                 cr_linked = m.linked_cr_players;
             }
         }
-        Ok(res) if res.status() == 404 => {
+        Ok(res) if res.status == 404 => {
             // Not in CoC clan, handled by default values
         }
         Ok(res) => {
-            error!("CoC metadata fetch failed: {}", res.status());
+            error!("CoC metadata fetch failed: {}", res.status);
         }
         Err(e) => {
             error!("CoC metadata request error: {:?}", e);
@@ -158,17 +184,17 @@ pub async fn discord_callback(
     }
 
     // 2. Fetch from CR Upstream
-    match client
-        .get(format!(
-            "{}/api/users/{}",
-            data.upstream_cr_url, user_info.id
-        ))
-        .header("Authorization", format!("Bearer {}", data.cr_api_token))
-        .send()
-        .await
+    let users_path = format!("/api/users/{}", user_info.id);
+    match fixtures::get(
+        &data,
+        ExternalApi::UpstreamCr,
+        &users_path,
+        &data.cr_api_token,
+    )
+    .await
     {
-        Ok(res) if res.status().is_success() => {
-            if let Ok(m) = res.json::<UserMetadata>().await {
+        Ok(res) if res.is_success() => {
+            if let Ok(m) = serde_json::from_slice::<UserMetadata>(&res.body) {
                 if m.admin {
                     final_is_admin = true;
                 }
@@ -195,11 +221,11 @@ pub async fn discord_callback(
                 }
             }
         }
-        Ok(res) if res.status() == 404 => {
+        Ok(res) if res.status == 404 => {
             // Not in CR clan
         }
         Ok(res) => {
-            error!("CR metadata fetch failed: {}", res.status());
+            error!("CR metadata fetch failed: {}", res.status);
         }
         Err(e) => {
             error!("CR metadata request error: {:?}", e);
@@ -211,6 +237,9 @@ pub async fn discord_callback(
         final_highest_role = "ADMIN".to_string();
     }
 
+    // Keep verified links the bot does not list yet
+    crate::links::merge_verified(&data, &user_info.id, &mut coc_linked, true).await;
+
     let linked_players_json =
         serde_json::to_string(&coc_linked).unwrap_or_else(|_| "[]".to_string());
     let linked_cr_players_json =
@@ -278,7 +307,7 @@ pub async fn discord_callback(
         &EncodingKey::from_secret(data.jwt_secret.as_bytes()),
     ) {
         Ok(t) => t,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
+        Err(e) => return Err(ApiError::Internal(format!("JWT encoding error: {}", e))),
     };
 
     let cookie = Cookie::build("auth_token", token_str)
@@ -288,40 +317,66 @@ pub async fn discord_callback(
         .max_age(Duration::days(7))
         .finish();
 
-    HttpResponse::Found()
+    Ok(HttpResponse::Found()
         .append_header(("Location", data.frontend_url.clone()))
         .cookie(cookie)
-        .finish()
+        .finish())
 }
 
-pub async fn get_me(data: web::Data<AppState>, user: AuthenticatedUser) -> impl Responder {
+#[utoipa::path(
+    get,
+    path = "/auth/me",
+    tag = "auth",
+    description = "Profile of the logged-in user.",
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("AUTHENTICATED"))),
+    responses(
+        (status = 200, description = "Current user", body = crate::models::Me),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_me(
+    data: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
     let user_db = sqlx::query_as::<_, (String, String, Option<String>, Option<String>, Option<String>, Option<String>, bool, String, String)>(
         "SELECT discord_id, username, global_name, nickname, avatar, highest_role, is_admin, COALESCE(linked_players, '[]'), COALESCE(linked_cr_players, '[]') FROM users WHERE discord_id = $1",
     )
     .bind(&user.claims.sub)
-    .fetch_one(&data.db_pool)
-    .await;
+    .fetch_optional(&data.db_pool)
+    .await?;
 
     match user_db {
-        Ok(u) => {
+        Some(u) => {
             let linked_players: Vec<String> = serde_json::from_str(&u.7).unwrap_or_default();
             let linked_cr_players: Vec<String> = serde_json::from_str(&u.8).unwrap_or_default();
-            HttpResponse::Ok().json(serde_json::json!({
-                "discord_id": u.0,
-                "username": u.1,
-                "global_name": u.2,
-                "nickname": u.3,
-                "avatar": u.4,
-                "highest_role": u.5,
-                "is_admin": u.6,
-                "linked_players": linked_players,
-                "linked_cr_players": linked_cr_players,
+            Ok(HttpResponse::Ok().json(crate::models::Me {
+                discord_id: u.0,
+                username: u.1,
+                global_name: u.2,
+                nickname: u.3,
+                avatar: u.4,
+                highest_role: u.5,
+                is_admin: u.6,
+                linked_players,
+                linked_cr_players,
             }))
         }
-        Err(_) => HttpResponse::NotFound().finish(),
+        None => Err(ApiError::NotFound("User not found".into())),
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    tag = "auth",
+    description = "Clears the `auth_token` cookie.",
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Logged out"),
+    )
+)]
 pub async fn logout() -> impl Responder {
     let cookie = Cookie::build("auth_token", "")
         .path("/")
@@ -341,8 +396,40 @@ pub struct AuthenticatedUser {
     pub linked_cr_players: Vec<String>,
 }
 
+// Fetch linked players and state from DB to ensure real-time permissions
+pub async fn load_user(data: &AppState, mut claims: Claims) -> AuthenticatedUser {
+    let user_db = sqlx::query_as::<_, (String, String, Option<String>, bool)>(
+        "SELECT COALESCE(linked_players, '[]'), COALESCE(linked_cr_players, '[]'), highest_role, is_admin FROM users WHERE discord_id = $1",
+    )
+    .bind(&claims.sub)
+    .fetch_one(&data.db_pool)
+    .await;
+
+    let (linked_players, linked_cr_players, db_role, is_admin) = match user_db {
+        Ok((lp_json, cr_json, role, admin)) => (
+            serde_json::from_str(&lp_json).unwrap_or_default(),
+            serde_json::from_str(&cr_json).unwrap_or_default(),
+            role,
+            admin,
+        ),
+        Err(_) => (vec![], vec![], None, false),
+    };
+
+    if is_admin {
+        claims.role = Some("ADMIN".to_string());
+    } else if db_role.is_some() {
+        claims.role = db_role;
+    }
+
+    AuthenticatedUser {
+        claims,
+        linked_players,
+        linked_cr_players,
+    }
+}
+
 impl FromRequest for AuthenticatedUser {
-    type Error = actix_web::Error;
+    type Error = ApiError;
     type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
@@ -354,9 +441,7 @@ impl FromRequest for AuthenticatedUser {
         let token = match req.cookie("auth_token") {
             Some(c) => c.value().to_string(),
             None => {
-                return Box::pin(ready(Err(actix_web::error::ErrorUnauthorized(
-                    "No auth token",
-                ))));
+                return Box::pin(ready(Err(ApiError::Unauthorized("No auth token"))));
             }
         };
 
@@ -365,40 +450,8 @@ impl FromRequest for AuthenticatedUser {
             let validation = jsonwebtoken::Validation::default();
 
             match jsonwebtoken::decode::<Claims>(&token, &decoding_key, &validation) {
-                Ok(c) => {
-                    let user_id = c.claims.sub.clone();
-                    // Fetch linked players and state from DB to ensure real-time permissions
-                    let user_db = sqlx::query_as::<_, (String, String, Option<String>, bool)>(
-                        "SELECT COALESCE(linked_players, '[]'), COALESCE(linked_cr_players, '[]'), highest_role, is_admin FROM users WHERE discord_id = $1",
-                    )
-                    .bind(&user_id)
-                    .fetch_one(&data.db_pool)
-                    .await;
-
-                    let (linked_players, linked_cr_players, db_role, is_admin) = match user_db {
-                        Ok((lp_json, cr_json, role, admin)) => (
-                            serde_json::from_str(&lp_json).unwrap_or_default(),
-                            serde_json::from_str(&cr_json).unwrap_or_default(),
-                            role,
-                            admin,
-                        ),
-                        Err(_) => (vec![], vec![], None, false),
-                    };
-
-                    let mut claims = c.claims;
-                    if is_admin {
-                        claims.role = Some("ADMIN".to_string());
-                    } else if db_role.is_some() {
-                        claims.role = db_role;
-                    }
-
-                    Ok(AuthenticatedUser {
-                        claims,
-                        linked_players,
-                        linked_cr_players,
-                    })
-                }
-                Err(_) => Err(actix_web::error::ErrorUnauthorized("Invalid token")),
+                Ok(c) => Ok(load_user(&data, c.claims).await),
+                Err(_) => Err(ApiError::Unauthorized("Invalid token")),
             }
         })
     }
@@ -409,7 +462,7 @@ pub struct OptionalAuthenticatedUser {
 }
 
 impl FromRequest for OptionalAuthenticatedUser {
-    type Error = actix_web::Error;
+    type Error = ApiError;
     type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
@@ -428,41 +481,9 @@ impl FromRequest for OptionalAuthenticatedUser {
             let validation = jsonwebtoken::Validation::default();
 
             match jsonwebtoken::decode::<Claims>(&token, &decoding_key, &validation) {
-                Ok(c) => {
-                    let user_id = c.claims.sub.clone();
-                    // Fetch linked players and state from DB to ensure real-time permissions
-                    let user_db = sqlx::query_as::<_, (String, String, Option<String>, bool)>(
-                        "SELECT COALESCE(linked_players, '[]'), COALESCE(linked_cr_players, '[]'), highest_role, is_admin FROM users WHERE discord_id = $1",
-                    )
-                    .bind(&user_id)
-                    .fetch_one(&data.db_pool)
-                    .await;
-
-                    let (linked_players, linked_cr_players, db_role, is_admin) = match user_db {
-                        Ok((lp_json, cr_json, role, admin)) => (
-                            serde_json::from_str(&lp_json).unwrap_or_default(),
-                            serde_json::from_str(&cr_json).unwrap_or_default(),
-                            role,
-                            admin,
-                        ),
-                        Err(_) => (vec![], vec![], None, false),
-                    };
-
-                    let mut claims = c.claims;
-                    if is_admin {
-                        claims.role = Some("ADMIN".to_string());
-                    } else if db_role.is_some() {
-                        claims.role = db_role;
-                    }
-
-                    Ok(OptionalAuthenticatedUser {
-                        user: Some(AuthenticatedUser {
-                            claims,
-                            linked_players,
-                            linked_cr_players,
-                        }),
-                    })
-                }
+                Ok(c) => Ok(OptionalAuthenticatedUser {
+                    user: Some(load_user(&data, c.claims).await),
+                }),
                 Err(_) => Ok(OptionalAuthenticatedUser { user: None }),
             }
         })
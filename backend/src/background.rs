@@ -1,10 +1,16 @@
+use crate::fixtures::{self, ExternalApi};
+use crate::jobs::{Job, JobRegistry, JobSchedule};
+use crate::latency::{maintain_latency_history, measure_and_save_latency};
 use crate::models::{AppState, GameType};
-use crate::utils::{update_supercell_cache, update_upstream_cache};
+use crate::utils::{
+    decode_cache_body, encode_tag, get_cache_body, get_cache_prefix, update_supercell_cache,
+    update_upstream_cache,
+};
 
 use log::{debug, error, info};
 use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
 use std::time::Duration;
-use tokio::time::interval;
 
 #[derive(Deserialize)]
 struct Clan {
@@ -17,202 +23,94 @@ struct Clan {
     badge_url: Option<String>,
 }
 
-pub fn spawn_background_task(data: AppState) {
-    // 1. Task for Latency Measurements (Every 1 minute)
-    let latency_data = data.clone();
-    tokio::spawn(async move {
-        let mut ticker = interval(Duration::from_secs(60));
-        loop {
-            ticker.tick().await;
-            let _ = measure_and_save_latency(&latency_data).await;
-        }
-    });
-
-    // 2. Task for CoC Cache Refresh
-    let coc_cache_data = data.clone();
-    tokio::spawn(async move {
-        let mut ticker = interval(Duration::from_secs(
-            coc_cache_data.background_refresh_interval * 60,
-        ));
-        loop {
-            ticker.tick().await;
-            refresh_clans(&coc_cache_data, GameType::ClashOfClans).await;
-        }
-    });
-
-    // 3. Task for CR Cache Refresh (offset by 15 seconds)
-    let cr_cache_data = data.clone();
-    tokio::spawn(async move {
-        // Offset by a bit to spread load
-        tokio::time::sleep(Duration::from_secs(15)).await;
-        let mut ticker = interval(Duration::from_secs(
-            cr_cache_data.background_refresh_interval * 60,
-        ));
-        loop {
-            ticker.tick().await;
-            refresh_clans(&cr_cache_data, GameType::ClashRoyale).await;
-        }
-    });
-
-    // 4. Task for Side Clans CWL Refresh (Every 1 hour)
-    let side_clans_data = data.clone();
-    tokio::spawn(async move {
-        let mut ticker = interval(Duration::from_secs(3600));
-        loop {
-            ticker.tick().await;
-            refresh_side_clans_cwl(&side_clans_data).await;
-        }
-    });
-}
-
-async fn measure_and_save_latency(data: &AppState) {
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-
-    debug!("Background: Measuring latency...");
-
-    // 1. Measure CoC Upstream API
-    let start = std::time::Instant::now();
-    let coc_upstream_res = data
-        .client
-        .get(format!("{}/api/guild", data.upstream_coc_url))
-        .header("Authorization", format!("Bearer {}", data.coc_api_token))
-        .timeout(Duration::from_secs(5))
-        .send()
-        .await;
-    let coc_upstream_latency = if coc_upstream_res.is_ok() {
-        start.elapsed().as_millis() as i32
-    } else {
-        -1
-    };
-
-    let _ = sqlx::query(
-        "INSERT INTO latency_measurements (api_name, latency_ms, timestamp) VALUES ($1, $2, $3)",
-    )
-    .bind("upstream_coc")
-    .bind(coc_upstream_latency)
-    .bind(now)
-    .execute(&data.db_pool)
-    .await;
-
-    // 2. Measure CR Upstream API
-    let start = std::time::Instant::now();
-    let cr_upstream_res = data
-        .client
-        .get(format!("{}/api/guild", data.upstream_cr_url))
-        .header("Authorization", format!("Bearer {}", data.cr_api_token))
-        .timeout(Duration::from_secs(5))
-        .send()
-        .await;
-    let cr_upstream_latency = if cr_upstream_res.is_ok() {
-        start.elapsed().as_millis() as i32
-    } else {
-        -1
-    };
-
-    let _ = sqlx::query(
-        "INSERT INTO latency_measurements (api_name, latency_ms, timestamp) VALUES ($1, $2, $3)",
-    )
-    .bind("upstream_cr")
-    .bind(cr_upstream_latency)
-    .bind(now)
-    .execute(&data.db_pool)
-    .await;
-
-    // 3. Measure CoC Supercell API
-    let start = std::time::Instant::now();
-    let coc_sc_res = data
-        .client
-        .get("https://api.clashofclans.com/v1/clans/%232PP")
-        .header(
-            "Authorization",
-            format!("Bearer {}", data.clash_of_clans_api_token),
-        )
-        .timeout(Duration::from_secs(5))
-        .send()
-        .await;
-    let coc_sc_latency = if coc_sc_res.is_ok() {
-        start.elapsed().as_millis() as i32
-    } else {
-        -1
-    };
-
-    let _ = sqlx::query(
-        "INSERT INTO latency_measurements (api_name, latency_ms, timestamp) VALUES ($1, $2, $3)",
-    )
-    .bind("supercell_coc")
-    .bind(coc_sc_latency)
-    .bind(now)
-    .execute(&data.db_pool)
-    .await;
-
-    // 4. Measure CR Supercell API
-    let start = std::time::Instant::now();
-    let cr_sc_res = data
-        .client
-        .get("https://api.clashroyale.com/v1/clans/%232PP")
-        .header(
-            "Authorization",
-            format!("Bearer {}", data.clash_royale_api_token),
-        )
-        .timeout(Duration::from_secs(5))
-        .send()
-        .await;
-    let cr_sc_latency = if cr_sc_res.is_ok() {
-        start.elapsed().as_millis() as i32
-    } else {
-        -1
-    };
-
-    let _ = sqlx::query(
-        "INSERT INTO latency_measurements (api_name, latency_ms, timestamp) VALUES ($1, $2, $3)",
-    )
-    .bind("supercell_cr")
-    .bind(cr_sc_latency)
-    .bind(now)
-    .execute(&data.db_pool)
-    .await;
-
-    // 5. Measure Website (Frontend)
-    let start = std::time::Instant::now();
-    let url = if data.frontend_url.contains("localhost") {
-        data.frontend_url.replace("localhost", "website")
-    } else {
-        data.frontend_url.clone()
-    };
-
-    let website_res = data
-        .client
-        .get(&url)
-        .timeout(Duration::from_secs(5))
-        .send()
-        .await;
-    let website_latency = if website_res.is_ok() {
-        start.elapsed().as_millis() as i32
-    } else {
-        -1
-    };
-
-    let _ = sqlx::query(
-        "INSERT INTO latency_measurements (api_name, latency_ms, timestamp) VALUES ($1, $2, $3)",
-    )
-    .bind("website")
-    .bind(website_latency)
-    .bind(now)
-    .execute(&data.db_pool)
-    .await;
-
-    // Cleanup old data (older than 24 hours)
-    let one_day_ago = now - 24 * 60 * 60;
-    let _ = sqlx::query("DELETE FROM latency_measurements WHERE timestamp < $1")
-        .bind(one_day_ago)
-        .execute(&data.db_pool)
-        .await;
+pub fn build_job_registry(background_refresh_interval: u64) -> JobRegistry {
+    let refresh_every = Duration::from_secs(background_refresh_interval.max(1) * 60);
+
+    JobRegistry {
+        jobs: vec![
+            Job::new(
+                "latency_probe",
+                "Measure latency of all enabled probes",
+                JobSchedule::Interval(Duration::from_secs(60)),
+                |data| Box::pin(async move { measure_and_save_latency(&data).await }),
+            ),
+            Job::new(
+                "latency_rollup",
+                "Compute latency rollups and prune old measurements",
+                JobSchedule::Interval(Duration::from_secs(600)),
+                |data| Box::pin(async move { maintain_latency_history(&data).await }),
+            ),
+            Job::new(
+                "refresh_coc",
+                "Refresh CoC clan and member caches",
+                JobSchedule::Interval(refresh_every),
+                |data| Box::pin(async move { refresh_clans(&data, GameType::ClashOfClans).await }),
+            ),
+            // Offset by a bit to spread load
+            Job::new(
+                "refresh_cr",
+                "Refresh CR clan and member caches",
+                JobSchedule::Interval(refresh_every),
+                |data| Box::pin(async move { refresh_clans(&data, GameType::ClashRoyale).await }),
+            )
+            .with_initial_delay(Duration::from_secs(15)),
+            // Runs after the clan refresh so member lists are already cached
+            Job::new(
+                "refresh_coc_players",
+                "Refresh CoC player profiles of family members",
+                JobSchedule::Interval(refresh_every),
+                |data| {
+                    Box::pin(
+                        async move { refresh_player_profiles(&data, GameType::ClashOfClans).await },
+                    )
+                },
+            )
+            .with_initial_delay(Duration::from_secs(60)),
+            Job::new(
+                "refresh_cr_players",
+                "Refresh CR player profiles of family members",
+                JobSchedule::Interval(refresh_every),
+                |data| {
+                    Box::pin(
+                        async move { refresh_player_profiles(&data, GameType::ClashRoyale).await },
+                    )
+                },
+            )
+            .with_initial_delay(Duration::from_secs(75)),
+            Job::new(
+                "cache_gc",
+                "Evict cache entries past their retention",
+                JobSchedule::Interval(Duration::from_secs(6 * 3600)),
+                |data| Box::pin(async move { crate::cache_gc::collect_garbage(&data).await }),
+            )
+            .with_initial_delay(Duration::from_secs(120)),
+            Job::new(
+                "season_archive",
+                "Snapshot per-member season stats (the last snapshot of a season is kept, a final one is taken just before the end)",
+                JobSchedule::Interval(Duration::from_secs(3600)),
+                |data| {
+                    Box::pin(async move { crate::season_stats::capture_season_stats(&data).await })
+                },
+            )
+            .with_initial_delay(Duration::from_secs(180)),
+            Job::new(
+                "push_account_links",
+                "Retry pushing verified account links to the CoC bot",
+                JobSchedule::Interval(Duration::from_secs(900)),
+                |data| Box::pin(async move { crate::links::push_pending_links(&data).await }),
+            )
+            .with_initial_delay(Duration::from_secs(90)),
+            Job::new(
+                "refresh_side_clans_cwl",
+                "Sync side clans and record CWL league stats",
+                JobSchedule::Interval(Duration::from_secs(3600)),
+                |data| Box::pin(async move { refresh_side_clans_cwl(&data).await }),
+            ),
+        ],
+    }
 }
 
-async fn refresh_clans(data: &AppState, game: GameType) {
+async fn refresh_clans(data: &AppState, game: GameType) -> Result<i64, String> {
     let game_name = match game {
         GameType::ClashOfClans => "CoC",
         GameType::ClashRoyale => "CR",
@@ -226,32 +124,36 @@ async fn refresh_clans(data: &AppState, game: GameType) {
     }
 
     let clans_url = "/api/clans";
+    let mut clan_list_error = None;
     let mut clans = match update_upstream_cache(data, game, clans_url).await {
         Ok(body_bytes) => serde_json::from_slice::<Vec<Clan>>(&body_bytes).unwrap_or_default(),
-        _ => vec![],
+        Err(e) => {
+            clan_list_error = Some(e);
+            vec![]
+        }
     };
 
     // Add side clans to the refresh list
-    if game == GameType::ClashOfClans {
-        if let Ok(side_clans) =
+    if game == GameType::ClashOfClans
+        && let Ok(side_clans) =
             sqlx::query_as::<_, crate::models::SideClan>("SELECT * FROM side_clans")
                 .fetch_all(&data.db_pool)
                 .await
-        {
-            for sc in side_clans {
-                if !clans.iter().any(|c| c.tag == sc.clan_tag) {
-                    clans.push(Clan {
-                        tag: sc.clan_tag,
-                        name: Some(sc.name),
-                        name_db: None,
-                        index: Some(sc.display_index),
-                        badge_url: sc.badge_url,
-                    });
-                }
+    {
+        for sc in side_clans {
+            if !clans.iter().any(|c| c.tag == sc.clan_tag) {
+                clans.push(Clan {
+                    tag: sc.clan_tag,
+                    name: Some(sc.name),
+                    name_db: None,
+                    index: Some(sc.display_index),
+                    badge_url: sc.badge_url,
+                });
             }
         }
     }
 
+    let mut refreshed = 0;
     if !clans.is_empty() {
         info!(
             "Background Refresh [{}]: Found {} clans to update.",
@@ -265,8 +167,6 @@ async fn refresh_clans(data: &AppState, game: GameType) {
                 continue;
             }
 
-            let encoded_tag = crate::utils::encode_tag(&clan.tag);
-
             debug!(
                 "Background Refresh [{}]: Processing clan {}/{} ({})",
                 game_name,
@@ -275,50 +175,8 @@ async fn refresh_clans(data: &AppState, game: GameType) {
                 clan.tag
             );
 
-            // Upstream endpoints
-            let upstream_endpoints = if game == GameType::ClashOfClans {
-                vec![
-                    format!("/api/clans/{}", encoded_tag),
-                    format!("/api/clans/{}/members", encoded_tag),
-                    // format!("/api/clans/{}/kickpoint-reasons", encoded_tag),
-                    format!("/api/clans/{}/war-members", encoded_tag),
-                    format!("/api/clans/{}/raid-members", encoded_tag),
-                    format!("/api/clans/{}/cwl-members", encoded_tag),
-                ]
-            } else {
-                vec![
-                    format!("/api/clans/{}", encoded_tag),
-                    format!("/api/clans/{}/members", encoded_tag),
-                    // format!("/api/clans/{}/kickpoint-reasons", encoded_tag),
-                ]
-            };
-
-            let supercell_endpoints = vec![format!("/clans/{}", encoded_tag)];
-
-            let mut set = tokio::task::JoinSet::new();
-
-            // Spawn upstream refreshes
-            for endpoint in upstream_endpoints {
-                let data = data.clone();
-                set.spawn(async move {
-                    let res = update_upstream_cache(&data, game, &endpoint).await;
-                    (format!("upstream:{}", endpoint), res)
-                });
-            }
-
-            // Spawn Supercell API refreshes
-            for endpoint in supercell_endpoints {
-                let data = data.clone();
-                set.spawn(async move {
-                    let res = update_supercell_cache(&data, game, &endpoint).await;
-                    (format!("supercell:{}", endpoint), res)
-                });
-            }
-
-            while let Some(res) = set.join_next().await {
-                if let Ok((endpoint, Err(e))) = res {
-                    error!("Error refreshing {}: {}", endpoint, e);
-                }
+            if refresh_clan(data, game, &clan.tag).await.is_ok() {
+                refreshed += 1;
             }
         }
         info!(
@@ -327,89 +185,300 @@ async fn refresh_clans(data: &AppState, game: GameType) {
         );
     }
 
+    if let Err(e) = crate::search::sync_search_index(data, game).await {
+        error!(
+            "Background Refresh [{}]: Search index sync failed: {}",
+            game_name, e
+        );
+    }
+
     info!(
         "Background Refresh [{}]: Cycle complete. Next run in {} minutes.",
         game_name, data.background_refresh_interval
     );
+
+    // Side clans may still have been refreshed, but the run is incomplete
+    match clan_list_error {
+        Some(e) => Err(format!(
+            "Failed to fetch clan list ({}), refreshed {} clans",
+            e, refreshed
+        )),
+        None => Ok(refreshed),
+    }
+}
+
+// Refresh all cached upstream and Supercell endpoints of a single clan
+pub async fn refresh_clan(data: &AppState, game: GameType, tag: &str) -> Result<(), String> {
+    let encoded_tag = crate::utils::encode_tag(tag);
+
+    // Upstream endpoints
+    let upstream_endpoints = if game == GameType::ClashOfClans {
+        vec![
+            format!("/api/clans/{}", encoded_tag),
+            format!("/api/clans/{}/members", encoded_tag),
+            // format!("/api/clans/{}/kickpoint-reasons", encoded_tag),
+            format!("/api/clans/{}/war-members", encoded_tag),
+            format!("/api/clans/{}/raid-members", encoded_tag),
+            format!("/api/clans/{}/cwl-members", encoded_tag),
+        ]
+    } else {
+        vec![
+            format!("/api/clans/{}", encoded_tag),
+            format!("/api/clans/{}/members", encoded_tag),
+            // format!("/api/clans/{}/kickpoint-reasons", encoded_tag),
+        ]
+    };
+
+    let supercell_endpoints = vec![format!("/clans/{}", encoded_tag)];
+
+    let mut set = tokio::task::JoinSet::new();
+
+    // Spawn upstream refreshes
+    for endpoint in upstream_endpoints {
+        let data = data.clone();
+        set.spawn(async move {
+            let res = update_upstream_cache(&data, game, &endpoint).await;
+            (format!("upstream:{}", endpoint), res)
+        });
+    }
+
+    // Spawn Supercell API refreshes
+    for endpoint in supercell_endpoints {
+        let data = data.clone();
+        set.spawn(async move {
+            let res = update_supercell_cache(&data, game, &endpoint).await;
+            (format!("supercell:{}", endpoint), res)
+        });
+    }
+
+    let mut errors = Vec::new();
+    while let Some(res) = set.join_next().await {
+        if let Ok((endpoint, Err(e))) = res {
+            error!("Error refreshing {}: {}", endpoint, e);
+            errors.push(format!("{}: {}", endpoint, e));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+// Tags of all family clans (main clans from the cached clan list, plus side clans for CoC)
+pub async fn family_clan_tags(data: &AppState, game: GameType) -> Result<Vec<String>, String> {
+    let prefix = get_cache_prefix(game);
+
+    let clans_body = get_cache_body(data, &format!("{}:upstream:/api/clans", prefix))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut clan_tags: Vec<String> = clans_body
+        .and_then(|body| serde_json::from_slice::<Vec<Clan>>(&body).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| c.tag)
+        .collect();
+
+    if game == GameType::ClashOfClans
+        && let Ok(side_clans) = sqlx::query_as::<_, (String,)>("SELECT clan_tag FROM side_clans")
+            .fetch_all(&data.db_pool)
+            .await
+    {
+        clan_tags.extend(side_clans.into_iter().map(|(tag,)| tag));
+    }
+
+    // Skip non-clan entries like "warteliste"
+    clan_tags.retain(|t| t.starts_with('#'));
+    clan_tags.sort();
+    clan_tags.dedup();
+    Ok(clan_tags)
+}
+
+// Tags of all family members according to the cached clan and member lists
+pub async fn family_member_tags(data: &AppState, game: GameType) -> Result<Vec<String>, String> {
+    let prefix = get_cache_prefix(game);
+    let clan_tags = family_clan_tags(data, game).await?;
+
+    let mut keys = Vec::new();
+    for tag in &clan_tags {
+        let encoded_tag = encode_tag(tag);
+        keys.push(format!("{}:supercell:/clans/{}", prefix, encoded_tag));
+        keys.push(format!(
+            "{}:upstream:/api/clans/{}/members",
+            prefix, encoded_tag
+        ));
+    }
+
+    let bodies = sqlx::query_as::<_, (Vec<u8>,)>("SELECT body FROM cache WHERE key = ANY($1)")
+        .bind(&keys)
+        .fetch_all(&data.db_pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Supercell clans list members under "memberList", the bots return a plain array
+    let mut tags = BTreeSet::new();
+    for (body,) in bodies {
+        let json: serde_json::Value =
+            serde_json::from_slice(&decode_cache_body(body)).unwrap_or(serde_json::Value::Null);
+        let members = json
+            .get("memberList")
+            .and_then(|v| v.as_array())
+            .or_else(|| json.as_array());
+        for member in members.into_iter().flatten() {
+            if let Some(tag) = member.get("tag").and_then(|t| t.as_str())
+                && !tag.is_empty()
+            {
+                tags.insert(crate::utils::normalize_tag(tag));
+            }
+        }
+    }
+
+    Ok(tags.into_iter().collect())
+}
+
+// Keep `/players/{tag}` caches of all family members warm, least recently refreshed
+// first. At most `player_refresh_budget` profiles are fetched per run.
+async fn refresh_player_profiles(data: &AppState, game: GameType) -> Result<i64, String> {
+    let prefix = get_cache_prefix(game);
+    let tags = family_member_tags(data, game).await?;
+
+    let player_key = |tag: &str| format!("{}:supercell:/players/{}", prefix, encode_tag(tag));
+    let keys: Vec<String> = tags.iter().map(|t| player_key(t)).collect();
+
+    let refreshed_at: HashMap<String, i64> =
+        sqlx::query_as::<_, (String, i64)>("SELECT key, updated_at FROM cache WHERE key = ANY($1)")
+            .bind(&keys)
+            .fetch_all(&data.db_pool)
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .collect();
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let min_age = data.background_refresh_interval.max(1) as i64 * 60;
+
+    // Never fetched profiles have no cache row and sort first
+    let mut due: Vec<(i64, &String)> = tags
+        .iter()
+        .map(|t| (refreshed_at.get(&player_key(t)).copied().unwrap_or(0), t))
+        .filter(|(updated_at, _)| now - updated_at >= min_age)
+        .collect();
+    due.sort();
+    due.truncate(data.player_refresh_budget.max(0) as usize);
+
+    info!(
+        "Background Refresh [{} players]: {} members, refreshing {} profiles",
+        prefix,
+        tags.len(),
+        due.len()
+    );
+
+    let mut refreshed = 0;
+    let mut failed = 0;
+    for (_, tag) in &due {
+        let url_path = format!("/players/{}", encode_tag(tag));
+        match update_supercell_cache(data, game, &url_path).await {
+            Ok(_) => refreshed += 1,
+            Err(e) => {
+                debug!("Error refreshing player {}: {}", tag, e);
+                failed += 1;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    if refreshed == 0 && failed > 0 {
+        return Err(format!("All {} player refreshes failed", failed));
+    }
+    if failed > 0 {
+        error!(
+            "Background Refresh [{} players]: {} of {} refreshes failed",
+            prefix,
+            failed,
+            due.len()
+        );
+    }
+    Ok(refreshed)
 }
 
-async fn refresh_side_clans_cwl(data: &AppState) {
+async fn refresh_side_clans_cwl(data: &AppState) -> Result<i64, String> {
     info!("Background Refresh [Side Clans CWL]: Starting...");
 
     // 0. Update side clans from external configuration endpoint
-    let sync_url = format!("{}/api/sideclans", data.upstream_coc_url);
     let mut side_clans_to_sync: Vec<crate::models::SideClan> = Vec::new();
 
     // First, try to fetch main clans to include them as well
-    let clans_url = format!("{}/api/clans", data.upstream_coc_url);
-    if let Ok(clans_resp) = data
-        .client
-        .get(&clans_url)
-        .header("Authorization", format!("Bearer {}", data.coc_api_token))
-        .send()
-        .await
+    if let Ok(clans_resp) = fixtures::get(
+        data,
+        ExternalApi::UpstreamCoc,
+        "/api/clans",
+        &data.coc_api_token,
+    )
+    .await
+        && clans_resp.is_success()
+        && let Ok(main_clans) = serde_json::from_slice::<Vec<Clan>>(&clans_resp.body)
     {
-        if clans_resp.status().is_success() {
-            if let Ok(clans_bytes) = clans_resp.bytes().await {
-                if let Ok(main_clans) = serde_json::from_slice::<Vec<Clan>>(&clans_bytes) {
-                    for clan in main_clans {
-                        side_clans_to_sync.push(crate::models::SideClan {
-                            clan_tag: clan.tag,
-                            name: clan
-                                .name
-                                .or(clan.name_db)
-                                .unwrap_or_else(|| "Unknown".to_string()),
-                            belongs_to: None, // Main clans don't belong to others
-                            display_index: clan.index.unwrap_or(999),
-                            badge_url: clan.badge_url,
-                        });
-                    }
-                }
-            }
+        for clan in main_clans {
+            side_clans_to_sync.push(crate::models::SideClan {
+                clan_tag: clan.tag,
+                name: clan
+                    .name
+                    .or(clan.name_db)
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                belongs_to: None, // Main clans don't belong to others
+                display_index: clan.index.unwrap_or(999),
+                badge_url: clan.badge_url,
+            });
         }
     }
 
-    match data
-        .client
-        .get(&sync_url)
-        .header("Authorization", format!("Bearer {}", data.coc_api_token))
-        .send()
-        .await
+    match fixtures::get(
+        data,
+        ExternalApi::UpstreamCoc,
+        "/api/sideclans",
+        &data.coc_api_token,
+    )
+    .await
     {
         Ok(sync_resp) => {
-            if sync_resp.status().is_success() {
-                if let Ok(sync_bytes) = sync_resp.bytes().await {
-                    match serde_json::from_slice::<Vec<crate::models::SideClan>>(&sync_bytes) {
-                        Ok(side_clans) => {
-                            info!(
-                                "Background Refresh [Side Clans CWL]: Syncing {} side clans and {} main clans...",
-                                side_clans.len(),
-                                side_clans_to_sync.len()
-                            );
+            if sync_resp.is_success() {
+                let sync_bytes = sync_resp.body;
+                match serde_json::from_slice::<Vec<crate::models::SideClan>>(&sync_bytes) {
+                    Ok(side_clans) => {
+                        info!(
+                            "Background Refresh [Side Clans CWL]: Syncing {} side clans and {} main clans...",
+                            side_clans.len(),
+                            side_clans_to_sync.len()
+                        );
 
-                            // Merge side clans, replacing main clan entries if they exist in side clans (to keep belongs_to)
-                            for sc in side_clans {
-                                if let Some(pos) = side_clans_to_sync
-                                    .iter()
-                                    .position(|c| c.clan_tag == sc.clan_tag)
-                                {
-                                    side_clans_to_sync[pos] = sc;
-                                } else {
-                                    side_clans_to_sync.push(sc);
-                                }
+                        // Merge side clans, replacing main clan entries if they exist in side clans (to keep belongs_to)
+                        for sc in side_clans {
+                            if let Some(pos) = side_clans_to_sync
+                                .iter()
+                                .position(|c| c.clan_tag == sc.clan_tag)
+                            {
+                                side_clans_to_sync[pos] = sc;
+                            } else {
+                                side_clans_to_sync.push(sc);
                             }
+                        }
 
-                            let tags_to_keep: Vec<String> = side_clans_to_sync
-                                .iter()
-                                .map(|c| c.clan_tag.clone())
-                                .collect();
-                            let _ = sqlx::query("DELETE FROM side_clans WHERE clan_tag != ALL($1)")
-                                .bind(&tags_to_keep)
-                                .execute(&data.db_pool)
-                                .await;
+                        let tags_to_keep: Vec<String> = side_clans_to_sync
+                            .iter()
+                            .map(|c| c.clan_tag.clone())
+                            .collect();
+                        let _ = sqlx::query("DELETE FROM side_clans WHERE clan_tag != ALL($1)")
+                            .bind(&tags_to_keep)
+                            .execute(&data.db_pool)
+                            .await;
 
-                            for clan in side_clans_to_sync {
-                                let _ = sqlx::query(
+                        for clan in side_clans_to_sync {
+                            let _ = sqlx::query(
                                     "INSERT INTO side_clans (clan_tag, name, belongs_to, display_index, badge_url) 
                                      VALUES ($1, $2, $3, $4, $5) 
                                      ON CONFLICT (clan_tag) DO UPDATE SET name = $2, belongs_to = $3, display_index = $4, badge_url = $5",
@@ -421,26 +490,25 @@ async fn refresh_side_clans_cwl(data: &AppState) {
                                 .bind(clan.badge_url)
                                 .execute(&data.db_pool)
                                 .await;
-                            }
                         }
-                        Err(e) => {
+                    }
+                    Err(e) => {
+                        error!(
+                            "Background Refresh [Side Clans CWL]: Failed to deserialize side clans from sync response: {}",
+                            e
+                        );
+                        if let Ok(body_str) = String::from_utf8(sync_bytes.to_vec()) {
                             error!(
-                                "Background Refresh [Side Clans CWL]: Failed to deserialize side clans from sync response: {}",
-                                e
+                                "Response snippet: {}",
+                                &body_str[..body_str.len().min(1000)]
                             );
-                            if let Ok(body_str) = String::from_utf8(sync_bytes.to_vec()) {
-                                error!(
-                                    "Response snippet: {}",
-                                    &body_str[..body_str.len().min(1000)]
-                                );
-                            }
                         }
                     }
                 }
             } else {
                 error!(
                     "Background Refresh [Side Clans CWL]: Failed to sync config. Status: {}",
-                    sync_resp.status()
+                    sync_resp.status
                 );
             }
         }
@@ -457,13 +525,14 @@ async fn refresh_side_clans_cwl(data: &AppState) {
         Ok(rows) => rows,
         Err(e) => {
             error!("Error fetching side clans from DB: {}", e);
-            return;
+            return Err(e.to_string());
         }
     };
 
-    let now = chrono::Utc::now();
-    let season = now.format("%Y-%m").to_string();
+    // Fallback if the leaguegroup endpoint has no season (e.g. no CWL running)
+    let season = crate::calendar::cwl(chrono::Utc::now()).key;
 
+    let processed = clans.len() as i64;
     for row in clans {
         use sqlx::Row;
         let clan_tag: String = row.get("clan_tag");
@@ -509,82 +578,72 @@ async fn refresh_side_clans_cwl(data: &AppState) {
                             .and_then(|v| v.as_str())
                             .map(|s| s.to_string());
 
-                        if league_badge_url.is_none() {
-                            if let Some(id) = league_id {
-                                league_badge_url = Some(get_cwl_badge_url(id));
-                            }
+                        if league_badge_url.is_none()
+                            && let Some(id) = league_id
+                        {
+                            league_badge_url = Some(get_cwl_badge_url(id));
                         }
 
                         // Try to get rank and accurate season from leaguegroup endpoint
                         let mut rank: Option<i32> = None;
                         let mut season_to_use = season.clone();
 
-                        let lg_url = format!(
-                            "https://api.clashofclans.com/v1/clans/{}/currentwar/leaguegroup",
-                            encoded_tag
-                        );
-                        let lg_res = data
-                            .client
-                            .get(&lg_url)
-                            .header(
-                                "Authorization",
-                                format!("Bearer {}", data.clash_of_clans_api_token),
-                            )
-                            .send()
-                            .await;
+                        let lg_path = format!("/clans/{}/currentwar/leaguegroup", encoded_tag);
+                        let lg_res = fixtures::get(
+                            data,
+                            ExternalApi::SupercellCoc,
+                            &lg_path,
+                            &data.clash_of_clans_api_token,
+                        )
+                        .await;
+
+                        if let Ok(lg_resp) = lg_res
+                            && lg_resp.is_success()
+                            && let Ok(lg_json) =
+                                serde_json::from_slice::<serde_json::Value>(&lg_resp.body)
+                        {
+                            if let Some(s) = lg_json
+                                .get("season")
+                                .and_then(|v: &serde_json::Value| v.as_str())
+                            {
+                                season_to_use = s.to_string();
+                            }
 
-                        if let Ok(lg_resp) = lg_res {
-                            if lg_resp.status().is_success() {
-                                if let Ok(lg_bytes) = lg_resp.bytes().await {
-                                    if let Ok(lg_json) =
-                                        serde_json::from_slice::<serde_json::Value>(&lg_bytes)
-                                    {
-                                        if let Some(s) = lg_json
-                                            .get("season")
-                                            .and_then(|v: &serde_json::Value| v.as_str())
-                                        {
-                                            season_to_use = s.to_string();
-                                        }
-
-                                        if let Some(clans) = lg_json
-                                            .get("clans")
-                                            .and_then(|v: &serde_json::Value| v.as_array())
-                                        {
-                                            let mut sorted_clans = clans.clone();
-                                            sorted_clans.sort_by(|a, b| {
-                                                let a_stars = a
-                                                    .get("stars")
-                                                    .and_then(|v: &serde_json::Value| v.as_i64())
-                                                    .unwrap_or(0);
-                                                let b_stars = b
-                                                    .get("stars")
-                                                    .and_then(|v: &serde_json::Value| v.as_i64())
-                                                    .unwrap_or(0);
-                                                let a_dest = a
-                                                    .get("destructionPercentage")
-                                                    .and_then(|v: &serde_json::Value| v.as_f64())
-                                                    .unwrap_or(0.0);
-                                                let b_dest = b
-                                                    .get("destructionPercentage")
-                                                    .and_then(|v: &serde_json::Value| v.as_f64())
-                                                    .unwrap_or(0.0);
-
-                                                b_stars.cmp(&a_stars).then_with(|| {
-                                                    b_dest
-                                                        .partial_cmp(&a_dest)
-                                                        .unwrap_or(std::cmp::Ordering::Equal)
-                                                })
-                                            });
-
-                                            if let Some(pos) = sorted_clans.iter().position(|c| {
-                                                c.get("tag")
-                                                    .and_then(|v: &serde_json::Value| v.as_str())
-                                                    == Some(&clan_tag)
-                                            }) {
-                                                rank = Some((pos + 1) as i32);
-                                            }
-                                        }
-                                    }
+                            if let Some(clans) = lg_json
+                                .get("clans")
+                                .and_then(|v: &serde_json::Value| v.as_array())
+                            {
+                                let mut sorted_clans = clans.clone();
+                                sorted_clans.sort_by(|a, b| {
+                                    let a_stars = a
+                                        .get("stars")
+                                        .and_then(|v: &serde_json::Value| v.as_i64())
+                                        .unwrap_or(0);
+                                    let b_stars = b
+                                        .get("stars")
+                                        .and_then(|v: &serde_json::Value| v.as_i64())
+                                        .unwrap_or(0);
+                                    let a_dest = a
+                                        .get("destructionPercentage")
+                                        .and_then(|v: &serde_json::Value| v.as_f64())
+                                        .unwrap_or(0.0);
+                                    let b_dest = b
+                                        .get("destructionPercentage")
+                                        .and_then(|v: &serde_json::Value| v.as_f64())
+                                        .unwrap_or(0.0);
+
+                                    b_stars.cmp(&a_stars).then_with(|| {
+                                        b_dest
+                                            .partial_cmp(&a_dest)
+                                            .unwrap_or(std::cmp::Ordering::Equal)
+                                    })
+                                });
+
+                                if let Some(pos) = sorted_clans.iter().position(|c| {
+                                    c.get("tag").and_then(|v: &serde_json::Value| v.as_str())
+                                        == Some(&clan_tag)
+                                }) {
+                                    rank = Some((pos + 1) as i32);
                                 }
                             }
                         }
@@ -613,6 +672,8 @@ async fn refresh_side_clans_cwl(data: &AppState) {
         }
         tokio::time::sleep(Duration::from_millis(100)).await;
     }
+
+    Ok(processed)
 }
 
 fn get_cwl_badge_url(id: i32) -> String {
@@ -0,0 +1,220 @@
+use crate::background::{family_clan_tags, family_member_tags};
+use crate::models::{AppState, CacheClassStats, CacheStats, GameType};
+use crate::utils::{CacheClass, encode_tag, get_cache_prefix, parse_cache_key};
+
+use log::info;
+use std::collections::{HashMap, HashSet};
+
+const DELETE_BATCH_SIZE: usize = 1000;
+
+// Family-wide lists the pinned tags are derived from
+const PINNED_KEYS: [&str; 3] = [
+    "coc:upstream:/api/clans",
+    "cr:upstream:/api/clans",
+    "coc:upstream:/api/guild",
+];
+
+// Encoded tags of current family clans and members per cache prefix ("coc:%23ABC")
+struct PinnedTags(HashSet<String>);
+
+impl PinnedTags {
+    async fn load(data: &AppState) -> Result<Self, String> {
+        let mut pinned = HashSet::new();
+        for game in [GameType::ClashOfClans, GameType::ClashRoyale] {
+            let prefix = get_cache_prefix(game);
+            let clans = family_clan_tags(data, game).await?;
+            let members = family_member_tags(data, game).await?;
+            for tag in clans.iter().chain(members.iter()) {
+                pinned.insert(format!("{}:{}", prefix, encode_tag(tag)));
+            }
+        }
+        Ok(PinnedTags(pinned))
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        if PINNED_KEYS.contains(&key) {
+            return true;
+        }
+        let Some((game, _, url_path)) = parse_cache_key(key) else {
+            return false;
+        };
+        match path_tag(url_path) {
+            Some(tag) => self
+                .0
+                .contains(&format!("{}:{}", get_cache_prefix(game), tag)),
+            None => false,
+        }
+    }
+}
+
+// The (encoded) clan or player tag a path refers to, e.g. "/api/clans/%23ABC/members"
+fn path_tag(url_path: &str) -> Option<&str> {
+    let path = url_path.strip_prefix("/api").unwrap_or(url_path);
+    let rest = path
+        .strip_prefix("/clans/")
+        .or_else(|| path.strip_prefix("/players/"))?;
+    let tag = rest.split(['/', '?']).next()?;
+    (!tag.is_empty()).then_some(tag)
+}
+
+fn retention_secs(data: &AppState, class: Option<CacheClass>) -> i64 {
+    match class {
+        Some(class) => data.cache_policies.get(class).retention_secs,
+        None => data.cache_policies.other_retention_secs,
+    }
+}
+
+fn class_name(class: Option<CacheClass>) -> &'static str {
+    class.map(|c| c.name()).unwrap_or("other")
+}
+
+// Delete entries that were neither updated nor read within their class retention.
+// Entries of current family clans and members are never evicted.
+pub async fn collect_garbage(data: &AppState) -> Result<i64, String> {
+    let now = chrono::Utc::now().timestamp();
+    let min_retention = [CacheClass::Clan, CacheClass::Player, CacheClass::User]
+        .into_iter()
+        .map(|c| data.cache_policies.get(c).retention_secs)
+        .chain([data.cache_policies.other_retention_secs])
+        .min()
+        .unwrap_or(0);
+
+    let candidates = sqlx::query_as::<_, (String, i64, Option<i64>)>(
+        "SELECT key, updated_at, accessed_at FROM cache
+         WHERE GREATEST(updated_at, COALESCE(accessed_at, 0)) < $1",
+    )
+    .bind(now - min_retention)
+    .fetch_all(&data.db_pool)
+    .await
+    .map_err(|e| format!("Error loading cache entries: {}", e))?;
+
+    let pinned = PinnedTags::load(data).await?;
+    prune_player_activity(data, &pinned, now).await?;
+
+    let mut expired: Vec<String> = Vec::new();
+    let mut kept_pinned = 0;
+    for (key, updated_at, accessed_at) in candidates {
+        let class = parse_cache_key(&key).and_then(|(_, _, path)| CacheClass::of_path(path));
+        let last_used = updated_at.max(accessed_at.unwrap_or(0));
+        if now - last_used < retention_secs(data, class) {
+            continue;
+        }
+        if pinned.contains(&key) {
+            kept_pinned += 1;
+            continue;
+        }
+        expired.push(key);
+    }
+
+    let mut deleted = 0;
+    for batch in expired.chunks(DELETE_BATCH_SIZE) {
+        let res = sqlx::query("DELETE FROM cache WHERE key = ANY($1)")
+            .bind(batch)
+            .execute(&data.db_pool)
+            .await
+            .map_err(|e| format!("Error deleting cache entries: {}", e))?;
+        deleted += res.rows_affected() as i64;
+    }
+
+    info!(
+        "Cache GC: deleted {} expired entries, kept {} pinned",
+        deleted, kept_pinned
+    );
+    Ok(deleted)
+}
+
+// Activity of players that are not in the family (fetched by a search or a profile
+// view) is dropped once they were not fetched within the player retention
+async fn prune_player_activity(
+    data: &AppState,
+    pinned: &PinnedTags,
+    now: i64,
+) -> Result<(), String> {
+    let stale = sqlx::query_as::<_, (String, String)>(
+        "SELECT game, player_tag FROM player_activity WHERE last_seen_at < $1",
+    )
+    .bind(now - retention_secs(data, Some(CacheClass::Player)))
+    .fetch_all(&data.db_pool)
+    .await
+    .map_err(|e| format!("Error loading player activity: {}", e))?;
+
+    let mut deleted = 0;
+    for game in [GameType::ClashOfClans, GameType::ClashRoyale] {
+        let prefix = get_cache_prefix(game);
+        let tags: Vec<&str> = stale
+            .iter()
+            .filter(|(g, tag)| {
+                g == prefix
+                    && !pinned
+                        .0
+                        .contains(&format!("{}:{}", prefix, encode_tag(tag)))
+            })
+            .map(|(_, tag)| tag.as_str())
+            .collect();
+        for batch in tags.chunks(DELETE_BATCH_SIZE) {
+            let res =
+                sqlx::query("DELETE FROM player_activity WHERE game = $1 AND player_tag = ANY($2)")
+                    .bind(prefix)
+                    .bind(batch)
+                    .execute(&data.db_pool)
+                    .await
+                    .map_err(|e| format!("Error deleting player activity: {}", e))?;
+            deleted += res.rows_affected();
+        }
+    }
+    if deleted > 0 {
+        info!(
+            "Cache GC: deleted the activity of {} non-family players",
+            deleted
+        );
+    }
+    Ok(())
+}
+
+// Entry counts and sizes per key class
+pub async fn cache_stats(data: &AppState) -> Result<CacheStats, String> {
+    let now = chrono::Utc::now().timestamp();
+
+    let rows = sqlx::query_as::<_, (String, i32, i64, Option<i64>)>(
+        "SELECT key, octet_length(body), updated_at, accessed_at FROM cache",
+    )
+    .fetch_all(&data.db_pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (table_bytes,): (i64,) = sqlx::query_as("SELECT pg_total_relation_size('cache')")
+        .fetch_one(&data.db_pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let pinned = PinnedTags::load(data).await?;
+
+    let mut classes: HashMap<String, CacheClassStats> = HashMap::new();
+    for (key, size, updated_at, accessed_at) in rows {
+        let class = parse_cache_key(&key).and_then(|(_, _, path)| CacheClass::of_path(path));
+        let stats = classes.entry(class_name(class).to_string()).or_default();
+        stats.entries += 1;
+        stats.bytes += size as i64;
+        stats.oldest_update = Some(
+            stats
+                .oldest_update
+                .map_or(updated_at, |o| o.min(updated_at)),
+        );
+
+        let is_pinned = pinned.contains(&key);
+        if is_pinned {
+            stats.pinned += 1;
+        }
+        let last_used = updated_at.max(accessed_at.unwrap_or(0));
+        if !is_pinned && now - last_used >= retention_secs(data, class) {
+            stats.expired += 1;
+        }
+    }
+
+    Ok(CacheStats {
+        table_bytes,
+        entries: classes.values().map(|c| c.entries).sum(),
+        body_bytes: classes.values().map(|c| c.bytes).sum(),
+        classes,
+    })
+}
@@ -0,0 +1,225 @@
+use crate::models::{GameType, SeasonWindow};
+
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
+
+// In-game schedules (UTC):
+// - CoC trophy season: ends on the last Monday of the month at 05:00. Keyed by the
+//   month it ends in, like the season ids of the Supercell API ("2026-10").
+// - CWL: starts on the 1st at 08:00, signup plus 7 war days are over after 10 days.
+// - Raid weekend: Friday 07:00 to Monday 07:00, keyed by its start date.
+// - Clan games: 22nd 08:00 to 28th 08:00.
+// - CR season: starts on the first Monday of the month at 09:00, keyed by that month.
+const COC_SEASON_END_HOUR: u32 = 5;
+const CWL_START_HOUR: u32 = 8;
+const CWL_DAYS: u64 = 10;
+const RAID_START_HOUR: u32 = 7;
+const RAID_DAYS: u64 = 3;
+const CLAN_GAMES_HOUR: u32 = 8;
+const CLAN_GAMES_START_DAY: u32 = 22;
+const CLAN_GAMES_END_DAY: u32 = 28;
+const CR_SEASON_START_HOUR: u32 = 9;
+
+#[derive(Debug, Clone, Copy)]
+struct Month {
+    year: i32,
+    month: u32,
+}
+
+impl Month {
+    fn of(at: DateTime<Utc>) -> Self {
+        Month {
+            year: at.year(),
+            month: at.month(),
+        }
+    }
+
+    fn next(self) -> Self {
+        match self.month {
+            12 => Month {
+                year: self.year + 1,
+                month: 1,
+            },
+            m => Month {
+                year: self.year,
+                month: m + 1,
+            },
+        }
+    }
+
+    fn prev(self) -> Self {
+        match self.month {
+            1 => Month {
+                year: self.year - 1,
+                month: 12,
+            },
+            m => Month {
+                year: self.year,
+                month: m - 1,
+            },
+        }
+    }
+
+    fn day(self, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(self.year, self.month, day).expect("valid day of month")
+    }
+
+    fn key(self) -> String {
+        format!("{:04}-{:02}", self.year, self.month)
+    }
+
+    fn last_monday(self) -> NaiveDate {
+        let last = self.next().day(1).pred_opt().expect("valid date");
+        last - Days::new(last.weekday().num_days_from_monday() as u64)
+    }
+
+    fn first_monday(self) -> NaiveDate {
+        let first = self.day(1);
+        first + Days::new((7 - first.weekday().num_days_from_monday() as u64) % 7)
+    }
+}
+
+fn at_hour(date: NaiveDate, hour: u32) -> DateTime<Utc> {
+    date.and_hms_opt(hour, 0, 0).expect("valid hour").and_utc()
+}
+
+fn window(
+    key: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    at: DateTime<Utc>,
+) -> SeasonWindow {
+    SeasonWindow {
+        key,
+        start: start.timestamp(),
+        end: end.timestamp(),
+        active: start <= at && at < end,
+    }
+}
+
+// Trophy season running at `at` (always active)
+pub fn season(game: GameType, at: DateTime<Utc>) -> SeasonWindow {
+    match game {
+        GameType::ClashOfClans => coc_season(at),
+        GameType::ClashRoyale => cr_season(at),
+    }
+}
+
+pub fn coc_season(at: DateTime<Utc>) -> SeasonWindow {
+    let mut month = Month::of(at);
+    if at >= at_hour(month.last_monday(), COC_SEASON_END_HOUR) {
+        month = month.next();
+    }
+    window(
+        month.key(),
+        at_hour(month.prev().last_monday(), COC_SEASON_END_HOUR),
+        at_hour(month.last_monday(), COC_SEASON_END_HOUR),
+        at,
+    )
+}
+
+pub fn cr_season(at: DateTime<Utc>) -> SeasonWindow {
+    let mut month = Month::of(at);
+    if at < at_hour(month.first_monday(), CR_SEASON_START_HOUR) {
+        month = month.prev();
+    }
+    window(
+        month.key(),
+        at_hour(month.first_monday(), CR_SEASON_START_HOUR),
+        at_hour(month.next().first_monday(), CR_SEASON_START_HOUR),
+        at,
+    )
+}
+
+// Latest CWL that started at or before `at` (active while it is running). Keyed like
+// the `season` of the leaguegroup endpoint.
+pub fn cwl(at: DateTime<Utc>) -> SeasonWindow {
+    let mut month = Month::of(at);
+    if at < at_hour(month.day(1), CWL_START_HOUR) {
+        month = month.prev();
+    }
+    let start = at_hour(month.day(1), CWL_START_HOUR);
+    window(month.key(), start, start + Days::new(CWL_DAYS), at)
+}
+
+// Latest raid weekend that started at or before `at`
+pub fn raid_weekend(at: DateTime<Utc>) -> SeasonWindow {
+    let date = at.date_naive();
+    let since_friday = (date.weekday().num_days_from_monday() as u64 + 3) % 7;
+    let mut start = at_hour(date - Days::new(since_friday), RAID_START_HOUR);
+    if start > at {
+        start = start - Days::new(7);
+    }
+    window(
+        start.format("%Y-%m-%d").to_string(),
+        start,
+        start + Days::new(RAID_DAYS),
+        at,
+    )
+}
+
+// Latest clan games that started at or before `at`
+pub fn clan_games(at: DateTime<Utc>) -> SeasonWindow {
+    let mut month = Month::of(at);
+    if at < at_hour(month.day(CLAN_GAMES_START_DAY), CLAN_GAMES_HOUR) {
+        month = month.prev();
+    }
+    window(
+        month.key(),
+        at_hour(month.day(CLAN_GAMES_START_DAY), CLAN_GAMES_HOUR),
+        at_hour(month.day(CLAN_GAMES_END_DAY), CLAN_GAMES_HOUR),
+        at,
+    )
+}
+
+// Month keys ("2026-10") used by season-keyed tables and endpoints
+pub fn is_season_key(key: &str) -> bool {
+    key.len() == 7 && NaiveDate::parse_from_str(&format!("{}-01", key), "%Y-%m-%d").is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn windows_switch_at_in_game_boundaries() {
+        // Last Monday of October 2026 is the 26th
+        assert_eq!(coc_season(utc("2026-10-26T04:59:59Z")).key, "2026-10");
+        assert_eq!(coc_season(utc("2026-10-26T05:00:00Z")).key, "2026-11");
+        assert_eq!(
+            coc_season(utc("2026-10-30T00:00:00Z")).end,
+            utc("2026-11-30T05:00:00Z").timestamp()
+        );
+        // Season ending in January starts in December of the year before
+        assert_eq!(
+            coc_season(utc("2027-01-10T00:00:00Z")).start,
+            utc("2026-12-28T05:00:00Z").timestamp()
+        );
+
+        // First Monday of November 2026 is the 2nd
+        assert_eq!(cr_season(utc("2026-11-02T08:00:00Z")).key, "2026-10");
+        assert_eq!(cr_season(utc("2026-11-02T09:00:00Z")).key, "2026-11");
+
+        let before_cwl = cwl(utc("2026-11-01T07:00:00Z"));
+        assert_eq!(
+            (before_cwl.key.as_str(), before_cwl.active),
+            ("2026-10", false)
+        );
+        assert!(cwl(utc("2026-11-05T00:00:00Z")).active);
+
+        // 2026-10-18 is a Sunday, 2026-10-19 a Monday
+        let raid = raid_weekend(utc("2026-10-18T12:00:00Z"));
+        assert_eq!((raid.key.as_str(), raid.active), ("2026-10-16", true));
+        assert!(!raid_weekend(utc("2026-10-19T07:00:00Z")).active);
+        assert_eq!(raid_weekend(utc("2026-10-16T06:00:00Z")).key, "2026-10-09");
+
+        assert_eq!(clan_games(utc("2026-10-21T00:00:00Z")).key, "2026-09");
+        assert!(clan_games(utc("2026-10-22T08:00:00Z")).active);
+
+        assert!(is_season_key("2026-10"));
+        assert!(!is_season_key("2026-13") && !is_season_key("2026-1"));
+    }
+}
@@ -0,0 +1,91 @@
+use actix_web::body::{BoxBody, MessageBody, to_bytes};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+// Adds an ETag (hash of the final response body) to successful GET responses and
+// answers matching If-None-Match / If-Modified-Since requests with 304 Not Modified.
+// The hash covers the filtered body, so users with different roles get different tags.
+pub async fn conditional_get(
+    req: ServiceRequest,
+    next: Next<impl MessageBody<Error: Into<Box<dyn std::error::Error>>> + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let is_get = req.method() == Method::GET;
+    let if_none_match = header_str(req.headers().get(header::IF_NONE_MATCH));
+    let if_modified_since = header_str(req.headers().get(header::IF_MODIFIED_SINCE));
+
+    let res = next.call(req).await?;
+    if !is_get || res.status() != StatusCode::OK {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (mut head, body) = res.into_parts();
+    let body = to_bytes(body).await.map_err(|e| {
+        let e: Box<dyn std::error::Error> = e.into();
+        actix_web::error::ErrorInternalServerError(e.to_string())
+    })?;
+
+    let digest = Sha256::digest(&body);
+    let etag = format!(
+        "\"{}\"",
+        digest[..16]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    );
+
+    let headers = head.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, value);
+    }
+    // Let browsers store the response but always revalidate it
+    if !headers.contains_key(header::CACHE_CONTROL) {
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("private, no-cache"),
+        );
+    }
+
+    // If-None-Match takes precedence over If-Modified-Since (RFC 9110)
+    let not_modified = match (if_none_match, if_modified_since) {
+        (Some(inm), _) => inm
+            .split(',')
+            .map(|t| t.trim().trim_start_matches("W/"))
+            .any(|t| t == "*" || t == etag),
+        (None, Some(ims)) => {
+            let last_modified = header_str(head.headers().get(header::LAST_MODIFIED));
+            match (
+                last_modified.and_then(|lm| header::HttpDate::from_str(&lm).ok()),
+                header::HttpDate::from_str(&ims).ok(),
+            ) {
+                (Some(lm), Some(ims)) => lm <= ims,
+                _ => false,
+            }
+        }
+        (None, None) => false,
+    };
+
+    if not_modified {
+        let mut response = HttpResponse::NotModified().finish();
+        for name in [header::ETAG, header::LAST_MODIFIED, header::CACHE_CONTROL] {
+            if let Some(value) = head.headers().get(&name) {
+                response.headers_mut().insert(name, value.clone());
+            }
+        }
+        return Ok(ServiceResponse::new(req, response));
+    }
+
+    Ok(ServiceResponse::new(
+        req,
+        head.set_body(body).map_into_boxed_body(),
+    ))
+}
+
+fn header_str(value: Option<&HeaderValue>) -> Option<String> {
+    value.and_then(|v| v.to_str().ok()).map(|s| s.to_string())
+}
@@ -0,0 +1,245 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use log::{error, warn};
+use serde::Serialize;
+use std::fmt;
+use utoipa::ToSchema;
+
+// Errors returned by API handlers. Every variant maps to an HTTP status and a stable,
+// machine-readable `code`; `error` keeps the human-readable message for the frontend.
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    InvalidTag(String),
+    // Missing or invalid auth cookie
+    Unauthorized(&'static str),
+    // Holds the required role, e.g. "COLEADER"
+    Forbidden(&'static str),
+    NotFound(String),
+    Conflict(String),
+    // No cache entry yet (the background refresh has not run)
+    NotYetCached,
+    // Supercell or a bot API failed or timed out
+    UpstreamUnavailable(String),
+    // Supercell or a bot API answered 429
+    RateLimited(String),
+    Database(String),
+    Internal(String),
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiErrorBody {
+    pub error: String,
+    // e.g. "not_found", "invalid_tag", "not_yet_cached", "upstream_unavailable"
+    pub code: &'static str,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::InvalidTag(_) => "invalid_tag",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::NotYetCached => "not_yet_cached",
+            ApiError::UpstreamUnavailable(_) => "upstream_unavailable",
+            ApiError::RateLimited(_) => "rate_limited",
+            ApiError::Database(_) => "database_error",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    // Map a non-200 answer of Supercell or a bot API. `msg` names internal hosts and paths
+    // and is only kept where it is not shown to clients (the callers log it). A 400 means
+    // our request was wrong, not the client's.
+    pub fn from_upstream_status(status: u16, msg: String) -> Self {
+        match status {
+            404 => ApiError::NotFound("Not found".to_string()),
+            429 => ApiError::RateLimited(msg),
+            _ => ApiError::UpstreamUnavailable(msg),
+        }
+    }
+
+    // Message shown to clients. Internal details are only logged.
+    pub fn public_message(&self) -> String {
+        match self {
+            ApiError::Forbidden(role) => format!("Access denied: Requires {} role", role),
+            ApiError::NotYetCached => {
+                "Data not yet available in cache. Background refresh is in progress.".to_string()
+            }
+            // Transport errors and timeouts name internal hosts and cache keys
+            ApiError::UpstreamUnavailable(_) => "Upstream service unavailable".to_string(),
+            ApiError::RateLimited(_) => "Upstream service rate limited, retry later".to_string(),
+            ApiError::Database(_) => "Internal Database Error".to_string(),
+            ApiError::Internal(_) => "Internal Server Error".to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(msg)
+            | ApiError::NotFound(msg)
+            | ApiError::Conflict(msg)
+            | ApiError::UpstreamUnavailable(msg)
+            | ApiError::RateLimited(msg)
+            | ApiError::Database(msg)
+            | ApiError::Internal(msg) => write!(f, "{}", msg),
+            ApiError::InvalidTag(tag) => write!(f, "Invalid tag '{}'", tag),
+            ApiError::Unauthorized(msg) => write!(f, "{}", msg),
+            ApiError::Forbidden(role) => write!(f, "Requires {} role", role),
+            ApiError::NotYetCached => write!(f, "Not yet cached"),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) | ApiError::InvalidTag(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::NotYetCached => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::UpstreamUnavailable(_) => StatusCode::BAD_GATEWAY,
+            ApiError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Database(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ApiError::Database(msg) | ApiError::Internal(msg) => {
+                error!("{}: {}", self.code(), msg)
+            }
+            ApiError::UpstreamUnavailable(msg) | ApiError::RateLimited(msg) => {
+                warn!("{}: {}", self.code(), msg)
+            }
+            _ => {}
+        }
+        HttpResponse::build(self.status_code()).json(ApiErrorBody {
+            error: self.public_message(),
+            code: self.code(),
+        })
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        ApiError::Database(e.to_string())
+    }
+}
+
+// Role check used at the top of protected handlers
+pub fn require_role(user_role: Option<&str>, role: &'static str) -> Result<(), ApiError> {
+    if crate::auth::has_required_role(user_role, role) {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden(role))
+    }
+}
+
+// Tags may be given with or without '#'. Non-clan entries of the bots (like
+// "warteliste") are alphanumeric as well, so only the character set is checked.
+pub fn validate_tag(tag: &str) -> Result<(), ApiError> {
+    let bare = tag.trim_start_matches('#');
+    if bare.is_empty() || bare.len() > 20 || !bare.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(ApiError::InvalidTag(tag.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_map_to_status_code_and_public_message() {
+        let cases = [
+            (
+                ApiError::BadRequest("Bad".into()),
+                400,
+                "bad_request",
+                "Bad",
+            ),
+            (
+                ApiError::InvalidTag("x!".into()),
+                400,
+                "invalid_tag",
+                "Invalid tag 'x!'",
+            ),
+            (
+                ApiError::Unauthorized("Not logged in"),
+                401,
+                "unauthorized",
+                "Not logged in",
+            ),
+            (
+                ApiError::Forbidden("ADMIN"),
+                403,
+                "forbidden",
+                "Access denied: Requires ADMIN role",
+            ),
+            (ApiError::NotFound("Gone".into()), 404, "not_found", "Gone"),
+            (ApiError::Conflict("Taken".into()), 409, "conflict", "Taken"),
+            (
+                ApiError::NotYetCached,
+                503,
+                "not_yet_cached",
+                "Data not yet available in cache. Background refresh is in progress.",
+            ),
+            (
+                ApiError::UpstreamUnavailable(
+                    "error sending request for url (http://coc-bot:8070/api/players/%23P1)".into(),
+                ),
+                502,
+                "upstream_unavailable",
+                "Upstream service unavailable",
+            ),
+            (
+                ApiError::RateLimited("coc:supercell:/players/%23P1".into()),
+                429,
+                "rate_limited",
+                "Upstream service rate limited, retry later",
+            ),
+            (
+                ApiError::Database("relation x".into()),
+                500,
+                "database_error",
+                "Internal Database Error",
+            ),
+            (
+                ApiError::Internal("panic".into()),
+                500,
+                "internal_error",
+                "Internal Server Error",
+            ),
+        ];
+        for (err, status, code, message) in cases {
+            assert_eq!(err.status_code().as_u16(), status);
+            assert_eq!(err.code(), code);
+            assert_eq!(err.public_message(), message);
+        }
+    }
+
+    #[test]
+    fn upstream_status_does_not_leak_the_message() {
+        let msg = || "Upstream CoC bot/api/players/%23P1 returned status 404".to_string();
+        let cases = [
+            (400, 502, "Upstream service unavailable"),
+            (404, 404, "Not found"),
+            (429, 429, "Upstream service rate limited, retry later"),
+            (500, 502, "Upstream service unavailable"),
+        ];
+        for (upstream, status, message) in cases {
+            let err = ApiError::from_upstream_status(upstream, msg());
+            assert_eq!(err.status_code().as_u16(), status);
+            assert_eq!(err.public_message(), message);
+        }
+    }
+}
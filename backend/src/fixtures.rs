@@ -0,0 +1,288 @@
+use crate::models::AppState;
+
+use bytes::Bytes;
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+
+// How outbound requests to Supercell, the bots and Discord are handled.
+// Set via `external.mode` / EXTERNAL_API_MODE (live, record or replay) and FIXTURES_DIR.
+// Latency probes and the OAuth token exchange always go to the network in live and
+// record mode; replay mode skips both. Replay logs in every OAuth code as the recorded
+// users/@me user and accepts every verify token, so release builds refuse it unless
+// `external.allow_insecure_replay` is set.
+#[derive(Debug, Clone)]
+pub enum FixtureMode {
+    Live,
+    // Forward requests and save every response as a fixture file
+    Record(PathBuf),
+    // Serve fixture files only, never touch the network
+    Replay(PathBuf),
+}
+
+impl FixtureMode {
+    // The mode was validated with the config
+    pub fn from_config(config: &crate::config::ExternalConfig) -> Self {
+        let dir = PathBuf::from(&config.fixtures_dir);
+        match config.mode.as_str() {
+            "record" => FixtureMode::Record(dir),
+            "replay" => FixtureMode::Replay(dir),
+            _ => FixtureMode::Live,
+        }
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self, FixtureMode::Replay(_))
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            FixtureMode::Live => "live".to_string(),
+            FixtureMode::Record(dir) => format!("record ({})", dir.display()),
+            FixtureMode::Replay(dir) => format!("replay ({})", dir.display()),
+        }
+    }
+}
+
+// External APIs. Fixtures are stored per API name, so recordings stay valid
+// when the configured base URLs change.
+#[derive(Debug, Clone, Copy)]
+pub enum ExternalApi {
+    SupercellCoc,
+    SupercellCr,
+    UpstreamCoc,
+    UpstreamCr,
+    Discord,
+}
+
+impl ExternalApi {
+    pub fn name(self) -> &'static str {
+        match self {
+            ExternalApi::SupercellCoc => "supercell_coc",
+            ExternalApi::SupercellCr => "supercell_cr",
+            ExternalApi::UpstreamCoc => "upstream_coc",
+            ExternalApi::UpstreamCr => "upstream_cr",
+            ExternalApi::Discord => "discord",
+        }
+    }
+
+    fn base_url(self, data: &AppState) -> &str {
+        match self {
+            ExternalApi::SupercellCoc => &data.supercell_coc_url,
+            ExternalApi::SupercellCr => &data.supercell_cr_url,
+            ExternalApi::UpstreamCoc => &data.upstream_coc_url,
+            ExternalApi::UpstreamCr => &data.upstream_cr_url,
+            ExternalApi::Discord => &data.discord_api_url,
+        }
+    }
+}
+
+pub struct ExternalResponse {
+    pub status: u16,
+    pub body: Bytes,
+}
+
+impl ExternalResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Fixture {
+    status: u16,
+    // JSON bodies are stored as JSON so fixtures stay readable and editable
+    body: serde_json::Value,
+}
+
+// GET `url_path` (relative to the API's base URL) with a bearer token
+pub async fn get(
+    data: &AppState,
+    api: ExternalApi,
+    url_path: &str,
+    token: &str,
+) -> Result<ExternalResponse, String> {
+    send(data, api, reqwest::Method::GET, url_path, token, None).await
+}
+
+// POST a JSON body. Fixtures are keyed by path only, so replay returns the same
+// response for every body.
+pub async fn post(
+    data: &AppState,
+    api: ExternalApi,
+    url_path: &str,
+    token: &str,
+    body: &serde_json::Value,
+) -> Result<ExternalResponse, String> {
+    send(
+        data,
+        api,
+        reqwest::Method::POST,
+        url_path,
+        token,
+        Some(body),
+    )
+    .await
+}
+
+// PATCH a JSON body, e.g. editing a Discord interaction response. Endpoints that
+// carry their token in the path are called with an empty `token`.
+pub async fn patch(
+    data: &AppState,
+    api: ExternalApi,
+    url_path: &str,
+    token: &str,
+    body: &serde_json::Value,
+) -> Result<ExternalResponse, String> {
+    send(
+        data,
+        api,
+        reqwest::Method::PATCH,
+        url_path,
+        token,
+        Some(body),
+    )
+    .await
+}
+
+async fn send(
+    data: &AppState,
+    api: ExternalApi,
+    method: reqwest::Method,
+    url_path: &str,
+    token: &str,
+    json: Option<&serde_json::Value>,
+) -> Result<ExternalResponse, String> {
+    match &data.fixtures {
+        FixtureMode::Replay(dir) => replay(dir, api, url_path).await,
+        mode => {
+            let url = crate::utils::format_url(api.base_url(data), url_path);
+            let mut request = data.client.request(method, &url);
+            if let Some(json) = json {
+                request = request
+                    .header("Content-Type", "application/json")
+                    .body(json.to_string());
+            }
+            if !token.is_empty() {
+                request = request.header("Authorization", format!("Bearer {}", token));
+            }
+            let res = request.send().await.map_err(|e| e.to_string())?;
+            let status = res.status().as_u16();
+            let body = res.bytes().await.map_err(|e| e.to_string())?;
+
+            if let FixtureMode::Record(dir) = mode {
+                record(dir, api, url_path, status, &body).await;
+            }
+            Ok(ExternalResponse { status, body })
+        }
+    }
+}
+
+async fn replay(dir: &Path, api: ExternalApi, url_path: &str) -> Result<ExternalResponse, String> {
+    let path = fixture_path(dir, api, url_path);
+    let content = tokio::fs::read(&path).await.map_err(|_| {
+        format!(
+            "No fixture for {}{} ({})",
+            api.name(),
+            url_path,
+            path.display()
+        )
+    })?;
+    let fixture: Fixture = serde_json::from_slice(&content)
+        .map_err(|e| format!("Invalid fixture {}: {}", path.display(), e))?;
+
+    let body = match fixture.body {
+        serde_json::Value::String(text) => Bytes::from(text),
+        json => Bytes::from(serde_json::to_vec(&json).unwrap_or_default()),
+    };
+    debug!(
+        "Replayed {}{} from {}",
+        api.name(),
+        url_path,
+        path.display()
+    );
+    Ok(ExternalResponse {
+        status: fixture.status,
+        body,
+    })
+}
+
+async fn record(dir: &Path, api: ExternalApi, url_path: &str, status: u16, body: &[u8]) {
+    let path = fixture_path(dir, api, url_path);
+    let fixture = Fixture {
+        status,
+        body: serde_json::from_slice(body).unwrap_or_else(|_| {
+            serde_json::Value::String(String::from_utf8_lossy(body).into_owned())
+        }),
+    };
+
+    if let Some(parent) = path.parent()
+        && let Err(e) = tokio::fs::create_dir_all(parent).await
+    {
+        warn!("Failed to create fixture dir {}: {}", parent.display(), e);
+        return;
+    }
+    let content = serde_json::to_vec_pretty(&fixture).unwrap_or_default();
+    match tokio::fs::write(&path, content).await {
+        Ok(()) => info!("Recorded {}{} to {}", api.name(), url_path, path.display()),
+        Err(e) => warn!("Failed to write fixture {}: {}", path.display(), e),
+    }
+}
+
+// e.g. supercell_coc + "/clans/%232PP/members?limit=5" -> {dir}/supercell_coc/clans/%232PP/members_limit=5.json
+fn fixture_path(dir: &Path, api: ExternalApi, url_path: &str) -> PathBuf {
+    let mut path = dir.join(api.name());
+    let segments: Vec<String> = url_path
+        .trim_start_matches('/')
+        .replace('?', "_")
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+        .map(|s| {
+            s.chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || "%-_.=@".contains(c) {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    match segments.split_last() {
+        Some((last, parents)) => {
+            for segment in parents {
+                path.push(segment);
+            }
+            path.push(format!("{}.json", last));
+        }
+        None => path.push("index.json"),
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixture_paths_stay_inside_the_fixture_dir() {
+        let dir = Path::new("fixtures");
+        assert_eq!(
+            fixture_path(
+                dir,
+                ExternalApi::SupercellCoc,
+                "/clans/%232PP/members?limit=5"
+            ),
+            PathBuf::from("fixtures/supercell_coc/clans/%232PP/members_limit=5.json")
+        );
+        assert_eq!(
+            fixture_path(dir, ExternalApi::UpstreamCoc, "/api/../../etc/passwd"),
+            PathBuf::from("fixtures/upstream_coc/api/etc/passwd.json")
+        );
+        assert_eq!(
+            fixture_path(dir, ExternalApi::Discord, "/"),
+            PathBuf::from("fixtures/discord/index.json")
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use actix_web::Error;
+use actix_web::body::{BoxBody, MessageBody, to_bytes};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use std::cell::RefCell;
+
+// Freshness of the cached data a response was built from. Cache reads record into a
+// task-local collector that `track_freshness` sets up for every request; the middleware
+// then reports it as X-Data-* headers or, with `?envelope=true`, wraps the JSON body.
+
+#[derive(Clone, Copy)]
+struct SourceFreshness {
+    // Oldest entry of this source that went into the response
+    updated_at: i64,
+    stale: bool,
+}
+
+#[derive(Default)]
+struct Freshness {
+    supercell: Option<SourceFreshness>,
+    upstream: Option<SourceFreshness>,
+}
+
+tokio::task_local! {
+    static FRESHNESS: RefCell<Freshness>;
+}
+
+pub const SOURCE_HEADER: &str = "x-data-source";
+pub const UPDATED_AT_HEADER: &str = "x-data-updated-at";
+pub const STALE_HEADER: &str = "x-data-stale";
+
+// Record a cache read. `source` is "supercell" or "upstream"; outside of a request
+// (e.g. in background jobs) this is a no-op.
+pub fn record(source: &str, updated_at: i64, stale: bool) {
+    let _ = FRESHNESS.try_with(|f| {
+        let mut f = f.borrow_mut();
+        let slot = match source {
+            "supercell" => &mut f.supercell,
+            "upstream" => &mut f.upstream,
+            _ => return,
+        };
+        *slot = Some(match *slot {
+            Some(prev) => SourceFreshness {
+                updated_at: prev.updated_at.min(updated_at),
+                stale: prev.stale || stale,
+            },
+            None => SourceFreshness { updated_at, stale },
+        });
+    });
+}
+
+impl Freshness {
+    fn source(&self) -> Option<&'static str> {
+        match (self.supercell, self.upstream) {
+            (Some(_), Some(_)) => Some("merged"),
+            (Some(_), None) => Some("supercell"),
+            (None, Some(_)) => Some("upstream"),
+            (None, None) => None,
+        }
+    }
+
+    fn stale(&self) -> bool {
+        [self.supercell, self.upstream]
+            .iter()
+            .flatten()
+            .any(|s| s.stale)
+    }
+
+    fn sources(&self) -> Vec<(&'static str, SourceFreshness)> {
+        [("supercell", self.supercell), ("upstream", self.upstream)]
+            .into_iter()
+            .filter_map(|(name, s)| s.map(|s| (name, s)))
+            .collect()
+    }
+}
+
+fn wants_envelope(req: &ServiceRequest) -> bool {
+    req.query_string()
+        .split('&')
+        .any(|pair| pair == "envelope=true" || pair == "envelope=1")
+}
+
+pub async fn track_freshness(
+    req: ServiceRequest,
+    next: Next<impl MessageBody<Error: Into<Box<dyn std::error::Error>>> + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let envelope = wants_envelope(&req);
+    let (res, freshness) = FRESHNESS
+        .scope(RefCell::new(Freshness::default()), async move {
+            let res = next.call(req).await;
+            (res, FRESHNESS.with(|f| f.take()))
+        })
+        .await;
+    let mut res = res?;
+
+    let Some(source) = freshness.source() else {
+        return Ok(res.map_into_boxed_body());
+    };
+
+    let updated_at = freshness
+        .sources()
+        .iter()
+        .map(|(name, s)| format!("{}={}", name, s.updated_at))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let headers = res.headers_mut();
+    for (name, value) in [
+        (SOURCE_HEADER, source.to_string()),
+        (UPDATED_AT_HEADER, updated_at),
+        (STALE_HEADER, freshness.stale().to_string()),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(HeaderName::from_static(name), value);
+        }
+    }
+
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"));
+    if !envelope || res.status() != StatusCode::OK || !is_json {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (mut head, body) = res.into_parts();
+    let body = to_bytes(body).await.map_err(|e| {
+        let e: Box<dyn std::error::Error> = e.into();
+        actix_web::error::ErrorInternalServerError(e.to_string())
+    })?;
+    let data: serde_json::Value = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+
+    let updated_at: serde_json::Map<String, serde_json::Value> = freshness
+        .sources()
+        .into_iter()
+        .map(|(name, s)| (name.to_string(), serde_json::json!(s.updated_at)))
+        .collect();
+    let wrapped = serde_json::json!({
+        "data": data,
+        "meta": {
+            "source": source,
+            "updated_at": updated_at,
+            "stale": freshness.stale(),
+        }
+    });
+
+    head.headers_mut().remove(header::CONTENT_LENGTH);
+    let body = serde_json::to_vec(&wrapped).unwrap_or_default();
+    Ok(ServiceResponse::new(
+        req,
+        head.set_body(body).map_into_boxed_body(),
+    ))
+}
@@ -1,20 +1,39 @@
 use crate::auth::{AuthenticatedUser, OptionalAuthenticatedUser, has_required_role};
-use crate::models::{AppState, ErrorResponse, GameType};
+use crate::errors::{ApiError, require_role, validate_tag};
+use crate::models::{
+    AppState, BadgeUrls, BotMember, BotUser, ClanInfo, FamilyClan, GameType, Kickpoint,
+    MergedMember,
+};
 use crate::utils::{
-    encode_tag, filter_member_data, forward_request, forward_request_with_filter,
-    get_cached_or_update_supercell_cache, get_cached_or_update_upstream_cache,
-    update_upstream_cache,
+    CacheClass, decode_cache_body, encode_tag, filter_member_data, forward_request,
+    forward_request_with_filter, get_cache_body, get_cache_body_dated,
+    get_cached_or_update_supercell_cache, get_cached_or_update_supercell_cache_dated,
+    get_cached_or_update_upstream_cache, get_cached_or_update_upstream_cache_dated, last_modified,
+    normalize_tag, record_cache_read, update_upstream_cache,
 };
+use actix_web::http::header::LastModified;
 use actix_web::{HttpResponse, Responder, web};
 use bytes::Bytes;
 use futures_util::future::join_all;
-use log::error;
+use log::{error, warn};
 
 // ============================================================================
 // CLASH OF CLANS HANDLERS
 // ============================================================================
 
 // 1. Get All CoC Clans
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans",
+    tag = "coc",
+    description = "Family clans from the bot. Kickpoint settings require MEMBER.",
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Family clans", body = [crate::models::FamilyClan]),
+        (status = 503, description = "Not yet cached", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_clans(
     data: web::Data<AppState>,
     opt_user: OptionalAuthenticatedUser,
@@ -23,10 +42,32 @@ pub async fn get_coc_clans(
         .user
         .as_ref()
         .and_then(|u| u.claims.role.as_deref());
-    forward_request_with_filter(&data, GameType::ClashOfClans, "/api/clans", user_role, &[]).await
+    forward_request_with_filter::<Vec<FamilyClan>>(
+        &data,
+        GameType::ClashOfClans,
+        "/api/clans",
+        user_role,
+        &[],
+    )
+    .await
 }
 
 // 2. Get CoC Clan Info
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}",
+    tag = "coc",
+    description = "Supercell clan merged with the bot's clan settings.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Clan", body = crate::models::ClanInfo),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell or bot API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_clan_info(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -36,6 +77,21 @@ pub async fn get_coc_clan_info(
 }
 
 // 2b. Get CoC Clan Config
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/config",
+    tag = "coc",
+    description = "Kickpoint settings of a clan.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("MEMBER"))),
+    responses(
+        (status = 200, description = "Clan config", body = crate::models::ClanConfig),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_clan_config(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -45,6 +101,19 @@ pub async fn get_coc_clan_config(
 }
 
 // 3. Get CoC Clan Members
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/members",
+    tag = "coc",
+    description = "Supercell member list merged with the bot's members, including members that left. Private fields require MEMBER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Members", body = [crate::models::MergedMember]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_clan_members(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -54,6 +123,20 @@ pub async fn get_coc_clan_members(
 }
 
 // 3b. Get CoC Clan Members Lite (No Supercell API data)
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/members-lite",
+    tag = "coc",
+    description = "Bot member list without Supercell data.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Members", body = [crate::models::BotMember]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 503, description = "Not yet cached", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_clan_members_lite(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -63,6 +146,22 @@ pub async fn get_coc_clan_members_lite(
 }
 
 // 4. Get CoC Clan Kickpoint Reasons
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/kickpoint-reasons",
+    tag = "coc",
+    description = "Kickpoint reasons configured in the bot.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("COLEADER"))),
+    responses(
+        (status = 200, description = "Kickpoint reasons", body = [crate::models::KickpointReason]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 503, description = "Not yet cached", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_clan_kickpoint_reasons(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -71,12 +170,56 @@ pub async fn get_coc_clan_kickpoint_reasons(
     get_clan_kickpoint_reasons_impl(&data, &tag, user, GameType::ClashOfClans).await
 }
 
+// 4b. Get CoC Clan Inactivity Report
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/inactivity",
+    tag = "coc",
+    description = "Last activity of every member, inferred from changes between player profile fetches.",
+    params(
+        ("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)"),
+        InactivityQuery,
+    ),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("COLEADER"))),
+    responses(
+        (status = 200, description = "Members, most inactive first", body = crate::models::InactivityReport),
+        (status = 400, description = "Invalid tag or thresholds", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_coc_clan_inactivity(
+    data: web::Data<AppState>,
+    tag: web::Path<String>,
+    query: web::Query<InactivityQuery>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    get_clan_inactivity_impl(&data, &tag, &query, user, GameType::ClashOfClans).await
+}
+
 // 5. Get CoC Clan War Members
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/war-members",
+    tag = "coc",
+    description = "War members from the bot. Private fields require MEMBER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "War members", body = [crate::models::BotMember]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 503, description = "Not yet cached", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_clan_war_members(
     data: web::Data<AppState>,
     tag: web::Path<String>,
     opt_user: OptionalAuthenticatedUser,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(&tag)?;
     let encoded_tag = encode_tag(&tag);
     let user_role = opt_user
         .user
@@ -87,7 +230,7 @@ pub async fn get_coc_clan_war_members(
         .as_ref()
         .map(|u| u.linked_players.as_slice())
         .unwrap_or(&[]);
-    forward_request_with_filter(
+    forward_request_with_filter::<Vec<BotMember>>(
         &data,
         GameType::ClashOfClans,
         &format!("/api/clans/{}/war-members", encoded_tag),
@@ -98,11 +241,26 @@ pub async fn get_coc_clan_war_members(
 }
 
 // 6. Get CoC Raid Members
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/raid-members",
+    tag = "coc",
+    description = "Raid members from the bot. Private fields require MEMBER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Raid members", body = [crate::models::BotMember]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 503, description = "Not yet cached", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_raid_members(
     data: web::Data<AppState>,
     tag: web::Path<String>,
     opt_user: OptionalAuthenticatedUser,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(&tag)?;
     let encoded_tag = encode_tag(&tag);
     let user_role = opt_user
         .user
@@ -113,7 +271,7 @@ pub async fn get_coc_raid_members(
         .as_ref()
         .map(|u| u.linked_players.as_slice())
         .unwrap_or(&[]);
-    forward_request_with_filter(
+    forward_request_with_filter::<Vec<BotMember>>(
         &data,
         GameType::ClashOfClans,
         &format!("/api/clans/{}/raid-members", encoded_tag),
@@ -124,11 +282,26 @@ pub async fn get_coc_raid_members(
 }
 
 // 7. Get CoC CWL Members
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/cwl-members",
+    tag = "coc",
+    description = "CWL members from the bot. Private fields require MEMBER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "CWL members", body = [crate::models::BotMember]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 503, description = "Not yet cached", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_cwl_members(
     data: web::Data<AppState>,
     tag: web::Path<String>,
     opt_user: OptionalAuthenticatedUser,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(&tag)?;
     let encoded_tag = encode_tag(&tag);
     let user_role = opt_user
         .user
@@ -139,7 +312,7 @@ pub async fn get_coc_cwl_members(
         .as_ref()
         .map(|u| u.linked_players.as_slice())
         .unwrap_or(&[]);
-    forward_request_with_filter(
+    forward_request_with_filter::<Vec<BotMember>>(
         &data,
         GameType::ClashOfClans,
         &format!("/api/clans/{}/cwl-members", encoded_tag),
@@ -150,6 +323,21 @@ pub async fn get_coc_cwl_members(
 }
 
 // 8. Get CoC Player
+#[utoipa::path(
+    get,
+    path = "/api/coc/players/{tag}",
+    tag = "coc",
+    description = "Supercell player profile. The kickpoint summary requires MEMBER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Player", body = crate::models::Player),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell or bot API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_player(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -159,6 +347,21 @@ pub async fn get_coc_player(
 }
 
 // 8b. Get CoC Player Identity
+#[utoipa::path(
+    get,
+    path = "/api/coc/players/{tag}/identity",
+    tag = "coc",
+    description = "Discord identity of the player's owner. Discord ids and accounts require COLEADER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("MEMBER"))),
+    responses(
+        (status = 200, description = "Identity", body = crate::models::PlayerIdentity),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_player_identity(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -168,6 +371,21 @@ pub async fn get_coc_player_identity(
 }
 
 // 8c. Get CoC Player Kickpoints
+#[utoipa::path(
+    get,
+    path = "/api/coc/players/{tag}/kickpoints",
+    tag = "coc",
+    description = "Kickpoint totals of a player (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("MEMBER"))),
+    responses(
+        (status = 200, description = "Kickpoint summary", body = crate::models::KickpointSummary),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_player_kickpoints(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -177,6 +395,21 @@ pub async fn get_coc_player_kickpoints(
 }
 
 // 8d. Get CoC Player Kickpoints Details
+#[utoipa::path(
+    get,
+    path = "/api/coc/players/{tag}/kickpoints/details",
+    tag = "coc",
+    description = "Active kickpoints. Reasons and descriptions require COLEADER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("MEMBER"))),
+    responses(
+        (status = 200, description = "Active kickpoints", body = [crate::models::Kickpoint]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_coc_player_kickpoints_details(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -185,11 +418,264 @@ pub async fn get_coc_player_kickpoints_details(
     get_player_kickpoints_details_impl(&data, &tag, opt_user, GameType::ClashOfClans).await
 }
 
+// 9. Get CoC Family Leaderboard
+#[utoipa::path(
+    get,
+    path = "/api/coc/leaderboards/{metric}",
+    tag = "coc",
+    description = "Ranks every member of all family clans (incl. side clans) from cached data.",
+    params(
+        ("metric" = String, Path, description = "trophies, war_stars, donations, hero_levels or upgrade_progress"),
+        LeaderboardQuery,
+    ),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Members, best first (town hall in `level`)", body = [crate::models::LeaderboardEntry]),
+        (status = 400, description = "Unknown metric or invalid filter", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_coc_leaderboard(
+    data: web::Data<AppState>,
+    metric: web::Path<String>,
+    query: web::Query<LeaderboardQuery>,
+) -> impl Responder {
+    get_leaderboard_impl(&data, &metric, &query, GameType::ClashOfClans).await
+}
+
+// 10. Get CoC Clan Seasons
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/seasons",
+    tag = "coc",
+    description = "Seasons archived for a clan.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Season keys (YYYY-MM), newest first", body = [String]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_coc_clan_seasons(
+    data: web::Data<AppState>,
+    tag: web::Path<String>,
+) -> impl Responder {
+    get_clan_seasons_impl(&data, &tag, GameType::ClashOfClans).await
+}
+
+// 10b. Get CoC Clan Season Stats
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/seasons/{season}",
+    tag = "coc",
+    description = "Archived member stats of a clan for one season (the last snapshot before the reset).",
+    params(
+        ("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)"),
+        ("season" = String, Path, description = "Season key, e.g. 2026-10"),
+    ),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Members, most donations first", body = [crate::models::SeasonStats]),
+        (status = 400, description = "Invalid tag or season", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Season not archived", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_coc_clan_season(
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (tag, season) = path.into_inner();
+    get_clan_season_impl(&data, &tag, &season, GameType::ClashOfClans).await
+}
+
+// 10c. Get CoC Player Seasons
+#[utoipa::path(
+    get,
+    path = "/api/coc/players/{tag}/seasons",
+    tag = "coc",
+    description = "All archived seasons of a player, for side by side comparison.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Seasons, newest first (one entry per clan the player was in)", body = [crate::models::SeasonStats]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_coc_player_seasons(
+    data: web::Data<AppState>,
+    tag: web::Path<String>,
+) -> impl Responder {
+    get_player_seasons_impl(&data, &tag, GameType::ClashOfClans).await
+}
+
+// 11. Get CoC Clan Requirements
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/requirements",
+    tag = "coc",
+    description = "Entry requirements of a family clan.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Requirements", body = crate::models::ClanRequirements),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "No requirements set", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_coc_clan_requirements(
+    data: web::Data<AppState>,
+    tag: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(&tag)?;
+    match crate::requirements::get_requirements(&data, &normalize_tag(&tag)).await? {
+        Some(requirements) => Ok(HttpResponse::Ok().json(requirements)),
+        None => Err(ApiError::NotFound("No requirements set".to_string())),
+    }
+}
+
+// 11b. Set CoC Clan Requirements
+#[utoipa::path(
+    put,
+    path = "/api/coc/clans/{tag}/requirements",
+    tag = "coc",
+    description = "Replaces the entry requirements of a family clan. Unset rules are not checked.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    request_body = crate::models::ClanRequirementsUpdate,
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("LEADER"))),
+    responses(
+        (status = 200, description = "Saved requirements", body = crate::models::ClanRequirements),
+        (status = 400, description = "Invalid tag or values", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not a family clan", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn put_coc_clan_requirements(
+    data: web::Data<AppState>,
+    tag: web::Path<String>,
+    body: web::Json<crate::models::ClanRequirementsUpdate>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "LEADER")?;
+    validate_tag(&tag)?;
+
+    let minimums = [
+        body.min_town_hall,
+        body.min_hero_levels,
+        body.min_trophies,
+        body.min_war_stars,
+    ];
+    if minimums.iter().flatten().any(|v| *v < 0)
+        || body
+            .max_rushed_percent
+            .is_some_and(|p| !(0.0..=100.0).contains(&p))
+    {
+        return Err(ApiError::BadRequest(
+            "Minimums must not be negative and max_rushed_percent must be within 0-100".to_string(),
+        ));
+    }
+
+    let clan_tag = normalize_tag(&tag);
+    let family = crate::background::family_clan_tags(&data, GameType::ClashOfClans)
+        .await
+        .map_err(ApiError::Internal)?;
+    if !family.iter().any(|t| normalize_tag(t) == clan_tag) {
+        return Err(ApiError::NotFound("Not a family clan".to_string()));
+    }
+
+    let saved =
+        crate::requirements::set_requirements(&data, &clan_tag, &body, &user.claims.sub).await?;
+    Ok(HttpResponse::Ok().json(saved))
+}
+
+// 11c. Check CoC Player Against Clan Requirements
+#[utoipa::path(
+    get,
+    path = "/api/coc/clans/{tag}/eligibility/{player_tag}",
+    tag = "coc",
+    description = "Evaluates the Supercell profile of a player against the clan's requirements.",
+    params(
+        ("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)"),
+        ("player_tag" = String, Path, description = "Player tag, with or without a leading '#' (%23)"),
+    ),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Result per rule", body = crate::models::ClanEligibility),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "No requirements set or player not found", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_coc_clan_eligibility(
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (tag, player_tag) = path.into_inner();
+    validate_tag(&tag)?;
+    let Some(requirements) =
+        crate::requirements::get_requirements(&data, &normalize_tag(&tag)).await?
+    else {
+        return Err(ApiError::NotFound("No requirements set".to_string()));
+    };
+    let profile = get_player_profile(&data, GameType::ClashOfClans, &player_tag).await?;
+
+    let rules = crate::requirements::evaluate(&requirements, &profile);
+    let (clan_name, belongs_to) = crate::requirements::family_clans(&data)
+        .await?
+        .into_iter()
+        .find(|(t, ..)| *t == requirements.clan_tag)
+        .map(|(_, name, belongs_to, _)| (name, belongs_to))
+        .unwrap_or((requirements.clan_tag.clone(), None));
+    Ok(HttpResponse::Ok().json(crate::models::ClanEligibility {
+        clan_tag: requirements.clan_tag,
+        clan_name,
+        belongs_to,
+        eligible: rules.iter().all(|r| r.passed),
+        rules,
+    }))
+}
+
+// 11d. Suggest Family Clans for a CoC Player
+#[utoipa::path(
+    get,
+    path = "/api/coc/eligibility/{player_tag}",
+    tag = "coc",
+    description = "Evaluates a player against all main and side clans, best fitting clan first.",
+    params(("player_tag" = String, Path, description = "Player tag, with or without a leading '#' (%23)")),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Clans, best fit first", body = [crate::models::ClanEligibility]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Player not found", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_coc_family_eligibility(
+    data: web::Data<AppState>,
+    player_tag: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let profile = get_player_profile(&data, GameType::ClashOfClans, &player_tag).await?;
+    let clans = crate::requirements::family_eligibility(&data, &profile).await?;
+    Ok(HttpResponse::Ok().json(clans))
+}
+
 // ============================================================================
 // CLASH ROYALE HANDLERS
 // ============================================================================
 
 // 1. Get All CR Clans
+#[utoipa::path(
+    get,
+    path = "/api/cr/clans",
+    tag = "cr",
+    description = "Family clans from the bot. Kickpoint settings require MEMBER.",
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Family clans", body = [crate::models::FamilyClan]),
+        (status = 503, description = "Not yet cached", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_cr_clans(
     data: web::Data<AppState>,
     opt_user: OptionalAuthenticatedUser,
@@ -198,10 +684,32 @@ pub async fn get_cr_clans(
         .user
         .as_ref()
         .and_then(|u| u.claims.role.as_deref());
-    forward_request_with_filter(&data, GameType::ClashRoyale, "/api/clans", user_role, &[]).await
+    forward_request_with_filter::<Vec<FamilyClan>>(
+        &data,
+        GameType::ClashRoyale,
+        "/api/clans",
+        user_role,
+        &[],
+    )
+    .await
 }
 
 // 2. Get CR Clan Info
+#[utoipa::path(
+    get,
+    path = "/api/cr/clans/{tag}",
+    tag = "cr",
+    description = "Supercell clan merged with the bot's clan settings.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Clan", body = crate::models::ClanInfo),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell or bot API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_cr_clan_info(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -211,6 +719,21 @@ pub async fn get_cr_clan_info(
 }
 
 // 2b. Get CR Clan Config
+#[utoipa::path(
+    get,
+    path = "/api/cr/clans/{tag}/config",
+    tag = "cr",
+    description = "Kickpoint settings of a clan.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("MEMBER"))),
+    responses(
+        (status = 200, description = "Clan config", body = crate::models::ClanConfig),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_cr_clan_config(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -220,6 +743,19 @@ pub async fn get_cr_clan_config(
 }
 
 // 3. Get CR Clan Members
+#[utoipa::path(
+    get,
+    path = "/api/cr/clans/{tag}/members",
+    tag = "cr",
+    description = "Supercell member list merged with the bot's members, including members that left. Private fields require MEMBER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Members", body = [crate::models::MergedMember]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_cr_clan_members(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -229,6 +765,20 @@ pub async fn get_cr_clan_members(
 }
 
 // 3b. Get CR Clan Members Lite (No Supercell API data)
+#[utoipa::path(
+    get,
+    path = "/api/cr/clans/{tag}/members-lite",
+    tag = "cr",
+    description = "Bot member list without Supercell data.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Members", body = [crate::models::BotMember]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 503, description = "Not yet cached", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_cr_clan_members_lite(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -238,6 +788,22 @@ pub async fn get_cr_clan_members_lite(
 }
 
 // 4. Get CR Clan Kickpoint Reasons
+#[utoipa::path(
+    get,
+    path = "/api/cr/clans/{tag}/kickpoint-reasons",
+    tag = "cr",
+    description = "Kickpoint reasons configured in the bot.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("COLEADER"))),
+    responses(
+        (status = 200, description = "Kickpoint reasons", body = [crate::models::KickpointReason]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 503, description = "Not yet cached", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_cr_clan_kickpoint_reasons(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -246,7 +812,51 @@ pub async fn get_cr_clan_kickpoint_reasons(
     get_clan_kickpoint_reasons_impl(&data, &tag, user, GameType::ClashRoyale).await
 }
 
+// 4b. Get CR Clan Inactivity Report
+#[utoipa::path(
+    get,
+    path = "/api/cr/clans/{tag}/inactivity",
+    tag = "cr",
+    description = "Last activity of every member, inferred from changes between player profile fetches.",
+    params(
+        ("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)"),
+        InactivityQuery,
+    ),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("COLEADER"))),
+    responses(
+        (status = 200, description = "Members, most inactive first", body = crate::models::InactivityReport),
+        (status = 400, description = "Invalid tag or thresholds", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_cr_clan_inactivity(
+    data: web::Data<AppState>,
+    tag: web::Path<String>,
+    query: web::Query<InactivityQuery>,
+    user: AuthenticatedUser,
+) -> impl Responder {
+    get_clan_inactivity_impl(&data, &tag, &query, user, GameType::ClashRoyale).await
+}
+
 // 5. Get CR Player
+#[utoipa::path(
+    get,
+    path = "/api/cr/players/{tag}",
+    tag = "cr",
+    description = "Supercell player profile. The kickpoint summary requires MEMBER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Player", body = crate::models::Player),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell or bot API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_cr_player(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -256,6 +866,21 @@ pub async fn get_cr_player(
 }
 
 // 5b. Get CR Player Identity
+#[utoipa::path(
+    get,
+    path = "/api/cr/players/{tag}/identity",
+    tag = "cr",
+    description = "Discord identity of the player's owner. Discord ids and accounts require COLEADER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("MEMBER"))),
+    responses(
+        (status = 200, description = "Identity", body = crate::models::PlayerIdentity),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_cr_player_identity(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -265,6 +890,21 @@ pub async fn get_cr_player_identity(
 }
 
 // 5c. Get CR Player Kickpoints
+#[utoipa::path(
+    get,
+    path = "/api/cr/players/{tag}/kickpoints",
+    tag = "cr",
+    description = "Kickpoint totals of a player (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("MEMBER"))),
+    responses(
+        (status = 200, description = "Kickpoint summary", body = crate::models::KickpointSummary),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_cr_player_kickpoints(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -274,6 +914,21 @@ pub async fn get_cr_player_kickpoints(
 }
 
 // 5d. Get CR Player Kickpoints Details
+#[utoipa::path(
+    get,
+    path = "/api/cr/players/{tag}/kickpoints/details",
+    tag = "cr",
+    description = "Active kickpoints. Reasons and descriptions require COLEADER (or the own account).",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("MEMBER"))),
+    responses(
+        (status = 200, description = "Active kickpoints", body = [crate::models::Kickpoint]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_cr_player_kickpoints_details(
     data: web::Data<AppState>,
     tag: web::Path<String>,
@@ -282,10 +937,207 @@ pub async fn get_cr_player_kickpoints_details(
     get_player_kickpoints_details_impl(&data, &tag, opt_user, GameType::ClashRoyale).await
 }
 
+// 6. Get CR Family Leaderboard
+#[utoipa::path(
+    get,
+    path = "/api/cr/leaderboards/{metric}",
+    tag = "cr",
+    description = "Ranks every member of all family clans (incl. side clans) from cached data.",
+    params(
+        ("metric" = String, Path, description = "trophies, wins or card_level (average, normalized to the common scale)"),
+        LeaderboardQuery,
+    ),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Members, best first (King level in `level`)", body = [crate::models::LeaderboardEntry]),
+        (status = 400, description = "Unknown metric or invalid filter", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_cr_leaderboard(
+    data: web::Data<AppState>,
+    metric: web::Path<String>,
+    query: web::Query<LeaderboardQuery>,
+) -> impl Responder {
+    get_leaderboard_impl(&data, &metric, &query, GameType::ClashRoyale).await
+}
+
+// 7. Get CR Clan Seasons
+#[utoipa::path(
+    get,
+    path = "/api/cr/clans/{tag}/seasons",
+    tag = "cr",
+    description = "Seasons archived for a clan.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Season keys (YYYY-MM), newest first", body = [String]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_cr_clan_seasons(
+    data: web::Data<AppState>,
+    tag: web::Path<String>,
+) -> impl Responder {
+    get_clan_seasons_impl(&data, &tag, GameType::ClashRoyale).await
+}
+
+// 7b. Get CR Clan Season Stats
+#[utoipa::path(
+    get,
+    path = "/api/cr/clans/{tag}/seasons/{season}",
+    tag = "cr",
+    description = "Archived member stats of a clan for one season (the last snapshot before the reset).",
+    params(
+        ("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)"),
+        ("season" = String, Path, description = "Season key, e.g. 2026-10"),
+    ),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Members, most donations first", body = [crate::models::SeasonStats]),
+        (status = 400, description = "Invalid tag or season", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Season not archived", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_cr_clan_season(
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (tag, season) = path.into_inner();
+    get_clan_season_impl(&data, &tag, &season, GameType::ClashRoyale).await
+}
+
+// 7c. Get CR Player Seasons
+#[utoipa::path(
+    get,
+    path = "/api/cr/players/{tag}/seasons",
+    tag = "cr",
+    description = "All archived seasons of a player, for side by side comparison.",
+    params(("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Seasons, newest first (one entry per clan the player was in)", body = [crate::models::SeasonStats]),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_cr_player_seasons(
+    data: web::Data<AppState>,
+    tag: web::Path<String>,
+) -> impl Responder {
+    get_player_seasons_impl(&data, &tag, GameType::ClashRoyale).await
+}
+
 // ============================================================================
 // SHARED IMPLEMENTATION FUNCTIONS
 // ============================================================================
 
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LeaderboardQuery {
+    /// Only members of this clan
+    clan: Option<String>,
+    /// Min. town hall (CoC) or King level (CR)
+    min_level: Option<i64>,
+    /// Max. town hall (CoC) or King level (CR)
+    max_level: Option<i64>,
+    /// Max. entries (default 100, at most 500)
+    limit: Option<usize>,
+}
+
+async fn get_leaderboard_impl(
+    data: &web::Data<AppState>,
+    metric: &str,
+    query: &LeaderboardQuery,
+    game: GameType,
+) -> Result<HttpResponse, ApiError> {
+    use crate::leaderboards::{Filters, Metric, build_leaderboard};
+
+    let Some(parsed) = Metric::parse(game, metric) else {
+        return Err(ApiError::BadRequest(format!(
+            "Unknown metric '{}' (expected one of {})",
+            metric,
+            Metric::names(game).join(", ")
+        )));
+    };
+    if let Some(clan) = &query.clan {
+        validate_tag(clan)?;
+    }
+
+    let filters = Filters {
+        clan: query.clan.as_deref().map(normalize_tag),
+        min_level: query.min_level,
+        max_level: query.max_level,
+    };
+    let mut entries = build_leaderboard(data, game, parsed, &filters).await?;
+    entries.truncate(query.limit.unwrap_or(100).clamp(1, 500));
+
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+// Supercell profile of a player (player cache policy)
+async fn get_player_profile(
+    data: &web::Data<AppState>,
+    game: GameType,
+    player_tag: &str,
+) -> Result<serde_json::Value, ApiError> {
+    validate_tag(player_tag)?;
+    let body = get_cached_or_update_supercell_cache(
+        data,
+        game,
+        &format!("/players/{}", encode_tag(player_tag)),
+        CacheClass::Player,
+    )
+    .await
+    .map_err(|e| match e {
+        ApiError::NotFound(_) => ApiError::NotFound("Player not found".into()),
+        e => e,
+    })?;
+    serde_json::from_slice(&body)
+        .map_err(|e| ApiError::Internal(format!("Invalid cached player: {}", e)))
+}
+
+async fn get_clan_seasons_impl(
+    data: &web::Data<AppState>,
+    tag: &str,
+    game: GameType,
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(tag)?;
+    let seasons = crate::season_stats::clan_seasons(data, game, &normalize_tag(tag)).await?;
+    Ok(HttpResponse::Ok().json(seasons))
+}
+
+async fn get_clan_season_impl(
+    data: &web::Data<AppState>,
+    tag: &str,
+    season: &str,
+    game: GameType,
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(tag)?;
+    if !crate::calendar::is_season_key(season) {
+        return Err(ApiError::BadRequest(
+            "Invalid season (expected YYYY-MM)".to_string(),
+        ));
+    }
+
+    let stats = crate::season_stats::clan_season(data, game, &normalize_tag(tag), season).await?;
+    if stats.is_empty() {
+        return Err(ApiError::NotFound(format!(
+            "No stats archived for season {}",
+            season
+        )));
+    }
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+async fn get_player_seasons_impl(
+    data: &web::Data<AppState>,
+    tag: &str,
+    game: GameType,
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(tag)?;
+    let stats = crate::season_stats::player_seasons(data, game, &normalize_tag(tag)).await?;
+    Ok(HttpResponse::Ok().json(stats))
+}
+
 fn get_cache_prefix(game: GameType) -> &'static str {
     match game {
         GameType::ClashOfClans => "coc",
@@ -293,27 +1145,44 @@ fn get_cache_prefix(game: GameType) -> &'static str {
     }
 }
 
-async fn get_clan_info_impl(data: &web::Data<AppState>, tag: &str, game: GameType) -> HttpResponse {
+fn parse_game(game: &str) -> Option<GameType> {
+    match game {
+        "coc" => Some(GameType::ClashOfClans),
+        "cr" => Some(GameType::ClashRoyale),
+        _ => None,
+    }
+}
+
+async fn get_clan_info_impl(
+    data: &web::Data<AppState>,
+    tag: &str,
+    game: GameType,
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(tag)?;
     let encoded_tag = encode_tag(tag);
     let supercell_url_path = format!("/clans/{}", encoded_tag);
 
     // 1. Get from Supercell cache (or update if missing/expired)
-    // We use a relatively long TTL (1 hour) because background task should keep it fresh
-    let sc_res = get_cached_or_update_supercell_cache(data, game, &supercell_url_path, 3600).await;
+    // Clan entries use a long TTL because the background task keeps them fresh
+    let sc_res =
+        get_cached_or_update_supercell_cache(data, game, &supercell_url_path, CacheClass::Clan)
+            .await;
 
     let mut clan_json = match sc_res {
         Ok(body) => {
             serde_json::from_slice::<serde_json::Value>(&body).unwrap_or(serde_json::Value::Null)
         }
+        Err(ApiError::NotFound(_)) => return Err(ApiError::NotFound("Clan not found".into())),
         Err(e) => {
             error!("Error fetching clan info: {}", e);
-            return HttpResponse::NotFound().json(serde_json::json!({ "error": "Clan not found" }));
+            return Err(e);
         }
     };
 
     // 2. Get from Upstream cache and merge
     let upstream_url_path = format!("/api/clans/{}", encoded_tag);
-    let up_res = get_cached_or_update_upstream_cache(data, game, &upstream_url_path, 3600).await;
+    let up_res =
+        get_cached_or_update_upstream_cache(data, game, &upstream_url_path, CacheClass::Clan).await;
 
     if let Ok(up_body) = up_res
         && let Ok(up_json) = serde_json::from_slice::<serde_json::Value>(&up_body)
@@ -337,22 +1206,17 @@ async fn get_clan_info_impl(data: &web::Data<AppState>, tag: &str, game: GameTyp
         }
     }
 
+    let mut clan: ClanInfo = serde_json::from_value(clan_json)
+        .map_err(|e| ApiError::Internal(format!("Invalid cached clan: {}", e)))?;
+
     // Fix badgeUrl mismatch (singular vs plural)
-    if let Some(obj) = clan_json.as_object_mut()
-        && !obj.contains_key("badgeUrls")
-        && let Some(url) = obj.get("badgeUrl").and_then(|u| u.as_str())
+    if clan.badge_urls.is_none()
+        && let Some(url) = clan.other.get("badgeUrl").and_then(|u| u.as_str())
     {
-        obj.insert(
-            "badgeUrls".to_string(),
-            serde_json::json!({
-                "small": url,
-                "medium": url,
-                "large": url
-            }),
-        );
+        clan.badge_urls = Some(BadgeUrls::from_single(url));
     }
 
-    HttpResponse::Ok().json(clan_json)
+    Ok(HttpResponse::Ok().json(clan))
 }
 
 async fn get_clan_config_impl(
@@ -360,50 +1224,33 @@ async fn get_clan_config_impl(
     tag: &str,
     opt_user: OptionalAuthenticatedUser,
     game: GameType,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
     let user_role = opt_user
         .user
         .as_ref()
         .and_then(|u| u.claims.role.as_deref());
-    if !has_required_role(user_role, "MEMBER") {
-        return HttpResponse::Forbidden().json(ErrorResponse {
-            error: "Access denied: Requires MEMBER role".into(),
-        });
-    }
+    require_role(user_role, "MEMBER")?;
+    validate_tag(tag)?;
 
     let encoded_tag = encode_tag(tag);
     let prefix = get_cache_prefix(game);
     let upstream_url_path = format!("/api/clans/{}", encoded_tag);
     let cache_key = format!("{}:upstream:{}", prefix, upstream_url_path);
 
-    let upstream_res = sqlx::query_as::<_, (Vec<u8>,)>("SELECT body FROM cache WHERE key = $1")
-        .bind(&cache_key)
-        .fetch_optional(&data.db_pool)
-        .await;
+    let upstream_res = get_cache_body(data, &cache_key).await?;
 
     match upstream_res {
-        Ok(Some((body,))) => {
+        Some(body) => {
             let json: serde_json::Value =
                 serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
-            if let Some(obj) = json.as_object() {
-                let mut config = serde_json::Map::new();
-                let fields = [
-                    "maxKickpoints",
-                    "minSeasonWins",
-                    "kickpointsExpireAfterDays",
-                    "kickpointReasons",
-                ];
-                for f in fields {
-                    if let Some(v) = obj.get(f) {
-                        config.insert(f.to_string(), v.clone());
-                    }
-                }
-                return HttpResponse::Ok().json(config);
+            if !json.is_object() {
+                return Err(ApiError::NotFound("Clan config not found".into()));
             }
-            HttpResponse::NotFound().json(serde_json::json!({ "error": "Clan config not found" }))
+            let config: crate::models::ClanConfig = serde_json::from_value(json)
+                .map_err(|e| ApiError::Internal(format!("Invalid cached clan config: {}", e)))?;
+            Ok(HttpResponse::Ok().json(config))
         }
-        _ => HttpResponse::NotFound()
-            .json(serde_json::json!({ "error": "Clan config not found in cache" })),
+        None => Err(ApiError::NotFound("Clan config not found in cache".into())),
     }
 }
 
@@ -412,7 +1259,8 @@ async fn get_clan_members_impl(
     tag: &str,
     opt_user: OptionalAuthenticatedUser,
     game: GameType,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(tag)?;
     let encoded_tag = encode_tag(tag);
     let prefix = get_cache_prefix(game);
     let user_role = opt_user
@@ -435,14 +1283,24 @@ async fn get_clan_members_impl(
     let upstream_url_path = format!("/api/clans/{}/members", encoded_tag);
 
     // Get bodies from cache (or update if missing/expired)
-    let supercell_res =
-        get_cached_or_update_supercell_cache(data, game, &supercell_url_path, 3600).await;
+    let supercell_res = get_cached_or_update_supercell_cache_dated(
+        data,
+        game,
+        &supercell_url_path,
+        CacheClass::Clan,
+    )
+    .await;
 
     let upstream_res =
-        get_cached_or_update_upstream_cache(data, game, &upstream_url_path, 3600).await;
+        get_cached_or_update_upstream_cache_dated(data, game, &upstream_url_path, CacheClass::Clan)
+            .await;
 
-    let mut supercell_members = match supercell_res {
-        Ok(body) => {
+    // Newest of the merged bodies, for Last-Modified
+    let mut updated_at = 0;
+
+    let supercell_members = match supercell_res {
+        Ok((body, sc_updated_at)) => {
+            updated_at = updated_at.max(sc_updated_at);
             let json: serde_json::Value =
                 serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
             json["memberList"].as_array().cloned().unwrap_or_default()
@@ -451,7 +1309,10 @@ async fn get_clan_members_impl(
     };
 
     let upstream_body = match upstream_res {
-        Ok(body) => body,
+        Ok((body, up_updated_at)) => {
+            updated_at = updated_at.max(up_updated_at);
+            body
+        }
         _ => Bytes::new(),
     };
 
@@ -470,10 +1331,11 @@ async fn get_clan_members_impl(
         .collect();
 
     // Merge upstream data into supercell list
-    for s_member in &mut supercell_members {
-        if let Some(obj) = s_member.as_object_mut() {
-            obj.insert("in_supercell".to_string(), serde_json::Value::Bool(true));
-        }
+    let mut final_members = Vec::with_capacity(supercell_members.len());
+    for mut s_member in supercell_members {
+        let mut in_upstream = false;
+        let mut is_dirty = false;
+        let mut upgrade_progress = None;
 
         if let Some(tag_ref) = s_member.get("tag").and_then(|t| t.as_str()) {
             let member_tag = tag_ref.to_string();
@@ -482,127 +1344,122 @@ async fn get_clan_members_impl(
             // Find matching upstream member
             if let Some(u_member) = upstream_members.iter().find(|m| {
                 m.get("tag").and_then(|t| t.as_str()).map(&normalize_tag) == Some(norm_tag.clone())
-            }) {
-                if let (Some(s_obj), Some(u_obj)) = (s_member.as_object_mut(), u_member.as_object())
-                {
-                    s_obj.insert("in_upstream".to_string(), serde_json::Value::Bool(true));
-                    let mut is_dirty = false;
-                    for (k, v) in u_obj {
-                        if !s_obj.contains_key(k) {
-                            s_obj.insert(k.clone(), v.clone());
-                        } else if k != "tag" {
-                            // Check for differences in common fields
-                            if k == "name" || k == "role" || k == "expLevel" {
-                                let s_val = s_obj.get(k);
-
-                                let is_truly_diff = match (k.as_str(), s_val, Some(v)) {
-                                    ("name", Some(sv), Some(uv)) => sv.as_str() != uv.as_str(),
-                                    ("role", Some(sv), Some(uv)) => {
-                                        let s_role = sv.as_str().unwrap_or("").to_lowercase();
-                                        let u_role = uv.as_str().unwrap_or("").to_lowercase();
-                                        // Normalize roles: leader, coleader, admin/elder, member
-                                        let norm_s = if s_role == "admin" || s_role == "elder" {
-                                            "admin"
-                                        } else {
-                                            &s_role
-                                        };
-                                        let norm_u = if u_role == "admin" || u_role == "elder" {
-                                            "admin"
-                                        } else {
-                                            &u_role
-                                        };
-                                        norm_s != norm_u
-                                    }
-                                    ("expLevel", Some(sv), Some(uv)) => {
-                                        // Compare numeric values regardless of JSON type (string vs number)
-                                        let s_num = sv
-                                            .as_i64()
-                                            .or_else(|| sv.as_str().and_then(|s| s.parse().ok()));
-                                        let u_num = uv
-                                            .as_i64()
-                                            .or_else(|| uv.as_str().and_then(|s| s.parse().ok()));
-                                        s_num != u_num
-                                    }
-                                    _ => false,
-                                };
-
-                                if is_truly_diff {
-                                    is_dirty = true;
+            }) && let (Some(s_obj), Some(u_obj)) =
+                (s_member.as_object_mut(), u_member.as_object())
+            {
+                in_upstream = true;
+                for (k, v) in u_obj {
+                    if !s_obj.contains_key(k) {
+                        s_obj.insert(k.clone(), v.clone());
+                    } else if k != "tag" {
+                        // Check for differences in common fields
+                        if k == "name" || k == "role" || k == "expLevel" {
+                            let s_val = s_obj.get(k);
+
+                            let is_truly_diff = match (k.as_str(), s_val, Some(v)) {
+                                ("name", Some(sv), Some(uv)) => sv.as_str() != uv.as_str(),
+                                ("role", Some(sv), Some(uv)) => {
+                                    let s_role = sv.as_str().unwrap_or("").to_lowercase();
+                                    let u_role = uv.as_str().unwrap_or("").to_lowercase();
+                                    // Normalize roles: leader, coleader, admin/elder, member
+                                    let norm_s = if s_role == "admin" || s_role == "elder" {
+                                        "admin"
+                                    } else {
+                                        &s_role
+                                    };
+                                    let norm_u = if u_role == "admin" || u_role == "elder" {
+                                        "admin"
+                                    } else {
+                                        &u_role
+                                    };
+                                    norm_s != norm_u
                                 }
+                                ("expLevel", Some(sv), Some(uv)) => {
+                                    // Compare numeric values regardless of JSON type (string vs number)
+                                    let s_num = sv
+                                        .as_i64()
+                                        .or_else(|| sv.as_str().and_then(|s| s.parse().ok()));
+                                    let u_num = uv
+                                        .as_i64()
+                                        .or_else(|| uv.as_str().and_then(|s| s.parse().ok()));
+                                    s_num != u_num
+                                }
+                                _ => false,
+                            };
+
+                            if is_truly_diff {
+                                is_dirty = true;
                             }
-                            s_obj.insert(format!("upstream_{}", k), v.clone());
                         }
+                        s_obj.insert(format!("upstream_{}", k), v.clone());
                     }
-                    s_obj.insert("is_dirty".to_string(), serde_json::Value::Bool(is_dirty));
-                    s_obj.insert("is_diff".to_string(), serde_json::Value::Bool(is_dirty));
-                    s_obj.insert("is_new".to_string(), serde_json::Value::Bool(false));
-                    s_obj.insert("is_left".to_string(), serde_json::Value::Bool(false));
                 }
-            } else if let Some(obj) = s_member.as_object_mut() {
-                obj.insert("in_upstream".to_string(), serde_json::Value::Bool(false));
-                obj.insert("is_dirty".to_string(), serde_json::Value::Bool(false));
-                obj.insert("is_diff".to_string(), serde_json::Value::Bool(true));
-                obj.insert("is_new".to_string(), serde_json::Value::Bool(true));
-                obj.insert("is_left".to_string(), serde_json::Value::Bool(false));
             }
 
-            // For CoC: Try to get additional data from player cache
-            if game == GameType::ClashOfClans {
+            // Enrich with profile data from the player cache (kept warm by the background refresh)
+            {
                 let player_cache_key =
                     format!("{}:supercell:/players/{}", prefix, encode_tag(&member_tag));
-                let player_res =
-                    sqlx::query_as::<_, (Vec<u8>,)>("SELECT body FROM cache WHERE key = $1")
-                        .bind(&player_cache_key)
-                        .fetch_optional(&data.db_pool)
-                        .await;
+                let player_res = get_cache_body_dated(data, &player_cache_key).await;
 
-                if let Ok(Some((p_body,))) = player_res
+                if let Ok(Some((p_body, p_updated_at))) = player_res
                     && let Ok(p_json) = serde_json::from_slice::<serde_json::Value>(&p_body)
                     && let (Some(s_obj), Some(p_obj)) =
                         (s_member.as_object_mut(), p_json.as_object())
                 {
-                    if let Some(stars) = p_obj.get("warStars") {
-                        s_obj.insert("warStars".to_string(), stars.clone());
-                    }
-                    if let Some(heroes) = p_obj.get("heroes") {
-                        s_obj.insert("heroes".to_string(), heroes.clone());
+                    updated_at = updated_at.max(p_updated_at);
+                    let fields: &[&str] = match game {
+                        GameType::ClashOfClans => &["warStars", "heroes", "league"],
+                        GameType::ClashRoyale => &[
+                            "bestTrophies",
+                            "wins",
+                            "losses",
+                            "warDayWins",
+                            "currentFavouriteCard",
+                            "leagueStatistics",
+                        ],
+                    };
+                    for field in fields {
+                        if let Some(value) = p_obj.get(*field) {
+                            s_obj.insert(field.to_string(), value.clone());
+                        }
                     }
-                    if let Some(league) = p_obj.get("league") {
-                        s_obj.insert("league".to_string(), league.clone());
+                    if game == GameType::ClashOfClans {
+                        upgrade_progress = crate::rush::upgrade_progress(&p_json);
                     }
                 }
             }
         }
+
+        final_members.push(MergedMember {
+            in_supercell: true,
+            in_upstream,
+            is_dirty,
+            is_diff: is_dirty || !in_upstream,
+            is_new: !in_upstream,
+            is_left: false,
+            upgrade_progress,
+            ..merged_member(s_member)
+        });
     }
 
     // Add members that are only in upstream (Left members)
-    let mut final_members = supercell_members;
     for u_member in upstream_members {
         if let Some(u_tag) = u_member.get("tag").and_then(|t| t.as_str())
             && !supercell_tags.contains(&normalize_tag(u_tag))
         {
             let mut mixed_member = u_member.clone();
             if let Some(obj) = mixed_member.as_object_mut() {
-                obj.insert("in_supercell".to_string(), serde_json::Value::Bool(false));
-                obj.insert("in_upstream".to_string(), serde_json::Value::Bool(true));
-                obj.insert("is_dirty".to_string(), serde_json::Value::Bool(false));
-                obj.insert("is_diff".to_string(), serde_json::Value::Bool(true));
-                obj.insert("is_new".to_string(), serde_json::Value::Bool(false));
-                obj.insert("is_left".to_string(), serde_json::Value::Bool(true));
-
                 // Cache check for left members to get their name/TH if bot is missing it
                 let player_cache_key =
                     format!("{}:supercell:/players/{}", prefix, encode_tag(u_tag));
-                let player_res =
-                    sqlx::query_as::<_, (Vec<u8>,)>("SELECT body FROM cache WHERE key = $1")
-                        .bind(&player_cache_key)
-                        .fetch_optional(&data.db_pool)
-                        .await;
+                let player_res = get_cache_body_dated(data, &player_cache_key).await;
 
-                if let Ok(Some((p_body,))) = player_res
+                if let Ok(Some((p_body, p_updated_at))) = player_res
                     && let Ok(p_json) = serde_json::from_slice::<serde_json::Value>(&p_body)
                     && let Some(p_obj) = p_json.as_object()
                 {
+                    updated_at = updated_at.max(p_updated_at);
                     for (pk, pv) in p_obj {
                         if !obj.contains_key(pk) {
                             obj.insert(pk.clone(), pv.clone());
@@ -632,11 +1489,64 @@ async fn get_clan_members_impl(
                     }
                 }
             }
-            final_members.push(mixed_member);
+            final_members.push(MergedMember {
+                in_supercell: false,
+                in_upstream: true,
+                is_dirty: false,
+                is_diff: true,
+                is_new: false,
+                is_left: true,
+                ..merged_member(mixed_member)
+            });
         }
     }
 
-    HttpResponse::Ok().json(final_members)
+    let mut res = HttpResponse::Ok();
+    if updated_at > 0 {
+        res.insert_header(LastModified(last_modified(updated_at)));
+    }
+    Ok(res.json(final_members))
+}
+
+// Split the typed fields off a merged member object; the flags are set by the caller
+fn merged_member(member: serde_json::Value) -> MergedMember {
+    let mut other = match member {
+        serde_json::Value::Object(obj) => obj,
+        _ => serde_json::Map::new(),
+    };
+    let mut take_str = |key: &str| match other.remove(key) {
+        Some(serde_json::Value::String(s)) => Some(s),
+        _ => None,
+    };
+    let tag = take_str("tag").unwrap_or_default();
+    let name = take_str("name").unwrap_or_else(|| tag.clone());
+    let role = take_str("role").unwrap_or_else(|| "member".to_string());
+    let upstream_name = take_str("upstream_name");
+    let upstream_role = take_str("upstream_role");
+    let active_kickpoints_count = other
+        .remove("activeKickpointsCount")
+        .and_then(|v| v.as_i64());
+    let active_kickpoints_sum = other.remove("activeKickpointsSum").and_then(|v| v.as_i64());
+    let is_linked = other.remove("isLinked").and_then(|v| v.as_bool());
+
+    MergedMember {
+        tag,
+        name,
+        role,
+        in_supercell: false,
+        in_upstream: false,
+        is_dirty: false,
+        is_diff: false,
+        is_new: false,
+        is_left: false,
+        upstream_name,
+        upstream_role,
+        upgrade_progress: None,
+        active_kickpoints_count,
+        active_kickpoints_sum,
+        is_linked,
+        other,
+    }
 }
 
 async fn get_clan_members_lite_impl(
@@ -644,7 +1554,8 @@ async fn get_clan_members_lite_impl(
     tag: &str,
     opt_user: OptionalAuthenticatedUser,
     game: GameType,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(tag)?;
     let encoded_tag = encode_tag(tag);
     let user_role = opt_user
         .user
@@ -663,23 +1574,73 @@ async fn get_clan_members_lite_impl(
         .unwrap_or(&[]);
 
     let upstream_url_path = format!("/api/clans/{}/members-lite", encoded_tag);
-    forward_request_with_filter(data, game, &upstream_url_path, user_role, exempt_tags).await
+    forward_request_with_filter::<Vec<BotMember>>(
+        data,
+        game,
+        &upstream_url_path,
+        user_role,
+        exempt_tags,
+    )
+    .await
 }
 
-async fn get_clan_kickpoint_reasons_impl(
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InactivityQuery {
+    /// Days without activity until a member is flagged (default from the config)
+    warn_days: Option<i64>,
+    /// Days without activity until a member counts as inactive (default from the config)
+    inactive_days: Option<i64>,
+}
+
+async fn get_clan_inactivity_impl(
     data: &web::Data<AppState>,
     tag: &str,
+    query: &InactivityQuery,
     user: AuthenticatedUser,
     game: GameType,
-) -> HttpResponse {
-    if !has_required_role(user.claims.role.as_deref(), "COLEADER") {
-        return HttpResponse::Forbidden().json(ErrorResponse {
-            error: "Access denied: Requires COLEADER or higher role".into(),
-        });
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "COLEADER")?;
+    validate_tag(tag)?;
+
+    let warn_days = query.warn_days.unwrap_or(data.inactivity.warn_days);
+    let inactive_days = query.inactive_days.unwrap_or(data.inactivity.inactive_days);
+    if warn_days < 1 || inactive_days < warn_days {
+        return Err(ApiError::BadRequest(
+            "warn_days must be at least 1 and at most inactive_days".to_string(),
+        ));
     }
 
+    let body = get_cached_or_update_supercell_cache(
+        data,
+        game,
+        &format!("/clans/{}", encode_tag(tag)),
+        CacheClass::Clan,
+    )
+    .await?;
+    let clan: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::Internal(format!("Invalid cached clan: {}", e)))?;
+
+    let members =
+        crate::activity::inactivity_report(data, game, &clan, warn_days, inactive_days).await?;
+    Ok(HttpResponse::Ok().json(crate::models::InactivityReport {
+        warn_days,
+        inactive_days,
+        members,
+    }))
+}
+
+async fn get_clan_kickpoint_reasons_impl(
+    data: &web::Data<AppState>,
+    tag: &str,
+    user: AuthenticatedUser,
+    game: GameType,
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "COLEADER")?;
+    validate_tag(tag)?;
+
     let encoded_tag = encode_tag(tag);
-    forward_request(
+    forward_request::<Vec<crate::models::KickpointReason>>(
         data,
         game,
         &format!("/api/clans/{}/kickpoint-reasons", encoded_tag),
@@ -692,22 +1653,49 @@ async fn get_player_impl(
     tag: &str,
     opt_user: OptionalAuthenticatedUser,
     game: GameType,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(tag)?;
     let encoded_tag = encode_tag(tag);
     let supercell_url_path = format!("/players/{}", encoded_tag);
     let upstream_url_path = format!("/api/players/{}", encoded_tag);
 
-    // Get cached or update (5min TTL)
-    let supercell_res =
-        get_cached_or_update_supercell_cache(data, game, &supercell_url_path, 300).await;
-    let upstream_res =
-        get_cached_or_update_upstream_cache(data, game, &upstream_url_path, 300).await;
+    // Get cached or update (player cache policy)
+    let supercell_res = get_cached_or_update_supercell_cache_dated(
+        data,
+        game,
+        &supercell_url_path,
+        CacheClass::Player,
+    )
+    .await;
+    let upstream_res = get_cached_or_update_upstream_cache_dated(
+        data,
+        game,
+        &upstream_url_path,
+        CacheClass::Player,
+    )
+    .await;
 
-    let mut player_json = match supercell_res {
-        Ok(body) => {
-            serde_json::from_slice::<serde_json::Value>(&body).unwrap_or(serde_json::Value::Null)
-        }
-        _ => return HttpResponse::NotFound().finish(),
+    let (player_json, mut updated_at) = match supercell_res {
+        Ok((body, updated_at)) => (
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap_or(serde_json::Value::Null),
+            updated_at,
+        ),
+        Err(ApiError::NotFound(_)) => return Err(ApiError::NotFound("Player not found".into())),
+        Err(e) => return Err(e),
+    };
+
+    let mut player = crate::models::Player {
+        upgrade_progress: match game {
+            GameType::ClashOfClans => crate::rush::upgrade_progress(&player_json),
+            GameType::ClashRoyale => None,
+        },
+        active_kickpoints_count: None,
+        active_kickpoints_sum: None,
+        total_kickpoints: None,
+        profile: match player_json {
+            serde_json::Value::Object(profile) => profile,
+            _ => serde_json::Map::new(),
+        },
     };
 
     // Fetch upstream summary if user is authorized
@@ -726,35 +1714,33 @@ async fn get_player_impl(
             }
         })
         .unwrap_or(&[]);
-    let tag_str = player_json
+    let tag_str = player
+        .profile
         .get("tag")
         .and_then(|t| t.as_str())
         .unwrap_or("");
     let is_exempt = exempt_tags.iter().any(|et| et == tag_str);
 
     if (has_required_role(user_role, "MEMBER") || is_exempt)
-        && let Ok(u_body) = upstream_res
+        && let Ok((u_body, u_updated_at)) = upstream_res
         && let Ok(u_json) = serde_json::from_slice::<serde_json::Value>(&u_body)
-        && let Some(obj) = player_json.as_object_mut()
     {
+        updated_at = updated_at.max(u_updated_at);
         // Only merge kickpoint summaries, NOT identity
         if let Some(akp) = u_json.get("activeKickpoints").and_then(|v| v.as_array()) {
             let sum: i64 = akp
                 .iter()
                 .filter_map(|kp| kp.get("amount").and_then(|a| a.as_i64()))
                 .sum();
-            obj.insert(
-                "activeKickpointsCount".to_string(),
-                serde_json::json!(akp.len()),
-            );
-            obj.insert("activeKickpointsSum".to_string(), serde_json::json!(sum));
-        }
-        if let Some(total) = u_json.get("totalKickpoints") {
-            obj.insert("totalKickpoints".to_string(), total.clone());
+            player.active_kickpoints_count = Some(akp.len() as i64);
+            player.active_kickpoints_sum = Some(sum);
         }
+        player.total_kickpoints = u_json.get("totalKickpoints").and_then(|v| v.as_i64());
     }
 
-    HttpResponse::Ok().json(player_json)
+    Ok(HttpResponse::Ok()
+        .insert_header(LastModified(last_modified(updated_at)))
+        .json(player))
 }
 
 async fn get_player_identity_impl(
@@ -762,7 +1748,8 @@ async fn get_player_identity_impl(
     tag: &str,
     opt_user: OptionalAuthenticatedUser,
     game: GameType,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(tag)?;
     let encoded_tag = encode_tag(tag);
     let upstream_url_path = format!("/api/players/{}", encoded_tag);
 
@@ -788,56 +1775,44 @@ async fn get_player_identity_impl(
     };
     let is_exempt = exempt_tags.iter().any(|et| et == &tag_str);
 
-    if !has_required_role(user_role, "MEMBER") && !is_exempt {
-        return HttpResponse::Forbidden().json(ErrorResponse {
-            error: "Access denied: Requires MEMBER role".into(),
-        });
+    if !is_exempt {
+        require_role(user_role, "MEMBER")?;
     }
 
-    // Get cached or update (5min TTL)
+    // Get cached or update (player cache policy)
     let upstream_res =
-        get_cached_or_update_upstream_cache(data, game, &upstream_url_path, 300).await;
+        get_cached_or_update_upstream_cache(data, game, &upstream_url_path, CacheClass::Player)
+            .await;
 
     match upstream_res {
         Ok(body) => {
             let u_json: serde_json::Value =
                 serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
             if let Some(obj) = u_json.as_object() {
-                let mut identity = serde_json::Map::new();
-
                 // Public-ish for members
-                if let Some(nick) = obj.get("nickname") {
-                    identity.insert("nickname".to_string(), nick.clone());
-                }
-                if let Some(gn) = obj.get("global_name") {
-                    identity.insert("global_name".to_string(), gn.clone());
-                }
-                if let Some(un) = obj.get("username") {
-                    identity.insert("username".to_string(), un.clone());
-                }
-                if let Some(av) = obj.get("avatar") {
-                    identity.insert("avatar".to_string(), av.clone());
-                }
+                let mut identity = crate::models::PlayerIdentity {
+                    nickname: obj.get("nickname").cloned(),
+                    global_name: obj.get("global_name").cloned(),
+                    username: obj.get("username").cloned(),
+                    avatar: obj.get("avatar").cloned(),
+                    user_id: None,
+                    discord_id: None,
+                    player_accounts: None,
+                };
 
                 // Sensitive - COLEADER+ or self
                 if has_required_role(user_role, "COLEADER") || is_exempt {
-                    if let Some(uid) = obj.get("userId") {
-                        identity.insert("userId".to_string(), uid.clone());
-                    }
-                    if let Some(did) = obj.get("discordId") {
-                        identity.insert("discordId".to_string(), did.clone());
-                    }
-                    if let Some(accs) = obj.get("playerAccounts") {
-                        identity.insert("playerAccounts".to_string(), accs.clone());
-                    }
+                    identity.user_id = obj.get("userId").cloned();
+                    identity.discord_id = obj.get("discordId").cloned();
+                    identity.player_accounts = obj.get("playerAccounts").cloned();
                 }
 
-                return HttpResponse::Ok().json(identity);
+                return Ok(HttpResponse::Ok().json(identity));
             }
-            HttpResponse::NotFound().json(serde_json::json!({ "error": "Identity not found" }))
+            Err(ApiError::NotFound("Identity not found".into()))
         }
-        _ => HttpResponse::NotFound()
-            .json(serde_json::json!({ "error": "Identity not found in cache" })),
+        Err(ApiError::NotFound(_)) => Err(ApiError::NotFound("Identity not found".into())),
+        Err(e) => Err(e),
     }
 }
 
@@ -846,19 +1821,22 @@ async fn get_player_kickpoints_impl(
     tag: &str,
     opt_user: OptionalAuthenticatedUser,
     game: GameType,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(tag)?;
     let encoded_tag = encode_tag(tag);
     let upstream_url_path = format!("/api/players/{}", encoded_tag);
 
-    // Get cached or update (5min TTL)
+    // Get cached or update (player cache policy)
     let upstream_res =
-        get_cached_or_update_upstream_cache(data, game, &upstream_url_path, 300).await;
+        get_cached_or_update_upstream_cache(data, game, &upstream_url_path, CacheClass::Player)
+            .await;
 
     let u_json = match upstream_res {
         Ok(body) => {
             serde_json::from_slice::<serde_json::Value>(&body).unwrap_or(serde_json::Value::Null)
         }
-        _ => return HttpResponse::NotFound().finish(),
+        Err(ApiError::NotFound(_)) => return Err(ApiError::NotFound("Player not found".into())),
+        Err(e) => return Err(e),
     };
 
     let user_role = opt_user
@@ -880,10 +1858,8 @@ async fn get_player_kickpoints_impl(
     let tag_str = u_json.get("tag").and_then(|t| t.as_str()).unwrap_or("");
     let tag_is_exempt = exempt_tags.iter().any(|et| et == tag_str);
 
-    if !has_required_role(user_role, "MEMBER") && !tag_is_exempt {
-        return HttpResponse::Forbidden().json(ErrorResponse {
-            error: "Access denied: Requires MEMBER role".into(),
-        });
+    if !tag_is_exempt {
+        require_role(user_role, "MEMBER")?;
     }
 
     let active_count = u_json
@@ -905,10 +1881,10 @@ async fn get_player_kickpoints_impl(
         .and_then(|v| v.as_i64())
         .unwrap_or(0);
 
-    HttpResponse::Ok().json(serde_json::json!({
-        "total": total,
-        "activeCount": active_count,
-        "activeSum": active_sum,
+    Ok(HttpResponse::Ok().json(crate::models::KickpointSummary {
+        total,
+        active_count: active_count as i64,
+        active_sum,
     }))
 }
 
@@ -917,19 +1893,22 @@ async fn get_player_kickpoints_details_impl(
     tag: &str,
     opt_user: OptionalAuthenticatedUser,
     game: GameType,
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError> {
+    validate_tag(tag)?;
     let encoded_tag = encode_tag(tag);
     let upstream_url_path = format!("/api/players/{}", encoded_tag);
 
-    // Get cached or update (5min TTL)
+    // Get cached or update (player cache policy)
     let upstream_res =
-        get_cached_or_update_upstream_cache(data, game, &upstream_url_path, 300).await;
+        get_cached_or_update_upstream_cache(data, game, &upstream_url_path, CacheClass::Player)
+            .await;
 
     let mut u_json = match upstream_res {
         Ok(body) => {
             serde_json::from_slice::<serde_json::Value>(&body).unwrap_or(serde_json::Value::Null)
         }
-        _ => return HttpResponse::NotFound().finish(),
+        Err(ApiError::NotFound(_)) => return Err(ApiError::NotFound("Player not found".into())),
+        Err(e) => return Err(e),
     };
 
     let user_role = opt_user
@@ -951,30 +1930,25 @@ async fn get_player_kickpoints_details_impl(
     let tag_str = u_json.get("tag").and_then(|t| t.as_str()).unwrap_or("");
     let tag_is_exempt = exempt_tags.iter().any(|et| et == tag_str);
 
-    if !has_required_role(user_role, "MEMBER") && !tag_is_exempt {
-        return HttpResponse::Forbidden().json(ErrorResponse {
-            error: "Access denied: Requires MEMBER role".into(),
-        });
+    if !tag_is_exempt {
+        require_role(user_role, "MEMBER")?;
     }
 
     let is_coleader = has_required_role(user_role, "COLEADER");
 
-    if let Some(akp) = u_json
-        .get_mut("activeKickpoints")
-        .and_then(|v| v.as_array_mut())
-    {
-        if !is_coleader && !tag_is_exempt {
-            for kp in akp.iter_mut() {
-                if let Some(kp_obj) = kp.as_object_mut() {
-                    kp_obj.remove("description");
-                    kp_obj.remove("reason");
-                }
-            }
+    let mut kickpoints: Vec<Kickpoint> = match u_json.get_mut("activeKickpoints") {
+        Some(akp) => serde_json::from_value(akp.take())
+            .map_err(|e| ApiError::Internal(format!("Invalid cached kickpoints: {}", e)))?,
+        None => vec![],
+    };
+    if !is_coleader && !tag_is_exempt {
+        for kp in &mut kickpoints {
+            kp.description = None;
+            kp.other.remove("reason");
         }
-        return HttpResponse::Ok().json(akp);
     }
 
-    HttpResponse::Ok().json(serde_json::Value::Array(vec![]))
+    Ok(HttpResponse::Ok().json(kickpoints))
 }
 
 // ============================================================================
@@ -982,28 +1956,40 @@ async fn get_player_kickpoints_details_impl(
 // ============================================================================
 
 // Get User
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}",
+    tag = "users",
+    description = "Bot user merged across both bots. Users can always read their own profile.",
+    params(("id" = String, Path, description = "Discord user id")),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("COLEADER"))),
+    responses(
+        (status = 200, description = "User", body = crate::models::BotUser),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_user(
     data: web::Data<AppState>,
     user_id: web::Path<String>,
     opt_user: OptionalAuthenticatedUser,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let is_self = opt_user
         .user
         .as_ref()
         .map(|u| u.claims.sub == *user_id)
         .unwrap_or(false);
-    let is_coleader = opt_user
-        .user
-        .as_ref()
-        .and_then(|u| u.claims.role.as_deref())
-        .map(|r| crate::auth::get_role_priority(r) >= crate::auth::get_role_priority("COLEADER"))
-        .unwrap_or(false);
 
-    if !is_self && !is_coleader {
-        return HttpResponse::Forbidden().json(ErrorResponse {
-            error: "Access denied: You can only view your own profile or require COLEADER role"
-                .into(),
-        });
+    // Users can always view their own profile
+    if !is_self {
+        require_role(
+            opt_user
+                .user
+                .as_ref()
+                .and_then(|u| u.claims.role.as_deref()),
+            "COLEADER",
+        )?;
     }
 
     let url_path = format!("/api/users/{}", user_id);
@@ -1013,106 +1999,56 @@ pub async fn get_user(
     let _ = update_upstream_cache(&data, GameType::ClashRoyale, &url_path).await;
 
     // Fetch both from cache
-    let coc_res = sqlx::query_as::<_, (Vec<u8>,)>("SELECT body FROM cache WHERE key = $1")
-        .bind(format!("coc:upstream:{}", url_path))
-        .fetch_optional(&data.db_pool)
-        .await;
-
-    let cr_res = sqlx::query_as::<_, (Vec<u8>,)>("SELECT body FROM cache WHERE key = $1")
-        .bind(format!("cr:upstream:{}", url_path))
-        .fetch_optional(&data.db_pool)
-        .await;
+    let coc_res = get_cache_body(&data, &format!("coc:upstream:{}", url_path)).await;
+    let cr_res = get_cache_body(&data, &format!("cr:upstream:{}", url_path)).await;
 
-    let coc_data: Option<serde_json::Value> = match coc_res {
-        Ok(Some((body,))) => serde_json::from_slice(&body).ok(),
+    let coc_data: Option<BotUser> = match coc_res {
+        Ok(Some(body)) => serde_json::from_slice(&body).ok(),
         _ => None,
     };
 
-    let cr_data: Option<serde_json::Value> = match cr_res {
-        Ok(Some((body,))) => serde_json::from_slice(&body).ok(),
+    let cr_data: Option<BotUser> = match cr_res {
+        Ok(Some(body)) => serde_json::from_slice(&body).ok(),
         _ => None,
     };
 
     match (coc_data, cr_data) {
-        (None, None) => HttpResponse::NotFound().json(ErrorResponse {
-            error: "User not found in any upstream bot".into(),
-        }),
-        (Some(coc), None) => HttpResponse::Ok().json(coc),
-        (None, Some(cr)) => HttpResponse::Ok().json(cr),
+        (None, None) => Err(ApiError::NotFound(
+            "User not found in any upstream bot".into(),
+        )),
+        (Some(coc), None) => Ok(HttpResponse::Ok().json(coc)),
+        (None, Some(cr)) => Ok(HttpResponse::Ok().json(cr)),
         (Some(mut coc), Some(cr)) => {
             // Merge CR into CoC data
-            if let (Some(coc_obj), Some(cr_obj)) = (coc.as_object_mut(), cr.as_object()) {
-                // Admin: true if either is true
-                let coc_admin = coc_obj
-                    .get("admin")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-                let cr_admin = cr_obj
-                    .get("admin")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(false);
-                coc_obj.insert(
-                    "admin".to_string(),
-                    serde_json::json!(coc_admin || cr_admin),
-                );
-
-                // Highest Role: max of both
-                let coc_role = coc_obj
-                    .get("highestRole")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("NOTMEMBER");
-                let cr_role = cr_obj
-                    .get("highestRole")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("NOTMEMBER");
-                if crate::auth::get_role_priority(cr_role)
-                    > crate::auth::get_role_priority(coc_role)
-                {
-                    coc_obj.insert("highestRole".to_string(), serde_json::json!(cr_role));
-                }
+            // Admin: true if either is true
+            coc.admin |= cr.admin;
+
+            // Highest Role: max of both
+            let coc_role = coc.highest_role.as_deref().unwrap_or("NOTMEMBER");
+            let cr_role = cr.highest_role.as_deref().unwrap_or("NOTMEMBER");
+            if crate::auth::get_role_priority(cr_role) > crate::auth::get_role_priority(coc_role) {
+                coc.highest_role = cr.highest_role;
+            }
 
-                // Linked Players: merge and deduplicate
-                let mut coc_linked = coc_obj
-                    .get("linkedPlayers")
-                    .and_then(|v| v.as_array())
-                    .cloned()
-                    .unwrap_or_default();
-                if let Some(cr_linked) = cr_obj.get("linkedPlayers").and_then(|v| v.as_array()) {
-                    for tag in cr_linked {
-                        if !coc_linked.contains(tag) {
-                            coc_linked.push(tag.clone());
-                        }
-                    }
+            // Linked Players: merge and deduplicate
+            for tag in cr.linked_players {
+                if !coc.linked_players.contains(&tag) {
+                    coc.linked_players.push(tag);
                 }
-                coc_obj.insert("linkedPlayers".to_string(), serde_json::json!(coc_linked));
-
-                // Linked CR Players: merge and deduplicate
-                let mut coc_cr_linked = coc_obj
-                    .get("linkedCrPlayers")
-                    .and_then(|v| v.as_array())
-                    .cloned()
-                    .unwrap_or_default();
-                if let Some(cr_cr_linked) = cr_obj.get("linkedCrPlayers").and_then(|v| v.as_array())
-                {
-                    for tag in cr_cr_linked {
-                        if !coc_cr_linked.contains(tag) {
-                            coc_cr_linked.push(tag.clone());
-                        }
-                    }
-                }
-                coc_obj.insert(
-                    "linkedCrPlayers".to_string(),
-                    serde_json::json!(coc_cr_linked),
-                );
-
-                // Nickname: prefer CoC but if null take CR
-                if coc_obj.get("nickname").is_none_or(|v| v.is_null())
-                    && let Some(cr_nick) = cr_obj.get("nickname")
-                {
-                    coc_obj.insert("nickname".to_string(), cr_nick.clone());
+            }
+
+            // Linked CR Players: merge and deduplicate
+            for tag in cr.linked_cr_players {
+                if !coc.linked_cr_players.contains(&tag) {
+                    coc.linked_cr_players.push(tag);
                 }
             }
-            HttpResponse::Ok().json(coc)
+
+            // Nickname: prefer CoC but if null take CR
+            if coc.nickname.is_none() {
+                coc.nickname = cr.nickname;
+            }
+            Ok(HttpResponse::Ok().json(coc))
         }
     }
 }
@@ -1122,163 +2058,92 @@ async fn fetch_aggregated_player_accounts(
     data: &web::Data<AppState>,
     coc_linked_players: Vec<String>,
     cr_linked_players: Vec<String>,
-) -> serde_json::Value {
-    let mut coc_players_futures = Vec::new();
-    let mut cr_players_futures = Vec::new();
-
-    // Prepare CoC futures
-    for tag in coc_linked_players {
-        let data = data.clone();
-        coc_players_futures.push(async move {
-            let encoded_tag = encode_tag(&tag);
-            let supercell_url_path = format!("/players/{}", encoded_tag);
-            let upstream_url_path = format!("/api/players/{}", encoded_tag);
-
-            let supercell_res = get_cached_or_update_supercell_cache(
-                &data,
-                GameType::ClashOfClans,
-                &supercell_url_path,
-                300,
-            )
-            .await;
-            let upstream_res = get_cached_or_update_upstream_cache(
-                &data,
-                GameType::ClashOfClans,
-                &upstream_url_path,
-                300,
-            )
-            .await;
-
-            if let Ok(supercell_body) = supercell_res
-                && let Ok(mut player_json) =
-                    serde_json::from_slice::<serde_json::Value>(&supercell_body)
-            {
-                if let Some(player_obj) = player_json.as_object_mut() {
-                    player_obj.insert("gameType".to_string(), serde_json::json!("coc"));
-
-                    if let Ok(u_body) = upstream_res
-                        && let Ok(u_json) = serde_json::from_slice::<serde_json::Value>(&u_body)
-                        && let Some(u_obj) = u_json.as_object()
-                    {
-                        // Explicitly mark upstream clan to differentiate from supercell clan
-                        if let Some(upstream_clan) = u_obj.get("clan") {
-                            player_obj.insert("upstream_clan".to_string(), upstream_clan.clone());
-                        }
-
-                        for (k, v) in u_obj {
-                            if !player_obj.contains_key(k) {
-                                player_obj.insert(k.clone(), v.clone());
-                            } else if k != "tag" {
-                                player_obj.insert(format!("upstream_{}", k), v.clone());
-                            }
-                        }
-                        if let Some(akp) = player_obj
-                            .get("activeKickpoints")
-                            .and_then(|v| v.as_array())
-                        {
-                            let sum: i64 = akp
-                                .iter()
-                                .filter_map(|kp| kp.get("amount").and_then(|a| a.as_i64()))
-                                .sum();
-                            player_obj.insert(
-                                "activeKickpointsCount".to_string(),
-                                serde_json::json!(akp.len()),
-                            );
-                            player_obj
-                                .insert("activeKickpointsSum".to_string(), serde_json::json!(sum));
-                        }
-                    }
-                    return Some(player_json);
-                }
-            }
-            None
-        });
+) -> crate::models::LinkedAccounts {
+    let coc_players_futures = coc_linked_players
+        .iter()
+        .map(|tag| fetch_linked_player(data, GameType::ClashOfClans, tag));
+    let cr_players_futures = cr_linked_players
+        .iter()
+        .map(|tag| fetch_linked_player(data, GameType::ClashRoyale, tag));
+
+    crate::models::LinkedAccounts {
+        coc: join_all(coc_players_futures)
+            .await
+            .into_iter()
+            .flatten()
+            .collect(),
+        cr: join_all(cr_players_futures)
+            .await
+            .into_iter()
+            .flatten()
+            .collect(),
     }
+}
 
-    // Prepare CR futures
-    for tag in cr_linked_players {
-        let data = data.clone();
-        cr_players_futures.push(async move {
-            let encoded_tag = encode_tag(&tag);
-            let supercell_url_path = format!("/players/{}", encoded_tag);
-            let upstream_url_path = format!("/api/players/{}", encoded_tag);
+// Supercell profile of a linked account merged with the bot's player entry
+async fn fetch_linked_player(
+    data: &AppState,
+    game: GameType,
+    tag: &str,
+) -> Option<crate::models::LinkedPlayer> {
+    let encoded_tag = encode_tag(tag);
+    let supercell_url_path = format!("/players/{}", encoded_tag);
+    let upstream_url_path = format!("/api/players/{}", encoded_tag);
 
-            let supercell_res = get_cached_or_update_supercell_cache(
-                &data,
-                GameType::ClashRoyale,
-                &supercell_url_path,
-                300,
-            )
+    let supercell_res =
+        get_cached_or_update_supercell_cache(data, game, &supercell_url_path, CacheClass::Player)
             .await;
-            let upstream_res = get_cached_or_update_upstream_cache(
-                &data,
-                GameType::ClashRoyale,
-                &upstream_url_path,
-                300,
-            )
+    let upstream_res =
+        get_cached_or_update_upstream_cache(data, game, &upstream_url_path, CacheClass::Player)
             .await;
 
-            if let Ok(supercell_body) = supercell_res
-                && let Ok(mut player_json) =
-                    serde_json::from_slice::<serde_json::Value>(&supercell_body)
-            {
-                if let Some(player_obj) = player_json.as_object_mut() {
-                    player_obj.insert("gameType".to_string(), serde_json::json!("cr"));
-
-                    if let Ok(u_body) = upstream_res
-                        && let Ok(u_json) = serde_json::from_slice::<serde_json::Value>(&u_body)
-                        && let Some(u_obj) = u_json.as_object()
-                    {
-                        // Explicitly mark upstream clan to differentiate from supercell clan
-                        if let Some(upstream_clan) = u_obj.get("clan") {
-                            player_obj.insert("upstream_clan".to_string(), upstream_clan.clone());
-                        }
+    let Ok(supercell_body) = supercell_res else {
+        return None;
+    };
+    let Ok(serde_json::Value::Object(mut player_obj)) =
+        serde_json::from_slice::<serde_json::Value>(&supercell_body)
+    else {
+        return None;
+    };
 
-                        for (k, v) in u_obj {
-                            if !player_obj.contains_key(k) {
-                                player_obj.insert(k.clone(), v.clone());
-                            } else if k != "tag" {
-                                player_obj.insert(format!("upstream_{}", k), v.clone());
-                            }
-                        }
-                        if let Some(akp) = player_obj
-                            .get("activeKickpoints")
-                            .and_then(|v| v.as_array())
-                        {
-                            let sum: i64 = akp
-                                .iter()
-                                .filter_map(|kp| kp.get("amount").and_then(|a| a.as_i64()))
-                                .sum();
-                            player_obj.insert(
-                                "activeKickpointsCount".to_string(),
-                                serde_json::json!(akp.len()),
-                            );
-                            player_obj
-                                .insert("activeKickpointsSum".to_string(), serde_json::json!(sum));
-                        }
-                    }
-                    return Some(player_json);
-                }
+    let mut player = crate::models::LinkedPlayer {
+        game_type: get_cache_prefix(game).to_string(),
+        active_kickpoints_count: None,
+        active_kickpoints_sum: None,
+        profile: serde_json::Map::new(),
+    };
+
+    if let Ok(u_body) = upstream_res
+        && let Ok(u_json) = serde_json::from_slice::<serde_json::Value>(&u_body)
+        && let Some(u_obj) = u_json.as_object()
+    {
+        // Explicitly mark upstream clan to differentiate from supercell clan
+        if let Some(upstream_clan) = u_obj.get("clan") {
+            player_obj.insert("upstream_clan".to_string(), upstream_clan.clone());
+        }
+
+        for (k, v) in u_obj {
+            if !player_obj.contains_key(k) {
+                player_obj.insert(k.clone(), v.clone());
+            } else if k != "tag" {
+                player_obj.insert(format!("upstream_{}", k), v.clone());
             }
-            None
-        });
+        }
+        if let Some(akp) = player_obj
+            .get("activeKickpoints")
+            .and_then(|v| v.as_array())
+        {
+            let sum: i64 = akp
+                .iter()
+                .filter_map(|kp| kp.get("amount").and_then(|a| a.as_i64()))
+                .sum();
+            player.active_kickpoints_count = Some(akp.len() as i64);
+            player.active_kickpoints_sum = Some(sum);
+        }
     }
 
-    let coc_players_data: Vec<serde_json::Value> = join_all(coc_players_futures)
-        .await
-        .into_iter()
-        .flatten()
-        .collect();
-    let cr_players_data: Vec<serde_json::Value> = join_all(cr_players_futures)
-        .await
-        .into_iter()
-        .flatten()
-        .collect();
-
-    serde_json::json!({
-        "coc": coc_players_data,
-        "cr": cr_players_data
-    })
+    player.profile = player_obj;
+    Some(player)
 }
 
 // Sync user accounts from upstreams and update DB
@@ -1294,11 +2159,21 @@ async fn sync_user_accounts(
 
     let url_path = format!("/api/users/{}", discord_id);
 
-    // 1. Fetch from Upstreams (TTL 0 to force refresh)
-    let coc_bot_res =
-        get_cached_or_update_upstream_cache(data, GameType::ClashOfClans, &url_path, 0).await;
-    let cr_bot_res =
-        get_cached_or_update_upstream_cache(data, GameType::ClashRoyale, &url_path, 0).await;
+    // 1. Fetch from Upstreams (user links are never served stale)
+    let coc_bot_res = get_cached_or_update_upstream_cache(
+        data,
+        GameType::ClashOfClans,
+        &url_path,
+        CacheClass::User,
+    )
+    .await;
+    let cr_bot_res = get_cached_or_update_upstream_cache(
+        data,
+        GameType::ClashRoyale,
+        &url_path,
+        CacheClass::User,
+    )
+    .await;
 
     let mut bot_a_coc = Vec::new();
     let mut bot_a_cr = Vec::new();
@@ -1348,9 +2223,14 @@ async fn sync_user_accounts(
         bot_b_success = true;
     }
 
-    // 2. Reconcile CoC (Authority is Bot A)
-    if bot_a_success && coc_players != bot_a_coc {
-        coc_players = bot_a_coc;
+    // 2. Reconcile CoC (Authority is Bot A, plus verified links it does not list yet)
+    if bot_a_success {
+        crate::links::merge_verified(data, discord_id, &mut bot_a_coc, true).await;
+        if coc_players != bot_a_coc {
+            coc_players = bot_a_coc;
+            modified = true;
+        }
+    } else if crate::links::merge_verified(data, discord_id, &mut coc_players, false).await {
         modified = true;
     }
 
@@ -1405,6 +2285,18 @@ async fn sync_user_accounts(
 }
 
 // Get My Player Accounts
+#[utoipa::path(
+    get,
+    path = "/api/me/accounts",
+    tag = "users",
+    description = "Linked accounts of the logged-in user, synced with both bots.",
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("AUTHENTICATED"))),
+    responses(
+        (status = 200, description = "Linked accounts", body = crate::models::LinkedAccounts),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_my_player_accounts(
     data: web::Data<AppState>,
     user: AuthenticatedUser,
@@ -1422,16 +2314,27 @@ pub async fn get_my_player_accounts(
 }
 
 // Get Another User's Player Accounts
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}/accounts",
+    tag = "users",
+    description = "Linked accounts of any user, synced with both bots.",
+    params(("id" = String, Path, description = "Discord user id")),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Linked accounts", body = crate::models::LinkedAccounts),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_user_player_accounts(
     data: web::Data<AppState>,
     user_id: web::Path<String>,
     auth_user: AuthenticatedUser,
-) -> impl Responder {
-    if !has_required_role(auth_user.claims.role.as_deref(), "ADMIN") {
-        return HttpResponse::Forbidden().json(ErrorResponse {
-            error: "Access denied: Requires ADMIN role".into(),
-        });
-    }
+) -> Result<HttpResponse, ApiError> {
+    require_role(auth_user.claims.role.as_deref(), "ADMIN")?;
 
     let uid = user_id.into_inner();
 
@@ -1441,25 +2344,86 @@ pub async fn get_user_player_accounts(
     )
     .bind(&uid)
     .fetch_optional(&data.db_pool)
-    .await;
+    .await?;
 
     let (coc_linked, cr_linked): (Vec<String>, Vec<String>) = match user_db {
-        Ok(Some((lp_json, cr_json))) => (
+        Some((lp_json, cr_json)) => (
             serde_json::from_str(&lp_json).unwrap_or_default(),
             serde_json::from_str(&cr_json).unwrap_or_default(),
         ),
-        _ => (Vec::new(), Vec::new()),
+        None => (Vec::new(), Vec::new()),
     };
 
     let (coc_linked, cr_linked) = sync_user_accounts(&data, &uid, coc_linked, cr_linked).await;
 
     if coc_linked.is_empty() && cr_linked.is_empty() {
-        return HttpResponse::NotFound()
-            .json(serde_json::json!({ "error": "User not found in local DB or any upstream" }));
+        return Err(ApiError::NotFound(
+            "User not found in local DB or any upstream".into(),
+        ));
     }
 
     let players_data = fetch_aggregated_player_accounts(&data, coc_linked, cr_linked).await;
-    HttpResponse::Ok().json(players_data)
+    Ok(HttpResponse::Ok().json(players_data))
+}
+
+// Get My Verified Account Links
+#[utoipa::path(
+    get,
+    path = "/api/me/accounts/links",
+    tag = "users",
+    description = "CoC accounts the logged-in user linked on the website, with their push state to the CoC bot.",
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("AUTHENTICATED"))),
+    responses(
+        (status = 200, description = "Verified links", body = [crate::models::AccountLink]),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_my_account_links(
+    data: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    let links = crate::links::links_of(&data, &user.claims.sub).await?;
+    Ok(HttpResponse::Ok().json(links))
+}
+
+// Link a CoC Account (Verified by API Token)
+#[utoipa::path(
+    post,
+    path = "/api/me/accounts/links",
+    tag = "users",
+    description = "Links a CoC account to the logged-in user after verifying its in-game API token with Supercell. The link is pushed to the CoC bot; failed pushes are retried by the push_account_links job.",
+    request_body = crate::models::AccountLinkRequest,
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("AUTHENTICATED"))),
+    responses(
+        (status = 200, description = "Stored link", body = crate::models::AccountLink),
+        (status = 400, description = "Invalid tag or API token", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Player not found", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn link_my_account(
+    data: web::Data<AppState>,
+    body: web::Json<crate::models::AccountLinkRequest>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    let token = body.token.trim();
+    if token.is_empty() {
+        return Err(ApiError::BadRequest("Missing API token".into()));
+    }
+    let profile = get_player_profile(&data, GameType::ClashOfClans, &body.player_tag).await?;
+    if !crate::links::verify_token(&data, &body.player_tag, token).await? {
+        return Err(ApiError::BadRequest("Invalid API token".into()));
+    }
+
+    let name = profile
+        .get("name")
+        .and_then(|n| n.as_str())
+        .unwrap_or_default();
+    let link = crate::links::link_account(&data, &user.claims.sub, &body.player_tag, name).await?;
+    Ok(HttpResponse::Ok().json(link))
 }
 
 // ============================================================================
@@ -1467,60 +2431,72 @@ pub async fn get_user_player_accounts(
 // ============================================================================
 
 // Get Guild Info
+#[utoipa::path(
+    get,
+    path = "/api/guild",
+    tag = "status",
+    description = "Discord guild summary. ADMIN gets the full guild object.",
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Guild", body = crate::models::GuildInfo),
+        (status = 503, description = "Not yet cached", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_guild_info(
     data: web::Data<AppState>,
     opt_user: OptionalAuthenticatedUser,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let user_role = opt_user
         .user
         .as_ref()
         .and_then(|u| u.claims.role.as_deref());
 
-    let result =
-        sqlx::query_as::<_, (Vec<u8>, i32)>("SELECT body, status FROM cache WHERE key = $1")
-            .bind("coc:upstream:/api/guild")
-            .fetch_optional(&data.db_pool)
-            .await;
+    let result = sqlx::query_as::<_, (Vec<u8>, i32, i64)>(
+        "SELECT body, status, updated_at FROM cache WHERE key = $1",
+    )
+    .bind("coc:upstream:/api/guild")
+    .fetch_optional(&data.db_pool)
+    .await?;
 
     match result {
-        Ok(Some((body, status))) => {
+        Some((body, status, updated_at)) => {
+            record_cache_read(&data, "coc:upstream:/api/guild", updated_at);
+            let body = decode_cache_body(body);
             let json: serde_json::Value =
                 serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
 
-            if !has_required_role(user_role, "ADMIN")
-                && let Some(obj) = json.as_object()
-            {
-                let mut summary = serde_json::Map::new();
-                if let Some(count) = obj.get("membercount") {
-                    summary.insert("membercount".to_string(), count.clone());
-                }
-                if let Some(name) = obj.get("name") {
-                    summary.insert("name".to_string(), name.clone());
-                }
-                if let Some(icon) = obj.get("icon") {
-                    summary.insert("icon".to_string(), icon.clone());
-                }
-                return HttpResponse::Ok().json(summary);
+            if !has_required_role(user_role, "ADMIN") && json.is_object() {
+                let summary = crate::models::GuildInfo {
+                    name: json["name"].as_str().map(String::from),
+                    icon: json["icon"].as_str().map(String::from),
+                    membercount: json["membercount"].as_i64(),
+                };
+                return Ok(HttpResponse::Ok()
+                    .insert_header(LastModified(last_modified(updated_at)))
+                    .json(summary));
             }
 
             let status = actix_web::http::StatusCode::from_u16(status as u16)
                 .unwrap_or(actix_web::http::StatusCode::OK);
-            HttpResponse::build(status).json(json)
+            Ok(HttpResponse::build(status)
+                .insert_header(LastModified(last_modified(updated_at)))
+                .json(json))
         }
-        _ => HttpResponse::ServiceUnavailable().finish(),
+        None => Err(ApiError::NotYetCached),
     }
 }
 
 // Uptime stats helper
-async fn get_uptime_stats(pool: &sqlx::PgPool, api_name: &str) -> (i32, i32) {
+async fn get_uptime_stats(pool: &sqlx::PgPool, api_name: &str) -> (i32, i32, Option<String>) {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
         .as_secs() as i64;
     let one_day_ago = now - 24 * 60 * 60;
 
-    let rows = sqlx::query_as::<_, (i32,)>(
-        "SELECT latency_ms FROM latency_measurements 
+    let rows = sqlx::query_as::<_, (i32, Option<String>)>(
+        "SELECT latency_ms, error_kind FROM latency_measurements 
          WHERE api_name = $1 AND timestamp > $2 
          ORDER BY timestamp DESC",
     )
@@ -1532,127 +2508,1385 @@ async fn get_uptime_stats(pool: &sqlx::PgPool, api_name: &str) -> (i32, i32) {
     match rows {
         Ok(data) if !data.is_empty() => {
             let successes = data.iter().filter(|r| r.0 != -1).count() as i32;
-            let current_latency = data[0].0;
-            (current_latency, successes)
+            let (current_latency, current_error) = data[0].clone();
+            (current_latency, successes, current_error)
         }
-        _ => (-1, 0),
+        _ => (-1, 0, None),
     }
 }
 
 // Get Admin Status
+#[utoipa::path(
+    get,
+    path = "/api/admin/status",
+    tag = "admin",
+    description = "Current status of every latency probe, keyed by probe name, plus this instance (`instance`) and the current leader of the background jobs (`leader`).",
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Probe status and leader", body = crate::models::AdminStatus),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_admin_status(
     data: web::Data<AppState>,
     user: AuthenticatedUser,
-) -> impl Responder {
-    if !has_required_role(user.claims.role.as_deref(), "ADMIN") {
-        return HttpResponse::Forbidden().json(ErrorResponse {
-            error: "Access denied: Requires ADMIN role".into(),
-        });
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    let probes = crate::latency::fetch_enabled_probes(&data.db_pool).await?;
+
+    let mut status = std::collections::HashMap::new();
+    for probe in probes {
+        let (latency, uptime_minutes, last_error) =
+            get_uptime_stats(&data.db_pool, &probe.name).await;
+        status.insert(
+            probe.name,
+            crate::models::ProbeStatus {
+                status: if latency != -1 { "ONLINE" } else { "OFFLINE" }.to_string(),
+                latency: if latency != -1 { latency } else { 0 },
+                uptime_minutes,
+                error: last_error,
+            },
+        );
     }
 
-    let (coc_upstream_latency, coc_upstream_minutes) =
-        get_uptime_stats(&data.db_pool, "upstream_coc").await;
-    let (cr_upstream_latency, cr_upstream_minutes) =
-        get_uptime_stats(&data.db_pool, "upstream_cr").await;
-    let (coc_api_latency, coc_api_minutes) = get_uptime_stats(&data.db_pool, "supercell_coc").await;
-    let (cr_api_latency, cr_api_minutes) = get_uptime_stats(&data.db_pool, "supercell_cr").await;
-    let (website_latency, website_uptime_minutes) =
-        get_uptime_stats(&data.db_pool, "website").await;
-
-    HttpResponse::Ok().json(serde_json::json!({
-        "upstream_coc": {
-            "status": if coc_upstream_latency != -1 { "ONLINE" } else { "OFFLINE" },
-            "latency": if coc_upstream_latency != -1 { coc_upstream_latency } else { 0 },
-            "uptime_minutes": coc_upstream_minutes
-        },
-        "upstream_cr": {
-            "status": if cr_upstream_latency != -1 { "ONLINE" } else { "OFFLINE" },
-            "latency": if cr_upstream_latency != -1 { cr_upstream_latency } else { 0 },
-            "uptime_minutes": cr_upstream_minutes
-        },
-        "supercell_coc": {
-            "status": if coc_api_latency != -1 { "ONLINE" } else { "OFFLINE" },
-            "latency": if coc_api_latency != -1 { coc_api_latency } else { 0 },
-            "uptime_minutes": coc_api_minutes
-        },
-        "supercell_cr": {
-            "status": if cr_api_latency != -1 { "ONLINE" } else { "OFFLINE" },
-            "latency": if cr_api_latency != -1 { cr_api_latency } else { 0 },
-            "uptime_minutes": cr_api_minutes
+    // Only the leader runs background jobs, so this tells which instance to look at
+    let leader = sqlx::query_as::<_, crate::models::LeaderLease>(
+        "SELECT instance_id, acquired_at, heartbeat_at FROM leader_lease WHERE id = 1",
+    )
+    .fetch_optional(&data.db_pool)
+    .await?;
+
+    Ok(HttpResponse::Ok().json(crate::models::AdminStatus {
+        instance: crate::models::InstanceInfo {
+            id: data.leader.instance_id.clone(),
+            started_at: data.leader.started_at,
+            is_leader: data.leader.is_leader(),
         },
-        "website": {
-            "status": if website_latency != -1 { "ONLINE" } else { "OFFLINE" },
-            "latency": if website_latency != -1 { website_latency } else { 0 },
-            "uptime_minutes": website_uptime_minutes
-        }
+        leader,
+        probes: status,
     }))
 }
 
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LatencyQuery {
+    /// Relative range ending now, e.g. "24h", "7d", "1y" (default 24h)
+    range: Option<String>,
+    /// raw, hour, day or auto (default auto)
+    resolution: Option<String>,
+    /// Restrict to a single probe
+    api: Option<String>,
+}
+
 // Get Latency History
+#[utoipa::path(
+    get,
+    path = "/api/admin/latency",
+    tag = "admin",
+    description = "Latency samples or hourly/daily rollups.",
+    params(LatencyQuery),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Latency history", body = [crate::models::LatencyPoint]),
+        (status = 400, description = "Invalid range or resolution", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+    )
+)]
 pub async fn get_latency_history(
     data: web::Data<AppState>,
+    query: web::Query<LatencyQuery>,
     user: AuthenticatedUser,
-) -> impl Responder {
-    if !has_required_role(user.claims.role.as_deref(), "ADMIN") {
-        return HttpResponse::Forbidden().json(ErrorResponse {
-            error: "Access denied: Requires ADMIN role".into(),
+) -> Result<HttpResponse, ApiError> {
+    use crate::models::{LatencyPoint, LatencyRollup, LatencySample};
+
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    let range_secs = match query.range.as_deref() {
+        Some(r) => match crate::latency::parse_range(r) {
+            Some(secs) => secs.min(365 * crate::latency::DAY_SECS),
+            None => {
+                return Err(ApiError::BadRequest(
+                    "Invalid range (expected e.g. 90m, 24h, 7d, 1y)".into(),
+                ));
+            }
+        },
+        None => 24 * crate::latency::HOUR_SECS,
+    };
+
+    let resolution = match query.resolution.as_deref().unwrap_or("auto") {
+        "auto" => crate::latency::auto_resolution(range_secs),
+        "raw" => "raw",
+        "hour" => "hour",
+        "day" => "day",
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Invalid resolution (expected raw, hour, day or auto)".into(),
+            ));
+        }
+    };
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let since = now - range_secs;
+
+    if resolution == "raw" {
+        let measurements = sqlx::query_as::<_, (String, i32, i64, Option<i32>, Option<String>)>(
+            "SELECT api_name, latency_ms, timestamp, status_code, error_kind FROM latency_measurements
+             WHERE timestamp >= $1 AND ($2::TEXT IS NULL OR api_name = $2)
+             ORDER BY timestamp ASC",
+        )
+        .bind(since)
+        .bind(&query.api)
+        .fetch_all(&data.db_pool)
+        .await?;
+
+        let data: Vec<LatencyPoint> = measurements
+            .into_iter()
+            .map(
+                |(api_name, latency_ms, timestamp, status_code, error_kind)| {
+                    LatencyPoint::Sample(LatencySample {
+                        api: api_name,
+                        latency: latency_ms,
+                        timestamp,
+                        status: status_code,
+                        error: error_kind,
+                    })
+                },
+            )
+            .collect();
+        return Ok(HttpResponse::Ok().json(data));
+    }
+
+    let rollups = sqlx::query_as::<
+        _,
+        (String, i64, i32, i32, Option<i32>, Option<i32>, Option<i32>),
+    >(
+        "SELECT api_name, bucket_start, samples, successes, p50_ms, p95_ms, avg_ms FROM latency_rollups
+         WHERE resolution = $1 AND bucket_start >= $2 AND ($3::TEXT IS NULL OR api_name = $3)
+         ORDER BY bucket_start ASC",
+    )
+    .bind(resolution)
+    .bind(since)
+    .bind(&query.api)
+    .fetch_all(&data.db_pool)
+    .await?;
+
+    let data: Vec<LatencyPoint> = rollups
+        .into_iter()
+        .map(
+            |(api_name, bucket_start, samples, successes, p50, p95, avg)| {
+                let availability = if samples > 0 {
+                    successes as f64 / samples as f64
+                } else {
+                    0.0
+                };
+                LatencyPoint::Rollup(LatencyRollup {
+                    api: api_name,
+                    latency: p50.unwrap_or(-1),
+                    timestamp: bucket_start,
+                    resolution: resolution.to_string(),
+                    samples,
+                    p50,
+                    p95,
+                    avg,
+                    availability,
+                })
+            },
+        )
+        .collect();
+    Ok(HttpResponse::Ok().json(data))
+}
+
+// Get Season Calendar
+#[utoipa::path(
+    get,
+    path = "/api/calendar",
+    tag = "status",
+    description = "Current trophy seasons and the latest CWL, raid weekend and clan games (UTC boundaries).",
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Calendar", body = crate::models::SeasonCalendar),
+    )
+)]
+pub async fn get_calendar() -> impl Responder {
+    let now = chrono::Utc::now();
+    HttpResponse::Ok().json(crate::models::SeasonCalendar {
+        coc_season: crate::calendar::coc_season(now),
+        cr_season: crate::calendar::cr_season(now),
+        cwl: crate::calendar::cwl(now),
+        raid_weekend: crate::calendar::raid_weekend(now),
+        clan_games: crate::calendar::clan_games(now),
+    })
+}
+
+// Get Public Status (no auth, no internal URLs or error messages)
+#[utoipa::path(
+    get,
+    path = "/api/status",
+    tag = "status",
+    description = "Service availability and incidents of the last 30 days.",
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Status", body = crate::models::PublicStatus),
+        (status = 500, description = "Database error", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_public_status(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    use crate::incidents::service_label;
+    use crate::models::{Incident, PublicIncident, PublicService, PublicStatus};
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    let probes = crate::latency::fetch_enabled_probes(&data.db_pool).await?;
+
+    let incidents = sqlx::query_as::<_, Incident>(
+        "SELECT id, api_name, started_at, ended_at, failure_count, error_kind, affected_endpoints
+         FROM incidents WHERE ended_at IS NULL OR started_at > $1
+         ORDER BY started_at DESC LIMIT 50",
+    )
+    .bind(now - 30 * 24 * 60 * 60)
+    .fetch_all(&data.db_pool)
+    .await?;
+
+    let mut services = Vec::new();
+    for probe in &probes {
+        let (latency, _, _) = get_uptime_stats(&data.db_pool, &probe.name).await;
+
+        let availability_24h = sqlx::query_as::<_, (Option<f64>,)>(
+            "SELECT AVG(CASE WHEN latency_ms >= 0 THEN 1.0 ELSE 0.0 END)::DOUBLE PRECISION
+             FROM latency_measurements WHERE api_name = $1 AND timestamp > $2",
+        )
+        .bind(&probe.name)
+        .bind(now - 24 * 60 * 60)
+        .fetch_one(&data.db_pool)
+        .await
+        .ok()
+        .and_then(|r| r.0);
+
+        let availability_30d = sqlx::query_as::<_, (Option<f64>,)>(
+            "SELECT (SUM(successes)::DOUBLE PRECISION / NULLIF(SUM(samples), 0))
+             FROM latency_rollups WHERE api_name = $1 AND resolution = 'day' AND bucket_start > $2",
+        )
+        .bind(&probe.name)
+        .bind(now - 30 * 24 * 60 * 60)
+        .fetch_one(&data.db_pool)
+        .await
+        .ok()
+        .and_then(|r| r.0);
+
+        let has_open_incident = incidents
+            .iter()
+            .any(|i| i.api_name == probe.name && i.ended_at.is_none());
+        let status = if has_open_incident {
+            "OFFLINE"
+        } else if latency == -1 {
+            "DEGRADED"
+        } else {
+            "ONLINE"
+        };
+
+        services.push(PublicService {
+            service: probe.name.clone(),
+            label: service_label(&probe.name),
+            status: status.to_string(),
+            availability_24h,
+            availability_30d,
         });
     }
 
-    let measurements = sqlx::query_as::<_, (String, i32, i64)>(
-        "SELECT api_name, latency_ms, timestamp FROM latency_measurements ORDER BY timestamp ASC",
+    let incidents: Vec<PublicIncident> = incidents
+        .into_iter()
+        .map(|i| PublicIncident {
+            id: i.id,
+            label: service_label(&i.api_name),
+            service: i.api_name,
+            started_at: i.started_at,
+            ended_at: i.ended_at,
+            duration_secs: i.ended_at.unwrap_or(now) - i.started_at,
+            ongoing: i.ended_at.is_none(),
+            error_kind: i.error_kind,
+            affected_endpoints: serde_json::from_str(&i.affected_endpoints).unwrap_or_default(),
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(PublicStatus {
+        services,
+        incidents,
+    }))
+}
+
+// List Background Jobs
+#[utoipa::path(
+    get,
+    path = "/api/admin/jobs",
+    tag = "admin",
+    description = "Registered background jobs with their last runs.",
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Jobs", body = [crate::models::JobInfo]),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_jobs(
+    data: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    use crate::models::{JobInfo, JobRun};
+
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    let mut jobs = Vec::new();
+    for job in &data.jobs.jobs {
+        let last_run = sqlx::query_as::<_, JobRun>(
+            "SELECT * FROM job_runs WHERE job_name = $1 ORDER BY started_at DESC, id DESC LIMIT 1",
+        )
+        .bind(job.name)
+        .fetch_optional(&data.db_pool)
+        .await
+        .unwrap_or_default();
+
+        let last_success = sqlx::query_as::<_, JobRun>(
+            "SELECT * FROM job_runs WHERE job_name = $1 AND status = 'success'
+             ORDER BY started_at DESC, id DESC LIMIT 1",
+        )
+        .bind(job.name)
+        .fetch_optional(&data.db_pool)
+        .await
+        .unwrap_or_default();
+
+        jobs.push(JobInfo {
+            name: job.name.to_string(),
+            description: job.description.to_string(),
+            schedule: job.schedule.describe(),
+            running: job.is_running(),
+            last_run,
+            last_success,
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(jobs))
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobRunsQuery {
+    limit: Option<i64>,
+}
+
+// Get Run History of a Job
+#[utoipa::path(
+    get,
+    path = "/api/admin/jobs/{name}/runs",
+    tag = "admin",
+    description = "Run history of a job.",
+    params(("name" = String, Path, description = "Job name"), JobRunsQuery),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Runs", body = [crate::models::JobRun]),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_job_runs(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+    query: web::Query<JobRunsQuery>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    use crate::models::JobRun;
+
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    let runs = sqlx::query_as::<_, JobRun>(
+        "SELECT * FROM job_runs WHERE job_name = $1 ORDER BY started_at DESC, id DESC LIMIT $2",
     )
+    .bind(name.as_str())
+    .bind(query.limit.unwrap_or(50).clamp(1, 500))
     .fetch_all(&data.db_pool)
+    .await?;
+
+    Ok(HttpResponse::Ok().json(runs))
+}
+
+// Trigger a Job Manually
+#[utoipa::path(
+    post,
+    path = "/api/admin/jobs/{name}/run",
+    tag = "admin",
+    description = "Starts a job (queued on followers).",
+    params(("name" = String, Path, description = "Job name")),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 202, description = "Run started", body = crate::models::JobStarted),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+        (status = 409, description = "Job is already running", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn trigger_job(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    use crate::jobs::{JobStartError, start_job};
+
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    match start_job(&data, &name, "manual").await {
+        Ok(run_id) => Ok(HttpResponse::Accepted().json(crate::models::JobStarted { run_id })),
+        Err(JobStartError::NotFound) => Err(ApiError::NotFound(format!("Unknown job '{}'", name))),
+        Err(JobStartError::AlreadyRunning) => Err(ApiError::Conflict(format!(
+            "Job '{}' is already running",
+            name
+        ))),
+        Err(JobStartError::Database(e)) => Err(ApiError::Database(e)),
+    }
+}
+
+// Refresh a Single Clan Immediately
+#[utoipa::path(
+    post,
+    path = "/api/admin/{game}/clans/{tag}/refresh",
+    tag = "admin",
+    description = "Refreshes all cache entries of one clan immediately.",
+    params(("game" = String, Path, description = "coc or cr"), ("tag" = String, Path, description = "Clan or player tag, with or without a leading '#' (%23)")),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Clan refreshed", body = crate::models::ClanRefreshed),
+        (status = 400, description = "Invalid tag", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell or bot API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn refresh_clan_now(
+    data: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    let (game, tag) = path.into_inner();
+    let Some(game) = parse_game(&game) else {
+        return Err(ApiError::NotFound(format!("Unknown game '{}'", game)));
+    };
+    validate_tag(&tag)?;
+    let tag = if tag.starts_with('#') {
+        tag
+    } else {
+        format!("#{}", tag)
+    };
+
+    let started_at = chrono::Utc::now().timestamp();
+    let result = crate::background::refresh_clan(&data, game, &tag).await;
+    let (status, err, items) = match &result {
+        Ok(()) => ("success", None, 1i64),
+        Err(e) => ("failed", Some(e.clone()), 0i64),
+    };
+
+    let _ = sqlx::query(
+        "INSERT INTO job_runs (job_name, target, trigger, status, error, items_processed, started_at, finished_at)
+         VALUES ('refresh_clan', $1, 'manual', $2, $3, $4, $5, $6)",
+    )
+    .bind(format!("{}:{}", get_cache_prefix(game), tag))
+    .bind(status)
+    .bind(&err)
+    .bind(items)
+    .bind(started_at)
+    .bind(chrono::Utc::now().timestamp())
+    .execute(&data.db_pool)
     .await;
 
-    match measurements {
-        Ok(rows) => {
-            let data: Vec<serde_json::Value> = rows
-                .into_iter()
-                .map(|(api_name, latency_ms, timestamp)| {
-                    serde_json::json!({
-                        "api": api_name,
-                        "latency": latency_ms,
-                        "timestamp": timestamp
-                    })
-                })
-                .collect();
-            HttpResponse::Ok().json(data)
-        }
-        Err(e) => {
-            error!("Database error fetching latency: {:?}", e);
-            HttpResponse::InternalServerError().finish()
+    match result {
+        Ok(()) => Ok(HttpResponse::Ok().json(crate::models::ClanRefreshed {
+            status: status.to_string(),
+            tag,
+        })),
+        Err(e) => Err(ApiError::UpstreamUnavailable(e)),
+    }
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CacheListQuery {
+    /// Key prefix, e.g. "coc:upstream:" or "cr:supercell:/players/"
+    prefix: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+// Browse Cache Entries
+#[utoipa::path(
+    get,
+    path = "/api/admin/cache",
+    tag = "admin",
+    description = "Browse cache entries by key prefix.",
+    params(CacheListQuery),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Cache entries", body = crate::models::CacheEntryList),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_cache_entries(
+    data: web::Data<AppState>,
+    query: web::Query<CacheListQuery>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    use crate::models::{CacheEntryList, CacheEntrySummary};
+
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    let prefix = query.prefix.clone().unwrap_or_default();
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let now = chrono::Utc::now().timestamp();
+
+    // starts_with avoids escaping LIKE wildcards (keys contain "%23")
+    let (total,) =
+        sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM cache WHERE starts_with(key, $1)")
+            .bind(&prefix)
+            .fetch_one(&data.db_pool)
+            .await?;
+
+    let entries = sqlx::query_as::<_, (String, i32, i32, i64)>(
+        "SELECT key, octet_length(body), status, updated_at FROM cache
+         WHERE starts_with(key, $1) ORDER BY key LIMIT $2 OFFSET $3",
+    )
+    .bind(&prefix)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&data.db_pool)
+    .await?;
+
+    let entries = entries
+        .into_iter()
+        .map(|(key, size, status, updated_at)| CacheEntrySummary {
+            key,
+            size,
+            status,
+            updated_at,
+            age_secs: now - updated_at,
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(CacheEntryList { total, entries }))
+}
+
+// Cache Size Report
+#[utoipa::path(
+    get,
+    path = "/api/admin/cache/stats",
+    tag = "admin",
+    description = "Cache size report per key class.",
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Cache stats", body = crate::models::CacheStats),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_cache_stats(
+    data: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    let stats = crate::cache_gc::cache_stats(&data)
+        .await
+        .map_err(ApiError::Internal)?;
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CacheKeyQuery {
+    key: Option<String>,
+    prefix: Option<String>,
+}
+
+// Get Decoded Cache Entry
+#[utoipa::path(
+    get,
+    path = "/api/admin/cache/entry",
+    tag = "admin",
+    description = "Decoded body and metadata of a cache entry.",
+    params(CacheKeyQuery),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Cache entry", body = crate::models::CacheEntry),
+        (status = 400, description = "Missing key", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Not found", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_cache_entry(
+    data: web::Data<AppState>,
+    query: web::Query<CacheKeyQuery>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    let Some(key) = query.key.as_deref() else {
+        return Err(ApiError::BadRequest("Missing 'key' parameter".into()));
+    };
+
+    let entry = sqlx::query_as::<_, (Vec<u8>, i32, i64)>(
+        "SELECT body, status, updated_at FROM cache WHERE key = $1",
+    )
+    .bind(key)
+    .fetch_optional(&data.db_pool)
+    .await?;
+
+    match entry {
+        Some((stored, status, updated_at)) => {
+            let body = decode_cache_body(stored.clone());
+            // Show JSON bodies as JSON, anything else as (lossy) text
+            let decoded = serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_else(|_| {
+                serde_json::Value::String(String::from_utf8_lossy(&body).into_owned())
+            });
+            Ok(HttpResponse::Ok().json(crate::models::CacheEntry {
+                key: key.to_string(),
+                size: stored.len() as i64,
+                decoded_size: body.len() as i64,
+                status,
+                updated_at,
+                age_secs: chrono::Utc::now().timestamp() - updated_at,
+                body: decoded,
+            }))
         }
+        None => Err(ApiError::NotFound(format!(
+            "Cache entry '{}' not found",
+            key
+        ))),
     }
 }
 
-pub async fn get_side_clans(data: web::Data<AppState>) -> impl Responder {
+// Invalidate Cache Entries (by exact key or prefix)
+#[utoipa::path(
+    delete,
+    path = "/api/admin/cache",
+    tag = "admin",
+    description = "Invalidate a cache entry by key or all entries with a prefix.",
+    params(CacheKeyQuery),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Deleted entries", body = crate::models::CacheDeleted),
+        (status = 400, description = "Neither key nor prefix given", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn delete_cache_entries(
+    data: web::Data<AppState>,
+    query: web::Query<CacheKeyQuery>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    let result = match (query.key.as_deref(), query.prefix.as_deref()) {
+        (Some(key), None) => {
+            sqlx::query("DELETE FROM cache WHERE key = $1")
+                .bind(key)
+                .execute(&data.db_pool)
+                .await
+        }
+        // An empty prefix would wipe the whole cache
+        (None, Some(prefix)) if !prefix.is_empty() => {
+            sqlx::query("DELETE FROM cache WHERE starts_with(key, $1)")
+                .bind(prefix)
+                .execute(&data.db_pool)
+                .await
+        }
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Specify either 'key' or a non-empty 'prefix'".into(),
+            ));
+        }
+    }?;
+
+    log::info!(
+        "Admin {} deleted {} cache entries ({:?})",
+        user.claims.sub,
+        result.rows_affected(),
+        query.key.as_deref().or(query.prefix.as_deref())
+    );
+    Ok(HttpResponse::Ok().json(crate::models::CacheDeleted {
+        deleted: result.rows_affected(),
+    }))
+}
+
+// Force Re-Fetch of a Cache Entry
+#[utoipa::path(
+    post,
+    path = "/api/admin/cache/refresh",
+    tag = "admin",
+    description = "Re-fetches a cache entry from its source.",
+    params(CacheKeyQuery),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("ADMIN"))),
+    responses(
+        (status = 200, description = "Entry refreshed", body = crate::models::CacheRefreshed),
+        (status = 400, description = "Missing or invalid key", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell or bot API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn refresh_cache_entry(
+    data: web::Data<AppState>,
+    query: web::Query<CacheKeyQuery>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "ADMIN")?;
+
+    let Some(key) = query.key.as_deref() else {
+        return Err(ApiError::BadRequest("Missing 'key' parameter".into()));
+    };
+    let Some((game, source, url_path)) = crate::utils::parse_cache_key(key) else {
+        return Err(ApiError::BadRequest(format!("Invalid cache key '{}'", key)));
+    };
+
+    let body = if source == "upstream" {
+        update_upstream_cache(&data, game, url_path).await?
+    } else {
+        crate::utils::update_supercell_cache(&data, game, url_path).await?
+    };
+
+    Ok(HttpResponse::Ok().json(crate::models::CacheRefreshed {
+        key: key.to_string(),
+        size: body.len() as i64,
+        updated_at: chrono::Utc::now().timestamp(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sideclans",
+    tag = "coc",
+    description = "Side clans with their CWL history.",
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Side clans", body = [crate::models::SideClanCwlHistory]),
+        (status = 500, description = "Database error", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_side_clans(data: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
     use crate::models::{SideClan, SideClanCWLStats, SideClanCwlHistory};
 
     let clans_query =
         sqlx::query_as::<_, SideClan>("SELECT clan_tag, name, belongs_to, display_index, badge_url FROM side_clans ORDER BY CASE WHEN display_index = 0 THEN 1 ELSE 0 END, display_index ASC, name ASC")
             .fetch_all(&data.db_pool)
-            .await;
+            .await?;
+
+    let mut results = Vec::new();
+    for clan in clans_query {
+        let history = sqlx::query_as::<_, SideClanCWLStats>(
+            "SELECT clan_tag, season, league_id, league_name, league_badge_url, rank FROM side_clans_cwl_stats WHERE clan_tag = $1 ORDER BY season DESC"
+        )
+        .bind(&clan.clan_tag)
+        .fetch_all(&data.db_pool)
+        .await
+        .unwrap_or_default();
+
+        results.push(SideClanCwlHistory { clan, history });
+    }
+    Ok(HttpResponse::Ok().json(results))
+}
+
+// ============================================================================
+// SEARCH HANDLERS
+// ============================================================================
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Player name, clan name, tag or Discord nickname (at least 2 characters)
+    q: String,
+    /// coc or cr (default both)
+    game: Option<String>,
+    /// clan or player (default both)
+    kind: Option<String>,
+    /// Max. results (default 20, at most 50)
+    limit: Option<i64>,
+}
 
-    match clans_query {
-        Ok(clans) => {
-            let mut results = Vec::new();
-            for clan in clans {
-                let history = sqlx::query_as::<_, SideClanCWLStats>(
-                    "SELECT clan_tag, season, league_id, league_name, league_badge_url, rank FROM side_clans_cwl_stats WHERE clan_tag = $1 ORDER BY season DESC"
+// Fuzzy Search over Family Players and Clans
+#[utoipa::path(
+    get,
+    path = "/api/search",
+    tag = "search",
+    description = "Fuzzy search over player names, clan names and tags of both games. Discord nicknames are matched and returned for MEMBER, hidden members only for COLEADER.",
+    params(SearchQuery),
+    security((), ("auth_token" = [])),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Matches, best first", body = [crate::models::SearchResult]),
+        (status = 400, description = "Query too short or invalid filter", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn search(
+    data: web::Data<AppState>,
+    query: web::Query<SearchQuery>,
+    opt_user: OptionalAuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    let q = query.q.trim();
+    if q.chars().count() < 2 {
+        return Err(ApiError::BadRequest(
+            "Search query must have at least 2 characters".into(),
+        ));
+    }
+    let game = match query.game.as_deref() {
+        None => None,
+        Some("coc") => Some(GameType::ClashOfClans),
+        Some("cr") => Some(GameType::ClashRoyale),
+        Some(_) => {
+            return Err(ApiError::BadRequest(
+                "Invalid game (expected coc or cr)".into(),
+            ));
+        }
+    };
+    let kind = match query.kind.as_deref() {
+        None | Some("clan") | Some("player") => query.kind.as_deref(),
+        Some(_) => {
+            return Err(ApiError::BadRequest(
+                "Invalid kind (expected clan or player)".into(),
+            ));
+        }
+    };
+    let limit = query.limit.unwrap_or(20).clamp(1, 50);
+    let user_role = opt_user
+        .user
+        .as_ref()
+        .and_then(|u| u.claims.role.as_deref());
+
+    let results = crate::search::search(&data, q, game, kind, limit, user_role).await?;
+    Ok(HttpResponse::Ok().json(results))
+}
+
+// ============================================================================
+// APPLICATION HANDLERS
+// ============================================================================
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApplicationQuery {
+    /// pending, waitlisted, accepted, rejected or withdrawn, comma-separated (default pending,waitlisted)
+    status: Option<String>,
+    /// coc or cr (default both)
+    game: Option<String>,
+}
+
+// 1. Submit Application
+#[utoipa::path(
+    post,
+    path = "/api/applications",
+    tag = "applications",
+    description = "Applies with an own (linked or verified) player account to join the family. CoC accounts are checked against the requirements of all family clans and the best fitting clan is suggested.",
+    request_body = crate::models::ApplicationCreate,
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("AUTHENTICATED"))),
+    responses(
+        (status = 201, description = "Created application", body = crate::models::Application),
+        (status = 400, description = "Invalid game or tag, or the account is not linked to the user", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Player not found", body = crate::errors::ApiErrorBody),
+        (status = 409, description = "The account already has an open application", body = crate::errors::ApiErrorBody),
+        (status = 502, description = "Supercell API failed", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn submit_application(
+    data: web::Data<AppState>,
+    body: web::Json<crate::models::ApplicationCreate>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    let game = parse_game(&body.game)
+        .ok_or_else(|| ApiError::BadRequest("Invalid game (expected coc or cr)".into()))?;
+    let message = body
+        .message
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty());
+    if message.is_some_and(|m| m.chars().count() > 2000) {
+        return Err(ApiError::BadRequest(
+            "Message must not exceed 2000 characters".into(),
+        ));
+    }
+
+    validate_tag(&body.player_tag)?;
+    if !crate::applications::is_own_account(&data, game, &user, &body.player_tag).await? {
+        return Err(ApiError::BadRequest(
+            "You can only apply with your own linked accounts".into(),
+        ));
+    }
+
+    let profile = get_player_profile(&data, game, &body.player_tag).await?;
+    let application =
+        crate::applications::submit(&data, game, &profile, &user.claims.sub, message).await?;
+    Ok(HttpResponse::Created().json(application))
+}
+
+// 2. Get Own Applications
+#[utoipa::path(
+    get,
+    path = "/api/applications/me",
+    tag = "applications",
+    description = "Applications submitted by the logged in user, newest first.",
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("AUTHENTICATED"))),
+    responses(
+        (status = 200, description = "Own applications", body = [crate::models::Application]),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_my_applications(
+    data: web::Data<AppState>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    let applications = crate::applications::list_for_user(&data, &user.claims.sub).await?;
+    Ok(HttpResponse::Ok().json(applications))
+}
+
+// 3. Withdraw Own Application
+#[utoipa::path(
+    post,
+    path = "/api/applications/{id}/withdraw",
+    tag = "applications",
+    description = "Withdraws an open application of the logged in user.",
+    params(("id" = i64, Path, description = "Application ID")),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("AUTHENTICATED"))),
+    responses(
+        (status = 200, description = "Withdrawn application", body = crate::models::Application),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "No own application with this ID", body = crate::errors::ApiErrorBody),
+        (status = 409, description = "Application already closed", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn withdraw_application(
+    data: web::Data<AppState>,
+    id: web::Path<i64>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    match crate::applications::get(&data, id).await? {
+        Some(a) if a.discord_id == user.claims.sub => {}
+        _ => return Err(ApiError::NotFound("Application not found".into())),
+    }
+    let application =
+        crate::applications::transition(&data, id, "withdraw", None, None, &user.claims.sub)
+            .await?;
+    Ok(HttpResponse::Ok().json(application))
+}
+
+// 4. Get Application Queue
+#[utoipa::path(
+    get,
+    path = "/api/applications",
+    tag = "applications",
+    description = "Applications to review, oldest first. Defaults to the open ones (pending and waitlisted).",
+    params(ApplicationQuery),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("COLEADER"))),
+    responses(
+        (status = 200, description = "Applications, oldest first", body = [crate::models::Application]),
+        (status = 400, description = "Invalid filter", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_applications(
+    data: web::Data<AppState>,
+    query: web::Query<ApplicationQuery>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "COLEADER")?;
+    let game = match query.game.as_deref() {
+        None => None,
+        Some(g) => Some(
+            parse_game(g)
+                .ok_or_else(|| ApiError::BadRequest("Invalid game (expected coc or cr)".into()))?,
+        ),
+    };
+    let statuses: Vec<&str> = match query.status.as_deref() {
+        None => crate::applications::OPEN_STATUSES.to_vec(),
+        Some(s) => s.split(',').map(str::trim).collect(),
+    };
+    if let Some(invalid) = statuses
+        .iter()
+        .find(|s| !crate::applications::STATUSES.contains(s))
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid status '{}'",
+            invalid
+        )));
+    }
+
+    let applications = crate::applications::list(&data, game, &statuses).await?;
+    Ok(HttpResponse::Ok().json(applications))
+}
+
+// 5. Get Application with History
+#[utoipa::path(
+    get,
+    path = "/api/applications/{id}",
+    tag = "applications",
+    description = "An application with its eligibility check and full history. Visible to the applicant and COLEADER.",
+    params(("id" = i64, Path, description = "Application ID")),
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("AUTHENTICATED"))),
+    responses(
+        (status = 200, description = "Application with history", body = crate::models::ApplicationDetail),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Application not found", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn get_application(
+    data: web::Data<AppState>,
+    id: web::Path<i64>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let application = crate::applications::get(&data, id)
+        .await?
+        .filter(|a| {
+            a.discord_id == user.claims.sub
+                || has_required_role(user.claims.role.as_deref(), "COLEADER")
+        })
+        .ok_or_else(|| ApiError::NotFound("Application not found".into()))?;
+
+    Ok(HttpResponse::Ok().json(crate::models::ApplicationDetail {
+        application,
+        eligibility: crate::applications::eligibility(&data, id).await?,
+        events: crate::applications::events(&data, id).await?,
+    }))
+}
+
+// 6. Decide on Application
+#[utoipa::path(
+    post,
+    path = "/api/applications/{id}/decision",
+    tag = "applications",
+    description = "Accepts, rejects or waitlists (assign) an open application. Assigning or accepting requires a family clan; accept defaults to the assigned clan.",
+    params(("id" = i64, Path, description = "Application ID")),
+    request_body = crate::models::ApplicationDecision,
+    security(("auth_token" = [])),
+    extensions(("x-required-role" = json!("COLEADER"))),
+    responses(
+        (status = 200, description = "Updated application", body = crate::models::Application),
+        (status = 400, description = "Invalid action or clan", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Not logged in", body = crate::errors::ApiErrorBody),
+        (status = 403, description = "Missing role", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Application not found", body = crate::errors::ApiErrorBody),
+        (status = 409, description = "Application already closed", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn decide_application(
+    data: web::Data<AppState>,
+    id: web::Path<i64>,
+    body: web::Json<crate::models::ApplicationDecision>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ApiError> {
+    require_role(user.claims.role.as_deref(), "COLEADER")?;
+    if !matches!(body.action.as_str(), "accept" | "reject" | "assign") {
+        return Err(ApiError::BadRequest(
+            "Invalid action (expected accept, reject or assign)".into(),
+        ));
+    }
+    if let Some(tag) = &body.clan_tag {
+        validate_tag(tag)?;
+    }
+    let note = body
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty());
+
+    let application = crate::applications::transition(
+        &data,
+        id.into_inner(),
+        &body.action,
+        body.clan_tag.as_deref(),
+        note,
+        &user.claims.sub,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(application))
+}
+
+// ============================================================================
+// DISCORD INTERACTION HANDLERS
+// ============================================================================
+
+// Discord Interactions (Slash Commands)
+#[utoipa::path(
+    post,
+    path = "/discord/interactions",
+    tag = "discord",
+    description = "Interactions endpoint of the Discord application, verified with its Ed25519 public key. Serves /player, /clan, /kickpoints and /cwl with the role and linked accounts of the calling Discord user. Commands are answered with a deferred response that is edited once the data is loaded; /player and /kickpoints replies are only visible to the caller.",
+    request_body(content = serde_json::Value, description = "Discord interaction"),
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Interaction response"),
+        (status = 400, description = "Invalid interaction", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Invalid request signature", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Interactions not configured", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn discord_interactions(
+    data: web::Data<AppState>,
+    req: actix_web::HttpRequest,
+    body: Bytes,
+) -> Result<HttpResponse, ApiError> {
+    use crate::interactions::{APPLICATION_COMMAND, PING};
+
+    let Some(key) = &data.discord_public_key else {
+        return Err(ApiError::NotFound(
+            "Discord interactions are not configured".into(),
+        ));
+    };
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+    };
+    if !crate::interactions::verify_signature(
+        key,
+        header("X-Signature-Ed25519"),
+        header("X-Signature-Timestamp"),
+        &body,
+        chrono::Utc::now().timestamp(),
+    ) {
+        return Err(ApiError::Unauthorized("Invalid request signature"));
+    }
+
+    let interaction: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|_| ApiError::BadRequest("Invalid interaction".into()))?;
+    match interaction.get("type").and_then(|t| t.as_i64()) {
+        Some(PING) => Ok(HttpResponse::Ok().json(crate::interactions::pong())),
+        Some(APPLICATION_COMMAND) => {
+            let command = match crate::interactions::parse_command(&interaction) {
+                Ok(command) => command,
+                Err(e) => return Ok(HttpResponse::Ok().json(crate::interactions::ephemeral(&e))),
+            };
+            let Some(response_path) = crate::interactions::original_response_path(&interaction)
+            else {
+                return Err(ApiError::BadRequest("Invalid interaction".into()));
+            };
+            let private = command.is_private();
+
+            // Answer within Discord's deadline and edit in the result once it is ready
+            let data = data.clone();
+            actix_web::rt::spawn(async move {
+                let reply = match run_interaction_command(&data, &interaction, command).await {
+                    Ok(embed) => crate::interactions::followup_embed(embed),
+                    Err(e @ ApiError::Forbidden(_)) => {
+                        crate::interactions::followup_text(&format!(
+                            "{}. Link your accounts on the website to use this command.",
+                            e.public_message()
+                        ))
+                    }
+                    Err(e) => {
+                        warn!("Interaction command failed: {}", e);
+                        crate::interactions::followup_text(&e.public_message())
+                    }
+                };
+                match crate::fixtures::patch(
+                    &data,
+                    crate::fixtures::ExternalApi::Discord,
+                    &response_path,
+                    "",
+                    &reply,
                 )
-                .bind(&clan.clan_tag)
-                .fetch_all(&data.db_pool)
                 .await
-                .unwrap_or_default();
+                {
+                    Ok(res) if res.is_success() => {}
+                    Ok(res) => warn!("Editing interaction response failed ({})", res.status),
+                    Err(e) => warn!("Editing interaction response failed: {}", e),
+                }
+            });
+            Ok(HttpResponse::Ok().json(crate::interactions::deferred(private)))
+        }
+        _ => Err(ApiError::BadRequest("Unsupported interaction type".into())),
+    }
+}
 
-                results.push(SideClanCwlHistory { clan, history });
-            }
-            HttpResponse::Ok().json(results)
+// Run a slash command through the same handler logic as the website, as the calling
+// Discord user (role and linked accounts from the users table)
+async fn run_interaction_command(
+    data: &web::Data<AppState>,
+    interaction: &serde_json::Value,
+    command: crate::interactions::Command,
+) -> Result<serde_json::Value, ApiError> {
+    use crate::interactions::Command;
+
+    let caller = match crate::interactions::caller_id(interaction) {
+        Some(sub) => Some(
+            crate::auth::load_user(
+                data,
+                crate::auth::Claims {
+                    sub,
+                    role: None,
+                    exp: 0,
+                },
+            )
+            .await,
+        ),
+        None => None,
+    };
+    let caller = OptionalAuthenticatedUser { user: caller };
+
+    match command {
+        Command::Player { game, tag } => {
+            let player = response_json(get_player_impl(data, &tag, caller, game).await?).await?;
+            Ok(crate::interactions::player_embed(game, &player))
         }
-        Err(e) => {
-            error!("Database error fetching side clans: {:?}", e);
-            HttpResponse::InternalServerError().finish()
+        Command::Clan { game, tag } => {
+            let clan = response_json(get_clan_info_impl(data, &tag, game).await?).await?;
+            Ok(crate::interactions::clan_embed(&clan))
+        }
+        Command::Kickpoints { game, tag } => {
+            let summary =
+                response_json(get_player_kickpoints_impl(data, &tag, caller, game).await?).await?;
+            Ok(crate::interactions::kickpoints_embed(&tag, &summary))
+        }
+        Command::Cwl => {
+            let clans = response_json(get_side_clans(data.clone()).await?).await?;
+            let clans: Vec<crate::models::SideClanCwlHistory> =
+                serde_json::from_value(clans).unwrap_or_default();
+            let season = crate::calendar::cwl(chrono::Utc::now()).key;
+            Ok(crate::interactions::cwl_embed(&season, &clans))
+        }
+    }
+}
+
+async fn response_json(res: HttpResponse) -> Result<serde_json::Value, ApiError> {
+    let body = actix_web::body::to_bytes(res.into_body())
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to read response: {}", e)))?;
+    serde_json::from_slice(&body)
+        .map_err(|e| ApiError::Internal(format!("Invalid response JSON: {}", e)))
+}
+
+// ============================================================================
+// BOT WEBHOOK HANDLERS
+// ============================================================================
+
+// Bot Change Webhook
+#[utoipa::path(
+    post,
+    path = "/api/webhooks/{game}",
+    tag = "webhooks",
+    description = "Change events of the CoC or CR bot (player_updated, kickpoint_added, link_changed, clan_config_changed). Refreshes the affected cached bot responses and re-syncs the users row of changed links. Authenticated with X-Bot-Timestamp and X-Bot-Signature (HMAC-SHA256 of \"{timestamp}.{body}\").",
+    params(("game" = String, Path, description = "coc or cr")),
+    request_body = crate::models::BotEvent,
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses(
+        (status = 200, description = "Refreshed and invalidated keys", body = crate::models::WebhookResult),
+        (status = 400, description = "Invalid event", body = crate::errors::ApiErrorBody),
+        (status = 401, description = "Invalid or expired signature", body = crate::errors::ApiErrorBody),
+        (status = 404, description = "Unknown game or webhook not configured", body = crate::errors::ApiErrorBody),
+    )
+)]
+pub async fn bot_webhook(
+    data: web::Data<AppState>,
+    game: web::Path<String>,
+    req: actix_web::HttpRequest,
+    body: Bytes,
+) -> Result<HttpResponse, ApiError> {
+    let game = parse_game(&game).ok_or_else(|| ApiError::NotFound("Unknown game".into()))?;
+    let secret = match game {
+        GameType::ClashOfClans => &data.coc_webhook_secret,
+        GameType::ClashRoyale => &data.cr_webhook_secret,
+    };
+    if secret.is_empty() {
+        return Err(ApiError::NotFound("Webhook not configured".into()));
+    }
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+    };
+    if !crate::webhooks::verify_signature(
+        secret,
+        header("X-Bot-Timestamp"),
+        &body,
+        header("X-Bot-Signature"),
+        chrono::Utc::now().timestamp(),
+    ) {
+        return Err(ApiError::Unauthorized("Invalid or expired signature"));
+    }
+
+    let event: crate::models::BotEvent = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid event: {}", e)))?;
+    let paths = crate::webhooks::affected_paths(&event)?;
+    let (refreshed, mut invalidated) = crate::webhooks::refresh_paths(&data, game, &paths).await?;
+
+    // Links are reconciled from both bots into the users row
+    let mut user_synced = None;
+    if event.event == "link_changed"
+        && let Some(discord_id) = &event.discord_id
+    {
+        // The sync reads the bot user through the cache, so drop the old link list first
+        let key = format!(
+            "{}:upstream:/api/users/{}",
+            get_cache_prefix(game),
+            discord_id
+        );
+        let deleted = sqlx::query("DELETE FROM cache WHERE key = $1")
+            .bind(&key)
+            .execute(&data.db_pool)
+            .await?;
+        if deleted.rows_affected() > 0 {
+            invalidated.push(key);
+        }
+
+        let user_db = sqlx::query_as::<_, (String, String)>(
+            "SELECT COALESCE(linked_players, '[]'), COALESCE(linked_cr_players, '[]') FROM users WHERE discord_id = $1",
+        )
+        .bind(discord_id)
+        .fetch_optional(&data.db_pool)
+        .await?;
+        if let Some((lp_json, cr_json)) = user_db {
+            sync_user_accounts(
+                &data,
+                discord_id,
+                serde_json::from_str(&lp_json).unwrap_or_default(),
+                serde_json::from_str(&cr_json).unwrap_or_default(),
+            )
+            .await;
+            user_synced = Some(discord_id.clone());
         }
     }
+
+    Ok(HttpResponse::Ok().json(crate::models::WebhookResult {
+        event: event.event,
+        refreshed,
+        invalidated,
+        user_synced,
+    }))
 }
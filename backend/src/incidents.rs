@@ -0,0 +1,130 @@
+use crate::models::AppState;
+
+use log::{error, info, warn};
+
+// Public label for a probe. Custom probes fall back to their name.
+pub fn service_label(api_name: &str) -> String {
+    match api_name {
+        "upstream_coc" => "CoC Bot".to_string(),
+        "upstream_cr" => "CR Bot".to_string(),
+        "supercell_coc" => "Clash of Clans API".to_string(),
+        "supercell_cr" => "Clash Royale API".to_string(),
+        "website" => "Website".to_string(),
+        other => other.to_string(),
+    }
+}
+
+// Public endpoints whose data goes stale while a service is down
+pub fn affected_endpoints(api_name: &str) -> Vec<&'static str> {
+    match api_name {
+        "upstream_coc" => vec![
+            "/api/coc/clans",
+            "/api/coc/clans/{tag}/members",
+            "/api/coc/clans/{tag}/war-members",
+            "/api/coc/clans/{tag}/raid-members",
+            "/api/coc/clans/{tag}/cwl-members",
+            "/api/coc/players/{tag}/kickpoints",
+            "/api/sideclans",
+        ],
+        "upstream_cr" => vec![
+            "/api/cr/clans",
+            "/api/cr/clans/{tag}/members",
+            "/api/cr/players/{tag}/kickpoints",
+        ],
+        "supercell_coc" => vec![
+            "/api/coc/clans/{tag}",
+            "/api/coc/clans/{tag}/members",
+            "/api/coc/players/{tag}",
+            "/api/sideclans",
+        ],
+        "supercell_cr" => vec![
+            "/api/cr/clans/{tag}",
+            "/api/cr/clans/{tag}/members",
+            "/api/cr/players/{tag}",
+        ],
+        _ => vec![],
+    }
+}
+
+// Open or close the incident for a probe after a new measurement was stored.
+// An incident starts at the first of `threshold` consecutive failures and ends
+// with the next successful measurement.
+pub async fn update_incident(
+    data: &AppState,
+    api_name: &str,
+    error_kind: Option<&str>,
+    timestamp: i64,
+) {
+    let success = error_kind.is_none();
+    let open = sqlx::query_as::<_, (i32,)>(
+        "SELECT id FROM incidents WHERE api_name = $1 AND ended_at IS NULL ORDER BY started_at DESC LIMIT 1",
+    )
+    .bind(api_name)
+    .fetch_optional(&data.db_pool)
+    .await;
+
+    let open_id = match open {
+        Ok(row) => row.map(|r| r.0),
+        Err(e) => {
+            error!("Error loading open incident for {}: {}", api_name, e);
+            return;
+        }
+    };
+
+    match (open_id, success) {
+        (Some(id), true) => {
+            info!("Incident {} for {} resolved", id, api_name);
+            let _ = sqlx::query("UPDATE incidents SET ended_at = $1 WHERE id = $2")
+                .bind(timestamp)
+                .bind(id)
+                .execute(&data.db_pool)
+                .await;
+        }
+        (Some(id), false) => {
+            let _ = sqlx::query(
+                "UPDATE incidents SET failure_count = failure_count + 1, error_kind = $1 WHERE id = $2",
+            )
+            .bind(error_kind)
+            .bind(id)
+            .execute(&data.db_pool)
+            .await;
+        }
+        (None, false) => {
+            let threshold = data.incident_failure_threshold.max(1);
+            let recent = sqlx::query_as::<_, (i32, i64)>(
+                "SELECT latency_ms, timestamp FROM latency_measurements
+                 WHERE api_name = $1 ORDER BY timestamp DESC LIMIT $2",
+            )
+            .bind(api_name)
+            .bind(threshold)
+            .fetch_all(&data.db_pool)
+            .await
+            .unwrap_or_default();
+
+            if (recent.len() as i64) < threshold || recent.iter().any(|r| r.0 != -1) {
+                return;
+            }
+
+            let started_at = recent.iter().map(|r| r.1).min().unwrap_or(timestamp);
+            let endpoints = serde_json::to_string(&affected_endpoints(api_name))
+                .unwrap_or_else(|_| "[]".to_string());
+
+            warn!(
+                "Incident opened for {} ({} consecutive failures)",
+                api_name, threshold
+            );
+            let _ = sqlx::query(
+                "INSERT INTO incidents (api_name, started_at, failure_count, error_kind, affected_endpoints)
+                 VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(api_name)
+            .bind(started_at)
+            .bind(threshold as i32)
+            .bind(error_kind)
+            .bind(endpoints)
+            .execute(&data.db_pool)
+            .await;
+        }
+        (None, true) => {}
+    }
+}
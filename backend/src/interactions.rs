@@ -0,0 +1,372 @@
+use crate::models::{GameType, SideClanCwlHistory};
+
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde_json::{Value, json};
+
+// Interaction types and callback types of the Discord API
+pub const PING: i64 = 1;
+pub const APPLICATION_COMMAND: i64 = 2;
+const PONG: i64 = 1;
+const CHANNEL_MESSAGE_WITH_SOURCE: i64 = 4;
+// "Thinking..." state; the reply follows by editing the original response
+const DEFERRED_CHANNEL_MESSAGE_WITH_SOURCE: i64 = 5;
+// Message flag: only the caller sees the message
+const EPHEMERAL: i64 = 64;
+const EMBED_COLOR: i64 = 0x5865F2;
+// Discord allows at most 25 fields per embed
+const MAX_EMBED_FIELDS: usize = 25;
+
+pub fn parse_public_key(key: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(key.trim()).ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+// Discord signs the timestamp header followed by the raw body
+pub fn verify_signature(
+    key: &VerifyingKey,
+    signature: &str,
+    timestamp: &str,
+    body: &[u8],
+    now: i64,
+) -> bool {
+    if !crate::webhooks::timestamp_within_skew(timestamp, now) {
+        return false;
+    }
+    let Some(signature) = hex::decode(signature)
+        .ok()
+        .and_then(|s| Signature::from_slice(&s).ok())
+    else {
+        return false;
+    };
+    let mut message = timestamp.as_bytes().to_vec();
+    message.extend_from_slice(body);
+    key.verify(&message, &signature).is_ok()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Player { game: GameType, tag: String },
+    Clan { game: GameType, tag: String },
+    Kickpoints { game: GameType, tag: String },
+    Cwl,
+}
+
+impl Command {
+    // Replies that may contain data gated by the caller's role (kickpoints) are only
+    // shown to the caller
+    pub fn is_private(&self) -> bool {
+        matches!(self, Command::Player { .. } | Command::Kickpoints { .. })
+    }
+}
+
+// Discord user of the caller (`member.user` in guilds, `user` in DMs)
+pub fn caller_id(interaction: &Value) -> Option<String> {
+    interaction
+        .pointer("/member/user/id")
+        .or_else(|| interaction.pointer("/user/id"))
+        .and_then(|id| id.as_str())
+        .map(str::to_string)
+}
+
+pub fn parse_command(interaction: &Value) -> Result<Command, String> {
+    let data = &interaction["data"];
+    let option = |name: &str| {
+        data["options"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|o| o["name"] == name)
+            .and_then(|o| o["value"].as_str())
+    };
+    let game = match option("game") {
+        None | Some("coc") => GameType::ClashOfClans,
+        Some("cr") => GameType::ClashRoyale,
+        Some(other) => return Err(format!("Unknown game '{}'", other)),
+    };
+    let tag = || {
+        option("tag")
+            .map(|t| t.trim().to_string())
+            .ok_or_else(|| "Missing option 'tag'".to_string())
+    };
+
+    match data["name"].as_str() {
+        Some("player") => Ok(Command::Player { game, tag: tag()? }),
+        Some("clan") => Ok(Command::Clan { game, tag: tag()? }),
+        Some("kickpoints") => Ok(Command::Kickpoints { game, tag: tag()? }),
+        Some("cwl") => Ok(Command::Cwl),
+        Some(other) => Err(format!("Unknown command '{}'", other)),
+        None => Err("Missing command name".to_string()),
+    }
+}
+
+pub fn pong() -> Value {
+    json!({ "type": PONG })
+}
+
+// Commands read cached data and may wait for Supercell or the bots, which can take
+// longer than the 3 seconds Discord allows for the initial response
+pub fn deferred(private: bool) -> Value {
+    if private {
+        json!({ "type": DEFERRED_CHANNEL_MESSAGE_WITH_SOURCE, "data": { "flags": EPHEMERAL } })
+    } else {
+        json!({ "type": DEFERRED_CHANNEL_MESSAGE_WITH_SOURCE })
+    }
+}
+
+// Path (relative to the Discord API) of the deferred response, for
+// `PATCH` with `followup_embed` or `followup_text`
+pub fn original_response_path(interaction: &Value) -> Option<String> {
+    Some(format!(
+        "/v10/webhooks/{}/{}/messages/@original",
+        interaction.get("application_id")?.as_str()?,
+        interaction.get("token")?.as_str()?
+    ))
+}
+
+pub fn followup_embed(embed: Value) -> Value {
+    json!({ "embeds": [embed] })
+}
+
+pub fn followup_text(text: &str) -> Value {
+    json!({ "content": text })
+}
+
+pub fn ephemeral(text: &str) -> Value {
+    json!({
+        "type": CHANNEL_MESSAGE_WITH_SOURCE,
+        "data": { "content": text, "flags": EPHEMERAL },
+    })
+}
+
+fn field(name: &str, value: impl ToString) -> Value {
+    json!({ "name": name, "value": value.to_string(), "inline": true })
+}
+
+fn text(value: &Value, pointer: &str) -> Option<String> {
+    match value.pointer(pointer)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn title(value: &Value) -> String {
+    format!(
+        "{} ({})",
+        text(value, "/name").unwrap_or_default(),
+        text(value, "/tag").unwrap_or_default()
+    )
+}
+
+// Player profile as returned by the player endpoints (incl. kickpoint summary and
+// upgrade progress if present)
+pub fn player_embed(game: GameType, player: &Value) -> Value {
+    let labels: &[(&str, &str)] = match game {
+        GameType::ClashOfClans => &[
+            ("Town Hall", "/townHallLevel"),
+            ("Trophies", "/trophies"),
+            ("War Stars", "/warStars"),
+            ("Clan", "/clan/name"),
+            ("Role", "/role"),
+        ],
+        GameType::ClashRoyale => &[
+            ("King Level", "/expLevel"),
+            ("Trophies", "/trophies"),
+            ("Best Trophies", "/bestTrophies"),
+            ("Wins", "/wins"),
+            ("Clan", "/clan/name"),
+            ("Role", "/role"),
+        ],
+    };
+    let mut fields: Vec<Value> = labels
+        .iter()
+        .filter_map(|(name, pointer)| Some(field(name, text(player, pointer)?)))
+        .collect();
+    if let Some(progress) = player.get("upgradeProgress") {
+        fields.push(field(
+            "Upgrades",
+            format!(
+                "{}% done, {}% rushed",
+                progress["progressPercent"], progress["rushedPercent"]
+            ),
+        ));
+    }
+    if let Some(sum) = text(player, "/activeKickpointsSum") {
+        fields.push(field(
+            "Kickpoints",
+            format!(
+                "{} active ({} entries)",
+                sum,
+                text(player, "/activeKickpointsCount").unwrap_or_default()
+            ),
+        ));
+    }
+
+    json!({
+        "title": title(player),
+        "color": EMBED_COLOR,
+        "fields": fields,
+    })
+}
+
+// Merged clan info (Supercell and bot data)
+pub fn clan_embed(clan: &Value) -> Value {
+    let mut fields = vec![];
+    if let Some(members) = text(clan, "/members") {
+        fields.push(field("Members", format!("{}/50", members)));
+    }
+    for (name, pointer) in [
+        ("Level", "/clanLevel"),
+        ("War League", "/warLeague/name"),
+        ("Max. Kickpoints", "/maxKickpoints"),
+        ("Min. Season Wins", "/minSeasonWins"),
+    ] {
+        if let Some(value) = text(clan, pointer) {
+            fields.push(field(name, value));
+        }
+    }
+
+    let mut embed = json!({
+        "title": title(clan),
+        "description": text(clan, "/description").unwrap_or_default(),
+        "color": EMBED_COLOR,
+        "fields": fields,
+    });
+    if let Some(badge) = text(clan, "/badgeUrls/medium") {
+        embed["thumbnail"] = json!({ "url": badge });
+    }
+    embed
+}
+
+// Kickpoint summary of a player (`total`, `activeCount`, `activeSum`)
+pub fn kickpoints_embed(tag: &str, summary: &Value) -> Value {
+    json!({
+        "title": format!("Kickpoints of {}", crate::utils::normalize_tag(tag)),
+        "color": EMBED_COLOR,
+        "fields": [
+            field("Active", format!(
+                "{} ({} entries)",
+                summary["activeSum"], summary["activeCount"]
+            )),
+            field("Total", &summary["total"]),
+        ],
+    })
+}
+
+// Latest CWL league and rank per family clan
+pub fn cwl_embed(season: &str, clans: &[SideClanCwlHistory]) -> Value {
+    let fields: Vec<Value> = clans
+        .iter()
+        .take(MAX_EMBED_FIELDS)
+        .map(|c| {
+            let value = match c.history.first() {
+                Some(stats) => {
+                    let mut value = stats.league_name.clone().unwrap_or("Unranked".to_string());
+                    if let Some(rank) = stats.rank {
+                        value.push_str(&format!(", rank {}", rank));
+                    }
+                    if stats.season != season {
+                        value.push_str(&format!(" ({})", stats.season));
+                    }
+                    value
+                }
+                None => "No CWL data".to_string(),
+            };
+            field(&c.clan.name, value)
+        })
+        .collect();
+
+    json!({
+        "title": format!("CWL {}", season),
+        "color": EMBED_COLOR,
+        "fields": fields,
+    })
+}
+
+// Slash command definitions, for registering with
+// `PUT /applications/{id}/commands` (see `--print-discord-commands`)
+pub fn command_definitions() -> Value {
+    let tag = |description: &str| json!({ "type": 3, "name": "tag", "description": description, "required": true });
+    let game = json!({
+        "type": 3,
+        "name": "game",
+        "description": "Game (default Clash of Clans)",
+        "choices": [
+            { "name": "Clash of Clans", "value": "coc" },
+            { "name": "Clash Royale", "value": "cr" },
+        ],
+    });
+    json!([
+        { "name": "player", "description": "Show a player profile", "options": [tag("Player tag"), game] },
+        { "name": "clan", "description": "Show a clan", "options": [tag("Clan tag"), game] },
+        { "name": "kickpoints", "description": "Show the kickpoints of a player", "options": [tag("Player tag"), game] },
+        { "name": "cwl", "description": "Show the CWL leagues of the family clans" },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn signed_commands_are_verified_and_parsed() {
+        let signing = SigningKey::from_bytes(&[7; 32]);
+        let key = parse_public_key(&hex::encode(signing.verifying_key().as_bytes())).unwrap();
+        let body = br##"{"type":2,"data":{"name":"kickpoints","options":[{"name":"tag","value":"#abc"},{"name":"game","value":"cr"}]},"member":{"user":{"id":"42"}}}"##;
+        let signature = hex::encode(
+            signing
+                .sign(&[b"1700000000".as_slice(), body].concat())
+                .to_bytes(),
+        );
+
+        assert!(verify_signature(
+            &key,
+            &signature,
+            "1700000000",
+            body,
+            1700000001
+        ));
+        assert!(!verify_signature(
+            &key,
+            &signature,
+            "1700000001",
+            body,
+            1700000001
+        ));
+        assert!(!verify_signature(
+            &key,
+            "zz",
+            "1700000000",
+            body,
+            1700000001
+        ));
+
+        let interaction: Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(caller_id(&interaction).as_deref(), Some("42"));
+        assert_eq!(
+            parse_command(&interaction),
+            Ok(Command::Kickpoints {
+                game: GameType::ClashRoyale,
+                tag: "#abc".to_string()
+            })
+        );
+        assert!(parse_command(&interaction).unwrap().is_private());
+    }
+
+    #[test]
+    fn player_embed_reads_the_serialized_upgrade_progress() {
+        let player = json!({
+            "tag": "#ABC",
+            "name": "Bob",
+            "upgradeProgress": crate::models::UpgradeProgress {
+                town_hall: 15,
+                progress_percent: 20.9,
+                rushed_percent: 75.8,
+                categories: vec![],
+            },
+        });
+        let embed = player_embed(GameType::ClashOfClans, &player);
+        assert_eq!(embed["fields"][0]["value"], "20.9% done, 75.8% rushed");
+    }
+}
@@ -0,0 +1,395 @@
+use crate::models::AppState;
+
+use futures_util::future::BoxFuture;
+use log::{debug, error, info, warn};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// A job returns the number of items it processed
+pub type JobFn = fn(AppState) -> BoxFuture<'static, Result<i64, String>>;
+
+// Runs older than this are removed from `job_runs`
+const RUN_HISTORY_RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
+
+// How often followers check for leadership and interval jobs re-check their due time
+const FOLLOWER_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub enum JobSchedule {
+    Interval(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl JobSchedule {
+    // Accepts "every 10m" / "every 30s" / "every 1h" or a 6/7-field cron expression
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if let Some(every) = s.strip_prefix("every ") {
+            let every = every.trim();
+            let unit_at = every.char_indices().last().map_or(0, |(i, _)| i);
+            let (num, unit) = every.split_at(unit_at);
+            let num: u64 = num
+                .parse()
+                .map_err(|_| format!("Invalid interval '{}'", every))?;
+            let unit_secs = match unit {
+                "s" => 1,
+                "m" => 60,
+                "h" => 60 * 60,
+                _ => return Err(format!("Invalid interval unit in '{}'", every)),
+            };
+            let secs = num
+                .checked_mul(unit_secs)
+                .ok_or_else(|| format!("Interval '{}' is too long", every))?;
+            if secs == 0 {
+                return Err("Interval must be greater than zero".to_string());
+            }
+            return Ok(JobSchedule::Interval(Duration::from_secs(secs)));
+        }
+        cron::Schedule::from_str(s)
+            .map(|c| JobSchedule::Cron(Box::new(c)))
+            .map_err(|e| format!("Invalid cron expression '{}': {}", s, e))
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            JobSchedule::Interval(d) => format!("every {}s", d.as_secs()),
+            JobSchedule::Cron(c) => c.to_string(),
+        }
+    }
+
+    fn next_delay(&self) -> Duration {
+        match self {
+            JobSchedule::Interval(d) => *d,
+            JobSchedule::Cron(c) => c
+                .upcoming(chrono::Utc)
+                .next()
+                .and_then(|next| (next - chrono::Utc::now()).to_std().ok())
+                .unwrap_or(Duration::from_secs(60)),
+        }
+    }
+}
+
+pub struct Job {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub schedule: JobSchedule,
+    // Delay before the first scheduled run (spreads load at startup)
+    pub initial_delay: Duration,
+    run: JobFn,
+    running: AtomicBool,
+}
+
+impl Job {
+    pub fn new(
+        name: &'static str,
+        description: &'static str,
+        schedule: JobSchedule,
+        run: JobFn,
+    ) -> Self {
+        Job {
+            name,
+            description,
+            schedule,
+            initial_delay: Duration::ZERO,
+            run,
+            running: AtomicBool::new(false),
+        }
+    }
+
+    pub fn with_initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+}
+
+pub struct JobRegistry {
+    pub jobs: Vec<Job>,
+}
+
+impl JobRegistry {
+    pub fn get(&self, name: &str) -> Option<&Job> {
+        self.jobs.iter().find(|j| j.name == name)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.jobs.iter().map(|j| j.name).collect()
+    }
+
+    // Schedule overrides from the config (validated on startup)
+    pub fn with_schedules(mut self, schedules: &BTreeMap<String, String>) -> Self {
+        for job in &mut self.jobs {
+            if let Some(schedule) = schedules.get(job.name) {
+                match JobSchedule::parse(schedule) {
+                    Ok(parsed) => job.schedule = parsed,
+                    Err(e) => error!("Job {}: {} (using default schedule)", job.name, e),
+                }
+            }
+        }
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum JobStartError {
+    NotFound,
+    AlreadyRunning,
+    Database(String),
+}
+
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+// Start a job in the background unless it is already running. Returns the run id.
+// On non-leader replicas the run is queued and picked up by the leader.
+pub async fn start_job(data: &AppState, name: &str, trigger: &str) -> Result<i32, JobStartError> {
+    let job = data.jobs.get(name).ok_or(JobStartError::NotFound)?;
+
+    if !data.leader.is_leader() {
+        return sqlx::query_as::<_, (i32,)>(
+            "INSERT INTO job_runs (job_name, trigger, status, started_at) VALUES ($1, $2, 'queued', $3) RETURNING id",
+        )
+        .bind(job.name)
+        .bind(trigger)
+        .bind(now_secs())
+        .fetch_one(&data.db_pool)
+        .await
+        .map(|(id,)| id)
+        .map_err(|e| JobStartError::Database(e.to_string()));
+    }
+
+    if job
+        .running
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err(JobStartError::AlreadyRunning);
+    }
+
+    let run_id = match sqlx::query_as::<_, (i32,)>(
+        "INSERT INTO job_runs (job_name, trigger, status, started_at, instance_id) VALUES ($1, $2, 'running', $3, $4) RETURNING id",
+    )
+    .bind(job.name)
+    .bind(trigger)
+    .bind(now_secs())
+    .bind(&data.leader.instance_id)
+    .fetch_one(&data.db_pool)
+    .await
+    {
+        Ok((id,)) => id,
+        Err(e) => {
+            job.running.store(false, Ordering::SeqCst);
+            return Err(JobStartError::Database(e.to_string()));
+        }
+    };
+
+    execute_run(data, job, run_id);
+    Ok(run_id)
+}
+
+// Run a job whose `running` flag is already set and record the outcome
+fn execute_run(data: &AppState, job: &Job, run_id: i32) {
+    let data = data.clone();
+    let job_name = job.name;
+    let run = job.run;
+    tokio::spawn(async move {
+        debug!("Job {} (run {}) started", job_name, run_id);
+
+        // Run in its own task so a panic is caught and recorded instead of killing the scheduler
+        let (status, error, items) = match tokio::spawn(run(data.clone())).await {
+            Ok(Ok(items)) => ("success", None, Some(items)),
+            Ok(Err(e)) => ("failed", Some(e), None),
+            Err(e) if e.is_panic() => ("panicked", Some("Job panicked".to_string()), None),
+            Err(e) => ("failed", Some(e.to_string()), None),
+        };
+
+        match status {
+            "success" => debug!("Job {} (run {}) finished", job_name, run_id),
+            _ => error!(
+                "Job {} (run {}) {}: {}",
+                job_name,
+                run_id,
+                status,
+                error.as_deref().unwrap_or("")
+            ),
+        }
+
+        let finished_at = now_secs();
+        let _ = sqlx::query(
+            "UPDATE job_runs SET status = $1, error = $2, items_processed = $3, finished_at = $4 WHERE id = $5",
+        )
+        .bind(status)
+        .bind(error)
+        .bind(items)
+        .bind(finished_at)
+        .bind(run_id)
+        .execute(&data.db_pool)
+        .await;
+
+        let _ = sqlx::query("DELETE FROM job_runs WHERE job_name = $1 AND started_at < $2")
+            .bind(job_name)
+            .bind(finished_at - RUN_HISTORY_RETENTION_SECS)
+            .execute(&data.db_pool)
+            .await;
+
+        if let Some(job) = data.jobs.get(job_name) {
+            job.running.store(false, Ordering::SeqCst);
+        }
+    });
+}
+
+// Leader only: start runs that were queued by manual triggers on other replicas
+async fn dispatch_queued_runs(data: &AppState) {
+    let queued = sqlx::query_as::<_, (i32, String)>(
+        "SELECT id, job_name FROM job_runs WHERE status = 'queued' ORDER BY id",
+    )
+    .fetch_all(&data.db_pool)
+    .await
+    .unwrap_or_default();
+
+    for (run_id, job_name) in queued {
+        let Some(job) = data.jobs.get(&job_name) else {
+            let _ = sqlx::query(
+                "UPDATE job_runs SET status = 'failed', error = 'Unknown job', finished_at = $1 WHERE id = $2",
+            )
+            .bind(now_secs())
+            .bind(run_id)
+            .execute(&data.db_pool)
+            .await;
+            continue;
+        };
+
+        // Leave it queued until the current run finishes
+        if job
+            .running
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            continue;
+        }
+
+        let claimed = sqlx::query(
+            "UPDATE job_runs SET status = 'running', started_at = $1, instance_id = $2
+             WHERE id = $3 AND status = 'queued'",
+        )
+        .bind(now_secs())
+        .bind(&data.leader.instance_id)
+        .bind(run_id)
+        .execute(&data.db_pool)
+        .await;
+
+        match claimed {
+            Ok(r) if r.rows_affected() == 1 => execute_run(data, job, run_id),
+            _ => job.running.store(false, Ordering::SeqCst),
+        }
+    }
+}
+
+// Spawn one scheduling loop per registered job
+pub fn start_scheduler(data: AppState) {
+    let dispatch_data = data.clone();
+    tokio::spawn(async move {
+        loop {
+            if dispatch_data.leader.is_leader() {
+                dispatch_queued_runs(&dispatch_data).await;
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    });
+
+    for job in &data.jobs.jobs {
+        let data = data.clone();
+        let name = job.name;
+        info!("Scheduling job {} ({})", name, job.schedule.describe());
+
+        tokio::spawn(async move {
+            let Some(job) = data.jobs.get(name) else {
+                return;
+            };
+            if !job.initial_delay.is_zero() {
+                tokio::time::sleep(job.initial_delay).await;
+            }
+
+            // Interval jobs run when due, cron jobs wait for their first slot
+            if let JobSchedule::Cron(_) = job.schedule {
+                tokio::time::sleep(job.schedule.next_delay()).await;
+            }
+
+            loop {
+                // Followers keep polling so they can take over when the leader dies
+                if !data.leader.is_leader() {
+                    tokio::time::sleep(FOLLOWER_POLL_INTERVAL).await;
+                    continue;
+                }
+
+                match &job.schedule {
+                    JobSchedule::Interval(every) => {
+                        // Continue the previous leader's cadence after a failover
+                        let last_started = sqlx::query_as::<_, (Option<i64>,)>(
+                            "SELECT MAX(started_at) FROM job_runs WHERE job_name = $1 AND status <> 'queued'",
+                        )
+                        .bind(name)
+                        .fetch_one(&data.db_pool)
+                        .await
+                        .ok()
+                        .and_then(|r| r.0);
+
+                        let due_in = last_started
+                            .map(|last| last + every.as_secs() as i64 - now_secs())
+                            .unwrap_or(0);
+                        if due_in > 0 {
+                            let wait = Duration::from_secs(due_in as u64);
+                            tokio::time::sleep(wait.min(FOLLOWER_POLL_INTERVAL)).await;
+                            continue;
+                        }
+
+                        run_scheduled(&data, name).await;
+                        tokio::time::sleep((*every).min(FOLLOWER_POLL_INTERVAL)).await;
+                    }
+                    JobSchedule::Cron(_) => {
+                        run_scheduled(&data, name).await;
+                        // Avoid firing twice within the same cron second
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        tokio::time::sleep(job.schedule.next_delay()).await;
+                    }
+                }
+            }
+        });
+    }
+}
+
+async fn run_scheduled(data: &AppState, name: &str) {
+    match start_job(data, name, "schedule").await {
+        Ok(_) => {}
+        Err(JobStartError::AlreadyRunning) => {
+            warn!("Job {} is still running, skipping scheduled run", name);
+        }
+        Err(e) => error!("Failed to start job {}: {:?}", name, e),
+    }
+}
+
+// Called when this instance becomes leader: runs left in 'running' state by a
+// previous leader (or an earlier process of this instance) can never finish
+pub async fn mark_interrupted_runs(
+    pool: &sqlx::PgPool,
+    instance_id: &str,
+    process_started_at: i64,
+) {
+    let _ = sqlx::query(
+        "UPDATE job_runs SET status = 'interrupted', finished_at = $1
+         WHERE status = 'running' AND (instance_id IS DISTINCT FROM $2 OR started_at < $3)",
+    )
+    .bind(now_secs())
+    .bind(instance_id)
+    .bind(process_started_at)
+    .execute(pool)
+    .await;
+}
@@ -0,0 +1,340 @@
+use crate::config::ProbeConfig;
+use crate::incidents::update_incident;
+use crate::models::{AppState, LatencyProbe};
+
+use futures_util::future::join_all;
+use log::debug;
+use std::time::Duration;
+
+// Raw 1-minute samples are kept for 48 hours, rollups for a year
+const RAW_RETENTION_SECS: i64 = 48 * 60 * 60;
+const ROLLUP_RETENTION_SECS: i64 = 365 * 24 * 60 * 60;
+
+pub const HOUR_SECS: i64 = 60 * 60;
+pub const DAY_SECS: i64 = 24 * 60 * 60;
+
+// Default probes, seeded into `latency_probes` on startup.
+// URLs may reference the configured base URLs via placeholders (see `expand_probe_url`).
+pub const DEFAULT_PROBES: [(&str, &str, &str); 5] = [
+    ("upstream_coc", "{upstream_coc}/api/guild", "upstream_coc"),
+    ("upstream_cr", "{upstream_cr}/api/guild", "upstream_cr"),
+    (
+        "supercell_coc",
+        "{supercell_coc}/locations?limit=1",
+        "supercell_coc",
+    ),
+    (
+        "supercell_cr",
+        "{supercell_cr}/locations?limit=1",
+        "supercell_cr",
+    ),
+    ("website", "{website}", "none"),
+];
+
+struct ProbeResult {
+    latency_ms: i32,
+    status_code: Option<i32>,
+    error_kind: Option<&'static str>,
+    error_message: Option<String>,
+}
+
+fn website_url(data: &AppState) -> String {
+    // Inside docker the frontend is reachable via its service name
+    if data.frontend_url.contains("localhost") {
+        data.frontend_url.replace("localhost", "website")
+    } else {
+        data.frontend_url.clone()
+    }
+}
+
+fn expand_probe_url(data: &AppState, url: &str) -> String {
+    url.replace("{upstream_coc}", &data.upstream_coc_url)
+        .replace("{upstream_cr}", &data.upstream_cr_url)
+        .replace("{supercell_coc}", &data.supercell_coc_url)
+        .replace("{supercell_cr}", &data.supercell_cr_url)
+        .replace("{website}", &website_url(data))
+}
+
+// Probes reference tokens by name so no secrets are stored in the database
+fn probe_token<'a>(data: &'a AppState, auth: &str) -> Option<&'a str> {
+    match auth {
+        "upstream_coc" => Some(&data.coc_api_token),
+        "upstream_cr" => Some(&data.cr_api_token),
+        "supercell_coc" => Some(&data.clash_of_clans_api_token),
+        "supercell_cr" => Some(&data.clash_royale_api_token),
+        _ => None,
+    }
+}
+
+fn classify_error(e: &oauth2::reqwest::Error) -> &'static str {
+    if e.is_timeout() {
+        return "timeout";
+    }
+    if e.is_connect() {
+        // hyper reports resolver failures as a "dns error" somewhere in the source chain
+        let mut source: Option<&dyn std::error::Error> = Some(e);
+        while let Some(err) = source {
+            let msg = err.to_string().to_lowercase();
+            if msg.contains("dns error") || msg.contains("failed to lookup address") {
+                return "dns";
+            }
+            source = err.source();
+        }
+        return "connect";
+    }
+    "request"
+}
+
+async fn run_probe(data: &AppState, probe: &LatencyProbe) -> ProbeResult {
+    let url = expand_probe_url(data, &probe.url);
+    let mut req = data
+        .client
+        .get(&url)
+        .timeout(Duration::from_millis(probe.timeout_ms.max(1) as u64));
+    if let Some(token) = probe_token(data, &probe.auth) {
+        req = req.header("Authorization", format!("Bearer {}", token));
+    }
+
+    let start = std::time::Instant::now();
+    match req.send().await {
+        Ok(res) => {
+            let latency_ms = start.elapsed().as_millis() as i32;
+            let status = res.status().as_u16() as i32;
+            if status == probe.expected_status {
+                ProbeResult {
+                    latency_ms,
+                    status_code: Some(status),
+                    error_kind: None,
+                    error_message: None,
+                }
+            } else {
+                ProbeResult {
+                    latency_ms: -1,
+                    status_code: Some(status),
+                    error_kind: Some("http_status"),
+                    error_message: Some(format!(
+                        "Expected status {}, got {}",
+                        probe.expected_status, status
+                    )),
+                }
+            }
+        }
+        Err(e) => ProbeResult {
+            latency_ms: -1,
+            status_code: None,
+            error_kind: Some(classify_error(&e)),
+            error_message: Some(e.to_string()),
+        },
+    }
+}
+
+// Probes from the config replace the definitions of the same name. Without
+// configured probes the defaults are seeded (existing rows are kept).
+pub async fn seed_probes(pool: &sqlx::PgPool, probes: &[ProbeConfig]) {
+    if probes.is_empty() {
+        for (name, url, auth) in DEFAULT_PROBES {
+            let _ = sqlx::query(
+                "INSERT INTO latency_probes (name, url, auth) VALUES ($1, $2, $3)
+                 ON CONFLICT (name) DO NOTHING",
+            )
+            .bind(name)
+            .bind(url)
+            .bind(auth)
+            .execute(pool)
+            .await;
+        }
+        return;
+    }
+
+    for probe in probes {
+        let _ = sqlx::query(
+            "INSERT INTO latency_probes (name, url, auth, expected_status, timeout_ms) VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (name) DO UPDATE SET url = $2, auth = $3, expected_status = $4, timeout_ms = $5",
+        )
+        .bind(&probe.name)
+        .bind(&probe.url)
+        .bind(&probe.auth)
+        .bind(probe.expected_status)
+        .bind(probe.timeout_ms)
+        .execute(pool)
+        .await;
+    }
+}
+
+pub async fn fetch_enabled_probes(pool: &sqlx::PgPool) -> Result<Vec<LatencyProbe>, sqlx::Error> {
+    sqlx::query_as::<_, LatencyProbe>(
+        "SELECT name, url, auth, expected_status, timeout_ms, enabled FROM latency_probes
+         WHERE enabled ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn measure_and_save_latency(data: &AppState) -> Result<i64, String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    // Probes measure the real APIs; replay mode stays offline
+    if data.fixtures.is_replay() {
+        debug!("Background: Skipping latency probes in replay mode");
+        return Ok(0);
+    }
+
+    debug!("Background: Measuring latency...");
+
+    let probes = fetch_enabled_probes(&data.db_pool)
+        .await
+        .map_err(|e| format!("Error loading latency probes: {}", e))?;
+
+    let results = join_all(probes.iter().map(|p| run_probe(data, p))).await;
+
+    for (probe, result) in probes.iter().zip(results) {
+        if let Some(kind) = result.error_kind {
+            debug!(
+                "Probe {} failed ({}): {}",
+                probe.name,
+                kind,
+                result.error_message.as_deref().unwrap_or("")
+            );
+        }
+
+        let _ = sqlx::query(
+            "INSERT INTO latency_measurements (api_name, latency_ms, timestamp, status_code, error_kind, error_message)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(&probe.name)
+        .bind(result.latency_ms)
+        .bind(now)
+        .bind(result.status_code)
+        .bind(result.error_kind)
+        .bind(result.error_message)
+        .execute(&data.db_pool)
+        .await;
+
+        update_incident(data, &probe.name, result.error_kind, now).await;
+    }
+
+    Ok(probes.len() as i64)
+}
+
+// Aggregate raw samples in [from, to) into buckets of `bucket_secs`
+async fn rollup(
+    pool: &sqlx::PgPool,
+    resolution: &str,
+    bucket_secs: i64,
+    from: i64,
+    to: i64,
+) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query(
+        "INSERT INTO latency_rollups (api_name, resolution, bucket_start, samples, successes, p50_ms, p95_ms, avg_ms)
+         SELECT api_name, $1, (timestamp / $2) * $2 AS bucket,
+                COUNT(*),
+                COUNT(*) FILTER (WHERE latency_ms >= 0),
+                ROUND(percentile_cont(0.5) WITHIN GROUP (ORDER BY latency_ms) FILTER (WHERE latency_ms >= 0))::INTEGER,
+                ROUND(percentile_cont(0.95) WITHIN GROUP (ORDER BY latency_ms) FILTER (WHERE latency_ms >= 0))::INTEGER,
+                ROUND(AVG(latency_ms) FILTER (WHERE latency_ms >= 0))::INTEGER
+         FROM latency_measurements
+         WHERE timestamp >= $3 AND timestamp < $4
+         GROUP BY api_name, bucket
+         ON CONFLICT (api_name, resolution, bucket_start) DO UPDATE SET
+            samples = EXCLUDED.samples,
+            successes = EXCLUDED.successes,
+            p50_ms = EXCLUDED.p50_ms,
+            p95_ms = EXCLUDED.p95_ms,
+            avg_ms = EXCLUDED.avg_ms",
+    )
+    .bind(resolution)
+    .bind(bucket_secs)
+    .bind(from)
+    .bind(to)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+// Recompute recent hourly/daily rollups and prune expired data
+pub async fn maintain_latency_history(data: &AppState) -> Result<i64, String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    // Recompute the current and previous buckets so late samples are included
+    let hour_from = (now / HOUR_SECS) * HOUR_SECS - 2 * HOUR_SECS;
+    let hourly = rollup(&data.db_pool, "hour", HOUR_SECS, hour_from, now + 1)
+        .await
+        .map_err(|e| format!("Error computing hourly latency rollups: {}", e))?;
+
+    let day_from = (now / DAY_SECS) * DAY_SECS - DAY_SECS;
+    let daily = rollup(&data.db_pool, "day", DAY_SECS, day_from, now + 1)
+        .await
+        .map_err(|e| format!("Error computing daily latency rollups: {}", e))?;
+
+    let _ = sqlx::query("DELETE FROM latency_measurements WHERE timestamp < $1")
+        .bind(now - RAW_RETENTION_SECS)
+        .execute(&data.db_pool)
+        .await;
+
+    let _ = sqlx::query("DELETE FROM latency_rollups WHERE bucket_start < $1")
+        .bind(now - ROLLUP_RETENTION_SECS)
+        .execute(&data.db_pool)
+        .await;
+
+    Ok((hourly + daily) as i64)
+}
+
+// Parse durations like "90m", "48h", "7d", "2w" or "1y" into seconds
+pub fn parse_range(range: &str) -> Option<i64> {
+    let range = range.trim();
+    let (unit_at, _) = range.char_indices().last()?;
+    let (num, unit) = range.split_at(unit_at);
+    let num: i64 = num.parse().ok()?;
+    let unit_secs = match unit {
+        "m" => 60,
+        "h" => HOUR_SECS,
+        "d" => DAY_SECS,
+        "w" => 7 * DAY_SECS,
+        "y" => 365 * DAY_SECS,
+        _ => return None,
+    };
+    if num <= 0 {
+        return None;
+    }
+    num.checked_mul(unit_secs)
+}
+
+// Pick the finest resolution that is still retained for the requested range
+pub fn auto_resolution(range_secs: i64) -> &'static str {
+    if range_secs <= RAW_RETENTION_SECS {
+        "raw"
+    } else if range_secs <= 31 * DAY_SECS {
+        "hour"
+    } else {
+        "day"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_range_rejects_bad_input_without_panicking() {
+        assert_eq!(parse_range("90m"), Some(90 * 60));
+        assert_eq!(parse_range(" 2w "), Some(14 * DAY_SECS));
+        for bad in [
+            "",
+            "d",
+            "0d",
+            "-1h",
+            "7x",
+            "7ü",
+            "ü",
+            "9223372036854775807y",
+        ] {
+            assert_eq!(parse_range(bad), None, "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::models::AppState;
+
+use log::{error, info, warn};
+use sqlx::Connection;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// Session-level advisory lock key ("LOST")
+const LEADER_LOCK_KEY: i64 = 0x4C4F_5354;
+const ELECTION_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct LeaderState {
+    pub instance_id: String,
+    pub started_at: i64,
+    is_leader: AtomicBool,
+}
+
+impl LeaderState {
+    pub fn new(instance_id: Option<String>) -> Self {
+        // Docker sets HOSTNAME to the container id, which is unique per replica
+        let instance_id = instance_id
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .unwrap_or_else(|| format!("instance-{}", std::process::id()));
+
+        LeaderState {
+            instance_id,
+            started_at: now_secs(),
+            is_leader: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::SeqCst)
+    }
+}
+
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+// Only the replica holding the advisory lock runs background jobs. The lock lives as
+// long as the dedicated connection, so it is released automatically when the leader dies.
+pub fn start_leader_election(data: AppState) {
+    tokio::spawn(async move {
+        let mut lock_conn: Option<sqlx::PgConnection> = None;
+
+        loop {
+            match lock_conn.as_mut() {
+                Some(conn) => {
+                    let heartbeat = sqlx::query(
+                        "UPDATE leader_lease SET heartbeat_at = $1 WHERE id = 1 AND instance_id = $2",
+                    )
+                    .bind(now_secs())
+                    .bind(&data.leader.instance_id)
+                    .execute(&mut *conn)
+                    .await;
+
+                    if let Err(e) = heartbeat {
+                        warn!(
+                            "Leader {} lost its lock connection: {}",
+                            data.leader.instance_id, e
+                        );
+                        data.leader.is_leader.store(false, Ordering::SeqCst);
+                        lock_conn = None;
+                    }
+                }
+                None => match try_acquire(&data).await {
+                    Ok(Some(conn)) => {
+                        info!("Instance {} is now the leader", data.leader.instance_id);
+                        data.leader.is_leader.store(true, Ordering::SeqCst);
+                        crate::jobs::mark_interrupted_runs(
+                            &data.db_pool,
+                            &data.leader.instance_id,
+                            data.leader.started_at,
+                        )
+                        .await;
+                        lock_conn = Some(conn);
+                    }
+                    Ok(None) => {}
+                    Err(e) => error!("Leader election failed: {}", e),
+                },
+            }
+
+            tokio::time::sleep(ELECTION_INTERVAL).await;
+        }
+    });
+}
+
+async fn try_acquire(data: &AppState) -> Result<Option<sqlx::PgConnection>, sqlx::Error> {
+    let mut conn = data.db_pool.acquire().await?.detach();
+
+    let (acquired,): (bool,) = sqlx::query_as("SELECT pg_try_advisory_lock($1)")
+        .bind(LEADER_LOCK_KEY)
+        .fetch_one(&mut conn)
+        .await?;
+
+    if !acquired {
+        let _ = conn.close().await;
+        return Ok(None);
+    }
+
+    let now = now_secs();
+    sqlx::query(
+        "INSERT INTO leader_lease (id, instance_id, acquired_at, heartbeat_at) VALUES (1, $1, $2, $2)
+         ON CONFLICT (id) DO UPDATE SET
+            instance_id = EXCLUDED.instance_id,
+            acquired_at = EXCLUDED.acquired_at,
+            heartbeat_at = EXCLUDED.heartbeat_at",
+    )
+    .bind(&data.leader.instance_id)
+    .bind(now)
+    .execute(&mut conn)
+    .await?;
+
+    Ok(Some(conn))
+}
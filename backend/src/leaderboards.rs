@@ -0,0 +1,252 @@
+use crate::background::family_clan_tags;
+use crate::errors::ApiError;
+use crate::models::{AppState, GameType, LeaderboardEntry};
+use crate::utils::{cached_bodies, encode_tag, get_cache_prefix, normalize_tag};
+
+use serde_json::{Number, Value};
+use std::collections::HashMap;
+
+// CR API card levels are relative to the rarity (commons go up to 14, legendaries to 6).
+// Levels are normalized to the common scale, as shown in game.
+const CR_COMMON_MAX_LEVEL: i64 = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Trophies,
+    Donations,
+    // CoC, from player profiles
+    WarStars,
+    HeroLevels,
+    UpgradeProgress,
+    // CR, from player profiles
+    Wins,
+    CardLevel,
+}
+
+impl Metric {
+    pub fn parse(game: GameType, name: &str) -> Option<Self> {
+        match (game, name) {
+            (_, "trophies") => Some(Metric::Trophies),
+            (GameType::ClashOfClans, "donations") => Some(Metric::Donations),
+            (GameType::ClashOfClans, "war_stars") => Some(Metric::WarStars),
+            (GameType::ClashOfClans, "hero_levels") => Some(Metric::HeroLevels),
+            (GameType::ClashOfClans, "upgrade_progress") => Some(Metric::UpgradeProgress),
+            (GameType::ClashRoyale, "wins") => Some(Metric::Wins),
+            (GameType::ClashRoyale, "card_level") => Some(Metric::CardLevel),
+            _ => None,
+        }
+    }
+
+    pub fn names(game: GameType) -> &'static [&'static str] {
+        match game {
+            GameType::ClashOfClans => &[
+                "trophies",
+                "war_stars",
+                "donations",
+                "hero_levels",
+                "upgrade_progress",
+            ],
+            GameType::ClashRoyale => &["trophies", "wins", "card_level"],
+        }
+    }
+
+    // Value of a member, from the clan member list entry or the cached player profile
+    pub fn value(self, member: &Value, profile: Option<&Value>) -> Option<Number> {
+        let int = |v: &Value, field: &str| v.get(field).and_then(|v| v.as_i64());
+        match self {
+            Metric::Trophies => int(member, "trophies").map(Number::from),
+            Metric::Donations => int(member, "donations").map(Number::from),
+            Metric::WarStars => int(profile?, "warStars").map(Number::from),
+            Metric::Wins => int(profile?, "wins").map(Number::from),
+            Metric::HeroLevels => {
+                // Home village heroes only
+                let heroes = profile?.get("heroes")?.as_array()?;
+                let sum: i64 = heroes
+                    .iter()
+                    .filter(|h| h.get("village").and_then(|v| v.as_str()) != Some("builderBase"))
+                    .filter_map(|h| int(h, "level"))
+                    .sum();
+                Some(Number::from(sum))
+            }
+            Metric::UpgradeProgress => {
+                Number::from_f64(crate::rush::upgrade_progress(profile?)?.progress_percent)
+            }
+            Metric::CardLevel => {
+                let cards = profile?.get("cards")?.as_array()?;
+                let levels: Vec<i64> = cards
+                    .iter()
+                    .filter_map(|c| {
+                        Some(int(c, "level")? + CR_COMMON_MAX_LEVEL - int(c, "maxLevel")?)
+                    })
+                    .collect();
+                if levels.is_empty() {
+                    return None;
+                }
+                let avg = levels.iter().sum::<i64>() as f64 / levels.len() as f64;
+                Number::from_f64((avg * 100.0).round() / 100.0)
+            }
+        }
+    }
+
+    fn needs_profile(self) -> bool {
+        !matches!(self, Metric::Trophies | Metric::Donations)
+    }
+}
+
+pub struct Filters {
+    // Normalized clan tag ("#ABC")
+    pub clan: Option<String>,
+    // Town hall (CoC) or King level (CR)
+    pub min_level: Option<i64>,
+    pub max_level: Option<i64>,
+}
+
+// Rank all members of all family clans (clan list and side clans) by a metric.
+// Only cached data is used; members without a cached profile are left out of
+// profile-based metrics.
+pub async fn build_leaderboard(
+    data: &AppState,
+    game: GameType,
+    metric: Metric,
+    filters: &Filters,
+) -> Result<Vec<LeaderboardEntry>, ApiError> {
+    let prefix = get_cache_prefix(game);
+    let mut clan_tags = family_clan_tags(data, game)
+        .await
+        .map_err(ApiError::Internal)?;
+    if let Some(clan) = &filters.clan {
+        clan_tags.retain(|t| normalize_tag(t) == *clan);
+    }
+
+    let clan_keys: Vec<String> = clan_tags
+        .iter()
+        .map(|t| format!("{}:supercell:/clans/{}", prefix, encode_tag(t)))
+        .collect();
+    let clan_bodies = cached_bodies(data, &clan_keys, 0).await?;
+
+    // (clan tag, clan name, member) of every member, a player is listed in one clan only
+    let mut members: Vec<(String, String, Value)> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for key in &clan_keys {
+        let Some(clan) = clan_bodies.get(key) else {
+            continue;
+        };
+        let clan_tag = clan.get("tag").and_then(|v| v.as_str()).unwrap_or_default();
+        let clan_name = clan
+            .get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        for member in clan["memberList"].as_array().into_iter().flatten() {
+            if let Some(tag) = member.get("tag").and_then(|v| v.as_str())
+                && seen.insert(normalize_tag(tag))
+            {
+                members.push((clan_tag.to_string(), clan_name.to_string(), member.clone()));
+            }
+        }
+    }
+
+    // Player profiles are needed for the TH and profile-based metrics
+    let player_key = |tag: &str| format!("{}:supercell:/players/{}", prefix, encode_tag(tag));
+    let needs_profiles = metric.needs_profile()
+        || (game == GameType::ClashOfClans
+            && (filters.min_level.is_some() || filters.max_level.is_some()));
+    let profiles = if needs_profiles {
+        let keys: Vec<String> = members
+            .iter()
+            .filter_map(|(_, _, m)| m.get("tag").and_then(|v| v.as_str()).map(player_key))
+            .collect();
+        cached_bodies(data, &keys, 0).await?
+    } else {
+        HashMap::new()
+    };
+
+    let mut entries = Vec::new();
+    for (clan_tag, clan_name, member) in members {
+        let tag = member
+            .get("tag")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let profile = profiles.get(&player_key(tag));
+
+        let level = match game {
+            GameType::ClashOfClans => member
+                .get("townHallLevel")
+                .or_else(|| profile.and_then(|p| p.get("townHallLevel")))
+                .and_then(|v| v.as_i64()),
+            GameType::ClashRoyale => member.get("expLevel").and_then(|v| v.as_i64()),
+        };
+        if filters
+            .min_level
+            .is_some_and(|min| level.is_none_or(|l| l < min))
+            || filters
+                .max_level
+                .is_some_and(|max| level.is_none_or(|l| l > max))
+        {
+            continue;
+        }
+
+        let Some(value) = metric.value(&member, profile) else {
+            continue;
+        };
+        entries.push(LeaderboardEntry {
+            rank: 0,
+            tag: tag.to_string(),
+            name: member
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or(tag)
+                .to_string(),
+            clan_tag,
+            clan_name,
+            level,
+            value,
+        });
+    }
+
+    let sort_value = |e: &LeaderboardEntry| e.value.as_f64().unwrap_or(0.0);
+    entries.sort_by(|a, b| {
+        sort_value(b)
+            .total_cmp(&sort_value(a))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    // Equal values share a rank (1, 1, 3)
+    for i in 0..entries.len() {
+        entries[i].rank = if i > 0 && entries[i].value == entries[i - 1].value {
+            entries[i - 1].rank
+        } else {
+            i as i64 + 1
+        };
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn profile_metrics_use_home_heroes_and_normalized_card_levels() {
+        let member = json!({ "tag": "#P1" });
+        let coc = json!({ "heroes": [
+            { "level": 90, "village": "home" },
+            { "level": 40, "village": "builderBase" },
+        ] });
+        assert_eq!(
+            Metric::HeroLevels.value(&member, Some(&coc)),
+            Some(Number::from(90))
+        );
+
+        // Common 14/14 is level 14 in game, legendary 5/6 is level 13
+        let cr = json!({ "cards": [
+            { "level": 14, "maxLevel": 14 },
+            { "level": 5, "maxLevel": 6 },
+        ] });
+        assert_eq!(
+            Metric::CardLevel.value(&member, Some(&cr)),
+            Number::from_f64(13.5)
+        );
+        assert_eq!(Metric::WarStars.value(&member, None), None);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::errors::ApiError;
+use crate::fixtures::{self, ExternalApi};
+use crate::models::{AccountLink, AppState};
+use crate::utils::{encode_tag, normalize_tag};
+
+use log::{info, warn};
+use serde_json::json;
+
+// Self-service links of CoC accounts, verified with the in-game API token. The CoC bot
+// stays the authority for links; verified links are pushed to it and kept locally until
+// the bot lists them, so `sync_user_accounts` does not drop them in the meantime.
+
+const SELECT_LINKS: &str = "SELECT player_tag, player_name, discord_id, verified_at, pushed_at, push_error FROM account_links";
+
+// Check an in-game API token (Settings > More Settings) with Supercell. Tokens are
+// single-use and expire after a few minutes.
+pub async fn verify_token(
+    data: &AppState,
+    player_tag: &str,
+    token: &str,
+) -> Result<bool, ApiError> {
+    let path = format!("/players/{}/verifytoken", encode_tag(player_tag));
+    let res = fixtures::post(
+        data,
+        ExternalApi::SupercellCoc,
+        &path,
+        &data.clash_of_clans_api_token,
+        &json!({ "token": token }),
+    )
+    .await
+    .map_err(ApiError::UpstreamUnavailable)?;
+    match res.status {
+        200 => {}
+        404 => return Err(ApiError::NotFound("Player not found".to_string())),
+        429 => {
+            return Err(ApiError::RateLimited(
+                "Supercell API rate limited".to_string(),
+            ));
+        }
+        status => {
+            return Err(ApiError::UpstreamUnavailable(format!(
+                "Supercell token verification failed ({})",
+                status
+            )));
+        }
+    }
+    let body: serde_json::Value = serde_json::from_slice(&res.body).map_err(|e| {
+        ApiError::UpstreamUnavailable(format!("Invalid verifytoken response: {}", e))
+    })?;
+    Ok(body.get("status").and_then(|s| s.as_str()) == Some("ok"))
+}
+
+// Send a link to the CoC bot (`POST /api/users/{id}/links`)
+async fn push_link(data: &AppState, discord_id: &str, player_tag: &str) -> Result<(), String> {
+    let res = fixtures::post(
+        data,
+        ExternalApi::UpstreamCoc,
+        &format!("/api/users/{}/links", discord_id),
+        &data.coc_api_token,
+        &json!({ "tag": player_tag }),
+    )
+    .await?;
+    if res.is_success() {
+        Ok(())
+    } else {
+        Err(format!("Bot answered {}", res.status))
+    }
+}
+
+async fn record_push(data: &AppState, player_tag: &str, result: &Result<(), String>) {
+    let now = chrono::Utc::now().timestamp();
+    let _ = sqlx::query(
+        "UPDATE account_links SET pushed_at = CASE WHEN $2::TEXT IS NULL THEN $3 END, push_error = $2
+         WHERE player_tag = $1",
+    )
+    .bind(player_tag)
+    .bind(result.as_ref().err())
+    .bind(now)
+    .execute(&data.db_pool)
+    .await;
+}
+
+// Store a verified link (a verified token moves the account away from a previous owner),
+// add it to the user's linked players and push it to the bot. A failed push is retried by
+// the `push_account_links` job.
+pub async fn link_account(
+    data: &AppState,
+    discord_id: &str,
+    player_tag: &str,
+    player_name: &str,
+) -> Result<AccountLink, ApiError> {
+    let tag = normalize_tag(player_tag);
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = data.db_pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO account_links (player_tag, player_name, discord_id, verified_at)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (player_tag) DO UPDATE SET player_name = $2, discord_id = $3, verified_at = $4,
+             pushed_at = NULL, push_error = NULL",
+    )
+    .bind(&tag)
+    .bind(player_name)
+    .bind(discord_id)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    // Previous owners lose the account right away, even while the bot is unreachable
+    sqlx::query(
+        "UPDATE users SET linked_players = (linked_players::jsonb - $1)::text
+         WHERE discord_id <> $2 AND linked_players::jsonb ? $1",
+    )
+    .bind(&tag)
+    .bind(discord_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let linked: Option<String> = sqlx::query_scalar(
+        "SELECT COALESCE(linked_players, '[]') FROM users WHERE discord_id = $1 FOR UPDATE",
+    )
+    .bind(discord_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let mut players: Vec<String> = linked
+        .and_then(|l| serde_json::from_str(&l).ok())
+        .unwrap_or_default();
+    if !players.iter().any(|p| normalize_tag(p) == tag) {
+        players.push(tag.clone());
+        sqlx::query("UPDATE users SET linked_players = $1 WHERE discord_id = $2")
+            .bind(serde_json::to_string(&players).unwrap_or_else(|_| "[]".to_string()))
+            .bind(discord_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    let pushed = push_link(data, discord_id, &tag).await;
+    if let Err(e) = &pushed {
+        warn!(
+            "Pushing link {} of {} to the CoC bot failed: {}",
+            tag, discord_id, e
+        );
+    }
+    record_push(data, &tag, &pushed).await;
+
+    sqlx::query_as::<_, AccountLink>(&format!("{} WHERE player_tag = $1", SELECT_LINKS))
+        .bind(&tag)
+        .fetch_one(&data.db_pool)
+        .await
+        .map_err(ApiError::from)
+}
+
+pub async fn links_of(data: &AppState, discord_id: &str) -> Result<Vec<AccountLink>, sqlx::Error> {
+    sqlx::query_as::<_, AccountLink>(&format!(
+        "{} WHERE discord_id = $1 ORDER BY verified_at ASC",
+        SELECT_LINKS
+    ))
+    .bind(discord_id)
+    .fetch_all(&data.db_pool)
+    .await
+}
+
+// Merge verified links the bot has not accepted yet into CoC accounts. Once a link is
+// pushed the bot is the authority again, so an unlink there is not undone here. If
+// `players` is the list of the bot, links it already contains count as pushed. Returns
+// whether `players` changed.
+pub async fn merge_verified(
+    data: &AppState,
+    discord_id: &str,
+    players: &mut Vec<String>,
+    from_bot: bool,
+) -> bool {
+    let Ok(links) = links_of(data, discord_id).await else {
+        return false;
+    };
+    let mut modified = false;
+    for link in links.into_iter().filter(|l| l.pushed_at.is_none()) {
+        if players.iter().any(|p| normalize_tag(p) == link.player_tag) {
+            if from_bot {
+                record_push(data, &link.player_tag, &Ok(())).await;
+            }
+        } else {
+            players.push(link.player_tag);
+            modified = true;
+        }
+    }
+    modified
+}
+
+// Retry pushing links the bot has not accepted yet
+pub async fn push_pending_links(data: &AppState) -> Result<i64, String> {
+    let pending = sqlx::query_as::<_, (String, String)>(
+        "SELECT player_tag, discord_id FROM account_links WHERE pushed_at IS NULL",
+    )
+    .fetch_all(&data.db_pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut pushed = 0;
+    for (tag, discord_id) in pending {
+        let result = push_link(data, &discord_id, &tag).await;
+        if result.is_ok() {
+            pushed += 1;
+        }
+        record_push(data, &tag, &result).await;
+    }
+    if pushed > 0 {
+        info!("Pushed {} verified account links to the CoC bot", pushed);
+    }
+    Ok(pushed)
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::sync::Arc;
+use utoipa::ToSchema;
 
 pub type DiscordOAuthClient = oauth2::Client<
     oauth2::StandardErrorResponse<oauth2::basic::BasicErrorResponseType>,
@@ -23,19 +25,31 @@ pub struct AppState {
     // CR Upstream API (new)
     pub upstream_cr_url: String,
     pub cr_api_token: String,
+    // HMAC secrets of the bots' change webhooks (empty: disabled)
+    pub coc_webhook_secret: String,
+    pub cr_webhook_secret: String,
     // Official Supercell APIs
     pub clash_of_clans_api_token: String,
     pub clash_royale_api_token: String,
+    pub supercell_coc_url: String,
+    pub supercell_cr_url: String,
+    pub discord_api_url: String,
+    // Verifies requests to /discord/interactions (disabled if not configured)
+    pub discord_public_key: Option<ed25519_dalek::VerifyingKey>,
+    // Live, record or replay of external API responses
+    pub fixtures: crate::fixtures::FixtureMode,
     pub db_pool: PgPool,
     pub oauth_client: DiscordOAuthClient,
     pub jwt_secret: String,
     pub frontend_url: String,
     pub background_refresh_interval: u64,
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct ErrorResponse {
-    pub error: String,
+    // Max. player profiles fetched per game and refresh run
+    pub player_refresh_budget: i64,
+    pub incident_failure_threshold: i64,
+    pub cache_policies: crate::utils::CachePolicies,
+    pub inactivity: crate::config::InactivityConfig,
+    pub jobs: Arc<crate::jobs::JobRegistry>,
+    pub leader: Arc<crate::leader::LeaderState>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,7 +58,7 @@ pub enum GameType {
     ClashRoyale,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, ToSchema)]
 pub struct SideClan {
     pub clan_tag: String,
     pub name: String,
@@ -54,7 +68,7 @@ pub struct SideClan {
     pub badge_url: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, ToSchema)]
 pub struct SideClanCWLStats {
     pub clan_tag: String,
     pub season: String, // YYYY-MM
@@ -64,8 +78,745 @@ pub struct SideClanCWLStats {
     pub rank: Option<i32>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct SideClanCwlHistory {
     pub clan: SideClan,
     pub history: Vec<SideClanCWLStats>,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct LatencyProbe {
+    pub name: String,
+    pub url: String,
+    pub auth: String, // none, upstream_coc, upstream_cr, supercell_coc, supercell_cr
+    pub expected_status: i32,
+    pub timeout_ms: i32,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct Incident {
+    pub id: i32,
+    pub api_name: String,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub failure_count: i32,
+    pub error_kind: Option<String>,
+    pub affected_endpoints: String, // JSON array
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, ToSchema)]
+pub struct JobRun {
+    pub id: i32,
+    pub job_name: String,
+    pub target: Option<String>,
+    pub trigger: String, // schedule, manual
+    pub status: String,  // queued, running, success, failed, panicked, interrupted
+    pub instance_id: Option<String>,
+    pub error: Option<String>,
+    pub items_processed: Option<i64>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, ToSchema)]
+pub struct SearchResult {
+    pub game: String, // coc, cr
+    pub kind: String, // clan, player
+    pub tag: String,
+    pub name: String,
+    // Discord nickname (members only)
+    pub nickname: Option<String>,
+    // Clan of a player
+    pub clan_tag: Option<String>,
+    pub clan_name: Option<String>,
+    // Trigram similarity of the best matching field (0..1)
+    pub score: f32,
+    // Last index sync of the entry, reported as freshness instead of in the body
+    #[serde(skip)]
+    pub updated_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LeaderboardEntry {
+    // Equal values share a rank
+    pub rank: i64,
+    pub tag: String,
+    pub name: String,
+    pub clan_tag: String,
+    pub clan_name: String,
+    // Town hall (CoC) or King level (CR)
+    pub level: Option<i64>,
+    // Integer for all metrics except the average card level
+    #[schema(value_type = f64)]
+    pub value: serde_json::Number,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, ToSchema)]
+pub struct SeasonStats {
+    pub season: String, // e.g. 2026-10
+    pub clan_tag: String,
+    pub player_tag: String,
+    pub name: String,
+    pub donations: i64,
+    pub donations_received: i64,
+    pub trophies: i64,
+    // CoC: attack wins, CR: wins since the first snapshot of the season
+    pub season_wins: Option<i64>,
+    // Time of the last snapshot (the final values once the season is over)
+    pub captured_at: i64,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct SeasonWindow {
+    // Season key, e.g. 2026-10 (raid weekends: start date 2026-10-16)
+    pub key: String,
+    pub start: i64,
+    pub end: i64,
+    pub active: bool,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct SeasonCalendar {
+    pub coc_season: SeasonWindow,
+    pub cr_season: SeasonWindow,
+    pub cwl: SeasonWindow,
+    pub raid_weekend: SeasonWindow,
+    pub clan_games: SeasonWindow,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct InactivityEntry {
+    pub tag: String,
+    pub name: String,
+    pub role: Option<String>,
+    // First and latest profile fetch (null if never fetched)
+    pub first_seen_at: Option<i64>,
+    pub last_seen_at: Option<i64>,
+    // Latest fetch in which an activity counter went up
+    pub last_active_at: Option<i64>,
+    // Counter that went up, e.g. donations, attackWins, battleCount
+    pub last_signal: Option<String>,
+    pub inactive_for_days: Option<i64>,
+    // active, warning, inactive or unknown
+    pub status: String,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct InactivityReport {
+    pub warn_days: i64,
+    pub inactive_days: i64,
+    pub members: Vec<InactivityEntry>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeCategory {
+    // heroes, pets, troops, spells, equipment or total
+    pub category: String,
+    pub levels: i64,
+    // Sum of the max. levels at the current town hall
+    pub max_levels: i64,
+    pub progress_percent: f64,
+    // Share of the previous town hall's max. levels still missing
+    pub rushed_percent: f64,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeProgress {
+    pub town_hall: i64,
+    pub progress_percent: f64,
+    pub rushed_percent: f64,
+    pub categories: Vec<UpgradeCategory>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, ToSchema)]
+pub struct ClanRequirements {
+    pub clan_tag: String,
+    pub min_town_hall: Option<i64>,
+    // Sum of the home village hero levels
+    pub min_hero_levels: Option<i64>,
+    pub min_trophies: Option<i64>,
+    pub min_war_stars: Option<i64>,
+    pub max_rushed_percent: Option<f64>,
+    pub notes: Option<String>,
+    // Discord id of the last editor
+    pub updated_by: Option<String>,
+    pub updated_at: i64,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct ClanRequirementsUpdate {
+    pub min_town_hall: Option<i64>,
+    pub min_hero_levels: Option<i64>,
+    pub min_trophies: Option<i64>,
+    pub min_war_stars: Option<i64>,
+    pub max_rushed_percent: Option<f64>,
+    pub notes: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct RuleResult {
+    // Requirement column, e.g. min_town_hall or max_rushed_percent
+    pub rule: String,
+    pub required: f64,
+    // Null if the profile lacks the value (counts as failed)
+    pub actual: Option<f64>,
+    pub passed: bool,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct ClanEligibility {
+    pub clan_tag: String,
+    pub clan_name: String,
+    // Main clan of a side clan
+    pub belongs_to: Option<String>,
+    pub eligible: bool,
+    pub rules: Vec<RuleResult>,
+}
+
+#[derive(Serialize, Debug, Clone, sqlx::FromRow, ToSchema)]
+pub struct Application {
+    pub id: i64,
+    // coc or cr
+    pub game: String,
+    pub player_tag: String,
+    pub player_name: String,
+    pub discord_id: String,
+    pub message: Option<String>,
+    // pending, waitlisted, accepted, rejected or withdrawn
+    pub status: String,
+    // Best fitting eligible family clan at submission (CoC only)
+    pub suggested_clan: Option<String>,
+    pub assigned_clan: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Serialize, Debug, Clone, sqlx::FromRow, ToSchema)]
+pub struct ApplicationEvent {
+    pub id: i64,
+    // submitted, assign, accept, reject or withdraw
+    pub action: String,
+    // Discord ID of the applicant or reviewer
+    pub actor: String,
+    pub clan_tag: Option<String>,
+    pub note: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct ApplicationDetail {
+    #[serde(flatten)]
+    pub application: Application,
+    // Eligibility for the family clans at submission, best fit first (CoC only)
+    #[schema(value_type = Option<Vec<ClanEligibility>>)]
+    pub eligibility: Option<serde_json::Value>,
+    pub events: Vec<ApplicationEvent>,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct ApplicationCreate {
+    // coc or cr
+    pub game: String,
+    pub player_tag: String,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, sqlx::FromRow, ToSchema)]
+pub struct AccountLink {
+    pub player_tag: String,
+    pub player_name: String,
+    pub discord_id: String,
+    pub verified_at: i64,
+    // Null until the CoC bot accepted the link
+    pub pushed_at: Option<i64>,
+    // Last failed push to the CoC bot
+    pub push_error: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct AccountLinkRequest {
+    pub player_tag: String,
+    // In-game API token (Settings > More Settings > API Token)
+    pub token: String,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct BotEvent {
+    // player_updated, kickpoint_added, link_changed or clan_config_changed
+    pub event: String,
+    pub player_tag: Option<String>,
+    pub clan_tag: Option<String>,
+    // Discord user whose links changed (link_changed)
+    pub discord_id: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct WebhookResult {
+    pub event: String,
+    // Cache keys fetched again from the bot
+    pub refreshed: Vec<String>,
+    // Cache keys removed because the bot no longer serves them
+    pub invalidated: Vec<String>,
+    // Discord ID of the re-synced users row (link_changed)
+    pub user_synced: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct ApplicationDecision {
+    // accept, reject or assign
+    pub action: String,
+    // Family clan to assign or accept into (accept defaults to the assigned clan)
+    pub clan_tag: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct Me {
+    pub discord_id: String,
+    pub username: String,
+    pub global_name: Option<String>,
+    pub nickname: Option<String>,
+    pub avatar: Option<String>,
+    pub highest_role: Option<String>,
+    pub is_admin: bool,
+    pub linked_players: Vec<String>,
+    pub linked_cr_players: Vec<String>,
+}
+
+// Guild summary for non-admins (admins get the bot's full guild object)
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct GuildInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub membercount: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct KickpointReason {
+    pub name: String,
+    pub amount: i64,
+}
+
+// Kickpoint settings of a clan, taken from the bot's clan
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClanConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_kickpoints: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_season_wins: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kickpoints_expire_after_days: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kickpoint_reasons: Option<Vec<KickpointReason>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BadgeUrls {
+    pub small: String,
+    pub medium: String,
+    pub large: String,
+}
+
+impl BadgeUrls {
+    // The bots and the Clash Royale API only have a single `badgeUrl`
+    pub fn from_single(url: &str) -> Self {
+        BadgeUrls {
+            small: url.to_string(),
+            medium: url.to_string(),
+            large: url.to_string(),
+        }
+    }
+}
+
+// Family clan as listed by the bot. The kickpoint settings are removed for non-members.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FamilyClan {
+    pub tag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "nameDB", skip_serializing_if = "Option::is_none")]
+    pub name_db: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badge_urls: Option<BadgeUrls>,
+    #[serde(flatten)]
+    pub config: ClanConfig,
+    // Remaining bot fields
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+// Supercell clan merged with the bot's clan settings
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClanInfo {
+    pub tag: String,
+    pub name: String,
+    #[serde(rename = "nameDB", skip_serializing_if = "Option::is_none")]
+    pub name_db: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badge_urls: Option<BadgeUrls>,
+    #[serde(flatten)]
+    pub config: ClanConfig,
+    // Remaining Supercell fields (memberList, clanLevel, warLeague, ...)
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+// Clan member merged from the Supercell member list and the bot's member list
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct MergedMember {
+    pub tag: String,
+    pub name: String,
+    pub role: String,
+    // Listed by Supercell (currently in the clan)
+    pub in_supercell: bool,
+    // Listed by the bot (registered for the clan)
+    pub in_upstream: bool,
+    // Name, role or level differ between Supercell and the bot
+    pub is_dirty: bool,
+    // Not in sync: dirty, only in Supercell or only in the bot
+    pub is_diff: bool,
+    // In the clan but not registered in the bot
+    pub is_new: bool,
+    // Registered in the bot but no longer in the clan
+    pub is_left: bool,
+    // Bot values of fields Supercell reports as well (further ones get the same prefix)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_role: Option<String>,
+    // CoC: upgrade progress and rush percentage of the town hall (from the player cache)
+    #[serde(rename = "upgradeProgress", skip_serializing_if = "Option::is_none")]
+    pub upgrade_progress: Option<UpgradeProgress>,
+    // Members only (the full kickpoints are removed)
+    #[serde(
+        rename = "activeKickpointsCount",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub active_kickpoints_count: Option<i64>,
+    #[serde(
+        rename = "activeKickpointsSum",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub active_kickpoints_sum: Option<i64>,
+    // Members only: the bot links the player to a Discord user (the IDs are removed)
+    #[serde(rename = "isLinked", skip_serializing_if = "Option::is_none")]
+    pub is_linked: Option<bool>,
+    // Remaining Supercell, bot and player profile fields
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+// Member of a bot member list (lite, war, raid or CWL members), filtered for the role
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BotMember {
+    pub tag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_kickpoints_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_kickpoints_sum: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_linked: Option<bool>,
+    // Remaining bot fields
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+// Active kickpoint. description and reason are only included for COLEADER+ or the owner.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Kickpoint {
+    #[serde(default)]
+    pub amount: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // Remaining bot fields (date, reason, ...)
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+// Bot user, merged across the CoC and CR bot
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BotUser {
+    // True if either bot reports the user as admin
+    #[serde(default)]
+    pub admin: bool,
+    // Highest role across both bots
+    pub highest_role: Option<String>,
+    pub nickname: Option<String>,
+    #[serde(default)]
+    pub linked_players: Vec<String>,
+    #[serde(default)]
+    pub linked_cr_players: Vec<String>,
+    // Remaining bot fields
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KickpointSummary {
+    pub total: i64,
+    pub active_count: i64,
+    pub active_sum: i64,
+}
+
+// Supercell player profile plus the bot's kickpoint summary (MEMBER role or own account)
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Player {
+    // CoC: upgrade progress and rush percentage of the town hall
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upgrade_progress: Option<UpgradeProgress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_kickpoints_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_kickpoints_sum: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_kickpoints: Option<i64>,
+    // Supercell player fields
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub profile: serde_json::Map<String, serde_json::Value>,
+}
+
+// Discord identity of a player's owner. userId, discordId and playerAccounts are only
+// included for COLEADER+ or the owner. Values are passed through from the bot.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerIdentity {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub nickname: Option<serde_json::Value>,
+    #[serde(rename = "global_name", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub global_name: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub username: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub avatar: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub user_id: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub discord_id: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Vec<Object>>)]
+    pub player_accounts: Option<serde_json::Value>,
+}
+
+// Linked account: Supercell profile merged with the bot's player entry. Bot fields that
+// collide with Supercell fields get an `upstream_` prefix (the bot's clan: `upstream_clan`).
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedPlayer {
+    pub game_type: String, // coc, cr
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_kickpoints_count: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_kickpoints_sum: Option<i64>,
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub profile: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct LinkedAccounts {
+    pub coc: Vec<LinkedPlayer>,
+    pub cr: Vec<LinkedPlayer>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct PublicService {
+    pub service: String,
+    pub label: String,
+    pub status: String, // ONLINE, DEGRADED, OFFLINE
+    pub availability_24h: Option<f64>,
+    pub availability_30d: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct PublicIncident {
+    pub id: i32,
+    pub service: String,
+    pub label: String,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub duration_secs: i64,
+    pub ongoing: bool,
+    pub error_kind: Option<String>,
+    pub affected_endpoints: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct PublicStatus {
+    pub services: Vec<PublicService>,
+    pub incidents: Vec<PublicIncident>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct ProbeStatus {
+    pub status: String, // ONLINE, OFFLINE
+    pub latency: i32,
+    pub uptime_minutes: i32,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct InstanceInfo {
+    pub id: String,
+    pub started_at: i64,
+    pub is_leader: bool,
+}
+
+#[derive(Serialize, Debug, Clone, sqlx::FromRow, ToSchema)]
+pub struct LeaderLease {
+    pub instance_id: String,
+    pub acquired_at: i64,
+    pub heartbeat_at: i64,
+}
+
+// Probe status keyed by probe name, plus this instance and the current leader
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct AdminStatus {
+    pub instance: InstanceInfo,
+    // Instance holding the leader lease (runs the background jobs)
+    pub leader: Option<LeaderLease>,
+    #[serde(flatten)]
+    pub probes: std::collections::HashMap<String, ProbeStatus>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct LatencySample {
+    pub api: String,
+    pub latency: i32,
+    pub timestamp: i64,
+    pub status: Option<i32>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct LatencyRollup {
+    pub api: String,
+    // p50 doubles as latency so charts built for raw samples keep working
+    pub latency: i32,
+    pub timestamp: i64,
+    pub resolution: String, // hour, day
+    pub samples: i32,
+    pub p50: Option<i32>,
+    pub p95: Option<i32>,
+    pub avg: Option<i32>,
+    pub availability: f64,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(untagged)]
+pub enum LatencyPoint {
+    Sample(LatencySample),
+    Rollup(LatencyRollup),
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct JobInfo {
+    pub name: String,
+    pub description: String,
+    pub schedule: String,
+    pub running: bool,
+    pub last_run: Option<JobRun>,
+    pub last_success: Option<JobRun>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct JobStarted {
+    pub run_id: i32,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct ClanRefreshed {
+    pub status: String,
+    pub tag: String,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct CacheEntrySummary {
+    pub key: String,
+    // Stored (compressed) size in bytes
+    pub size: i32,
+    pub status: i32,
+    pub updated_at: i64,
+    pub age_secs: i64,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct CacheEntryList {
+    pub total: i64,
+    pub entries: Vec<CacheEntrySummary>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct CacheEntry {
+    pub key: String,
+    pub size: i64,
+    pub decoded_size: i64,
+    pub status: i32,
+    pub updated_at: i64,
+    pub age_secs: i64,
+    // JSON bodies as JSON, anything else as text
+    #[schema(value_type = Object)]
+    pub body: serde_json::Value,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct CacheDeleted {
+    pub deleted: u64,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct CacheRefreshed {
+    pub key: String,
+    pub size: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct CacheClassStats {
+    pub entries: i64,
+    pub bytes: i64,
+    pub pinned: i64,
+    // Would be evicted by the next GC run
+    pub expired: i64,
+    pub oldest_update: Option<i64>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct CacheStats {
+    pub table_bytes: i64,
+    pub entries: i64,
+    pub body_bytes: i64,
+    // Keyed by class: clan, player, user, other
+    pub classes: std::collections::HashMap<String, CacheClassStats>,
+}
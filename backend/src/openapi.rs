@@ -0,0 +1,305 @@
+use crate::errors::ApiErrorBody;
+use crate::models::{
+    AccountLink, AccountLinkRequest, AdminStatus, Application, ApplicationCreate,
+    ApplicationDecision, ApplicationDetail, ApplicationEvent, BadgeUrls, BotEvent, BotMember,
+    BotUser, CacheClassStats, CacheDeleted, CacheEntry, CacheEntryList, CacheEntrySummary,
+    CacheRefreshed, CacheStats, ClanConfig, ClanEligibility, ClanInfo, ClanRefreshed,
+    ClanRequirements, ClanRequirementsUpdate, FamilyClan, GuildInfo, InactivityEntry,
+    InactivityReport, InstanceInfo, JobInfo, JobRun, JobStarted, Kickpoint, KickpointReason,
+    KickpointSummary, LatencyPoint, LatencyRollup, LatencySample, LeaderLease, LeaderboardEntry,
+    LinkedAccounts, LinkedPlayer, Me, MergedMember, Player, PlayerIdentity, ProbeStatus,
+    PublicIncident, PublicService, PublicStatus, RuleResult, SearchResult, SeasonCalendar,
+    SeasonStats, SeasonWindow, SideClan, SideClanCWLStats, SideClanCwlHistory, UpgradeCategory,
+    UpgradeProgress, WebhookResult,
+};
+
+use actix_web::{HttpResponse, Responder};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+// Name of the cookie security scheme referenced by `security(...)` in the path annotations
+pub const AUTH_COOKIE_SCHEME: &str = "auth_token";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "LOST Family API",
+        description = "Clan, player and status data of the LOST family. Every operation carries \
+            an `x-required-role` extension (PUBLIC, AUTHENTICATED, MEMBER, COLEADER, LEADER, ADMIN). \
+            Errors are returned as `ApiErrorBody` with a stable `code`."
+    ),
+    paths(
+        crate::auth::discord_login,
+        crate::auth::discord_callback,
+        crate::auth::get_me,
+        crate::auth::logout,
+        crate::handlers::get_my_player_accounts,
+        crate::handlers::get_my_account_links,
+        crate::handlers::link_my_account,
+        crate::handlers::get_user,
+        crate::handlers::get_user_player_accounts,
+        crate::handlers::get_coc_clans,
+        crate::handlers::get_coc_clan_info,
+        crate::handlers::get_coc_clan_config,
+        crate::handlers::get_coc_clan_members,
+        crate::handlers::get_coc_clan_members_lite,
+        crate::handlers::get_coc_clan_kickpoint_reasons,
+        crate::handlers::get_coc_clan_inactivity,
+        crate::handlers::get_coc_clan_war_members,
+        crate::handlers::get_coc_raid_members,
+        crate::handlers::get_coc_cwl_members,
+        crate::handlers::get_coc_player,
+        crate::handlers::get_coc_player_identity,
+        crate::handlers::get_coc_player_kickpoints,
+        crate::handlers::get_coc_player_kickpoints_details,
+        crate::handlers::get_coc_leaderboard,
+        crate::handlers::get_coc_clan_seasons,
+        crate::handlers::get_coc_clan_season,
+        crate::handlers::get_coc_player_seasons,
+        crate::handlers::get_coc_clan_requirements,
+        crate::handlers::put_coc_clan_requirements,
+        crate::handlers::get_coc_clan_eligibility,
+        crate::handlers::get_coc_family_eligibility,
+        crate::handlers::get_cr_clans,
+        crate::handlers::get_cr_clan_info,
+        crate::handlers::get_cr_clan_config,
+        crate::handlers::get_cr_clan_members,
+        crate::handlers::get_cr_clan_members_lite,
+        crate::handlers::get_cr_clan_kickpoint_reasons,
+        crate::handlers::get_cr_clan_inactivity,
+        crate::handlers::get_cr_player,
+        crate::handlers::get_cr_player_identity,
+        crate::handlers::get_cr_player_kickpoints,
+        crate::handlers::get_cr_player_kickpoints_details,
+        crate::handlers::get_cr_leaderboard,
+        crate::handlers::get_cr_clan_seasons,
+        crate::handlers::get_cr_clan_season,
+        crate::handlers::get_cr_player_seasons,
+        crate::handlers::get_guild_info,
+        crate::handlers::get_public_status,
+        crate::handlers::get_calendar,
+        crate::handlers::get_admin_status,
+        crate::handlers::get_latency_history,
+        crate::handlers::get_jobs,
+        crate::handlers::get_job_runs,
+        crate::handlers::trigger_job,
+        crate::handlers::refresh_clan_now,
+        crate::handlers::get_cache_entries,
+        crate::handlers::delete_cache_entries,
+        crate::handlers::get_cache_stats,
+        crate::handlers::get_cache_entry,
+        crate::handlers::refresh_cache_entry,
+        crate::handlers::get_side_clans,
+        crate::handlers::search,
+        crate::handlers::submit_application,
+        crate::handlers::get_my_applications,
+        crate::handlers::withdraw_application,
+        crate::handlers::get_applications,
+        crate::handlers::get_application,
+        crate::handlers::decide_application,
+        crate::handlers::discord_interactions,
+        crate::handlers::bot_webhook,
+        get_openapi_spec,
+    ),
+    components(schemas(
+        AccountLink,
+        AccountLinkRequest,
+        AdminStatus,
+        ApiErrorBody,
+        Application,
+        ApplicationCreate,
+        ApplicationDecision,
+        ApplicationDetail,
+        ApplicationEvent,
+        BadgeUrls,
+        BotEvent,
+        BotMember,
+        BotUser,
+        CacheClassStats,
+        CacheDeleted,
+        CacheEntry,
+        CacheEntryList,
+        CacheEntrySummary,
+        CacheRefreshed,
+        CacheStats,
+        ClanConfig,
+        ClanEligibility,
+        ClanInfo,
+        ClanRefreshed,
+        ClanRequirements,
+        ClanRequirementsUpdate,
+        FamilyClan,
+        GuildInfo,
+        InactivityEntry,
+        InactivityReport,
+        InstanceInfo,
+        JobInfo,
+        JobRun,
+        JobStarted,
+        Kickpoint,
+        KickpointReason,
+        KickpointSummary,
+        LatencyPoint,
+        LatencyRollup,
+        LatencySample,
+        LeaderLease,
+        LeaderboardEntry,
+        LinkedAccounts,
+        LinkedPlayer,
+        Me,
+        MergedMember,
+        Player,
+        PlayerIdentity,
+        ProbeStatus,
+        PublicIncident,
+        PublicService,
+        PublicStatus,
+        RuleResult,
+        SearchResult,
+        SeasonCalendar,
+        SeasonStats,
+        SeasonWindow,
+        SideClan,
+        SideClanCWLStats,
+        SideClanCwlHistory,
+        UpgradeCategory,
+        UpgradeProgress,
+        WebhookResult,
+    )),
+    modifiers(&CookieAuth),
+    tags(
+        (name = "auth", description = "Discord login and the current session"),
+        (name = "users", description = "Discord users and their linked accounts"),
+        (name = "coc", description = "Clash of Clans clans and players"),
+        (name = "cr", description = "Clash Royale clans and players"),
+        (name = "search", description = "Family-wide search over players and clans"),
+        (name = "applications", description = "Applications to join the family and the review queue"),
+        (name = "discord", description = "Discord interactions (slash commands)"),
+        (name = "webhooks", description = "Change events pushed by the bots"),
+        (name = "status", description = "Guild info and public service status"),
+        (name = "admin", description = "Monitoring, jobs and cache administration"),
+    )
+)]
+pub struct ApiDoc;
+
+struct CookieAuth;
+
+impl Modify for CookieAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            AUTH_COOKIE_SCHEME,
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::with_description(
+                "auth_token",
+                "JWT set by /auth/discord/callback",
+            ))),
+        );
+    }
+}
+
+// OpenAPI Specification
+#[utoipa::path(
+    get,
+    path = "/api/openapi.json",
+    tag = "status",
+    extensions(("x-required-role" = json!("PUBLIC"))),
+    responses((status = 200, description = "This document"))
+)]
+pub async fn get_openapi_spec() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::path::HttpMethod;
+
+    // (method, path) of every `.route(...)` registered in main.rs
+    fn registered_routes() -> Vec<(String, String)> {
+        let source = include_str!("main.rs");
+        let mut routes = Vec::new();
+        for chunk in source.split(".route(").skip(1) {
+            let mut parts = chunk.splitn(3, '"');
+            let path = parts.nth(1).expect("route path literal").to_string();
+            let rest = parts.next().unwrap_or_default();
+            let method = rest
+                .split("web::")
+                .nth(1)
+                .and_then(|m| m.split("()").next())
+                .expect("route method")
+                .to_string();
+            routes.push((method, path));
+        }
+        routes
+    }
+
+    fn http_method(method: &str) -> HttpMethod {
+        match method {
+            "get" => HttpMethod::Get,
+            "post" => HttpMethod::Post,
+            "put" => HttpMethod::Put,
+            "patch" => HttpMethod::Patch,
+            "delete" => HttpMethod::Delete,
+            other => panic!("Unsupported route method '{}'", other),
+        }
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        let spec = ApiDoc::openapi();
+        let routes = registered_routes();
+        assert!(routes.len() > 40, "failed to parse routes from main.rs");
+
+        let missing: Vec<String> = routes
+            .iter()
+            .filter(|(method, path)| {
+                let Some(item) = spec.paths.paths.get(path) else {
+                    return true;
+                };
+                let op = match http_method(method) {
+                    HttpMethod::Get => &item.get,
+                    HttpMethod::Post => &item.post,
+                    HttpMethod::Put => &item.put,
+                    HttpMethod::Patch => &item.patch,
+                    HttpMethod::Delete => &item.delete,
+                    _ => return true,
+                };
+                op.is_none()
+            })
+            .map(|(method, path)| format!("{} {}", method.to_uppercase(), path))
+            .collect();
+
+        assert!(
+            missing.is_empty(),
+            "Routes missing from the OpenAPI spec: {:?}",
+            missing
+        );
+    }
+
+    #[test]
+    fn every_operation_declares_a_role() {
+        let spec = ApiDoc::openapi();
+        for (path, item) in &spec.paths.paths {
+            for op in [&item.get, &item.post, &item.put, &item.patch, &item.delete]
+                .into_iter()
+                .flatten()
+            {
+                let role = op
+                    .extensions
+                    .as_ref()
+                    .and_then(|ext| ext.get("x-required-role"))
+                    .and_then(|v| v.as_str());
+                assert!(
+                    matches!(
+                        role,
+                        Some(
+                            "PUBLIC" | "AUTHENTICATED" | "MEMBER" | "COLEADER" | "LEADER" | "ADMIN"
+                        )
+                    ),
+                    "{} has no valid x-required-role",
+                    path
+                );
+            }
+        }
+    }
+}
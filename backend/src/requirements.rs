@@ -0,0 +1,229 @@
+use crate::background::family_clan_tags;
+use crate::leaderboards::Metric;
+use crate::models::{
+    AppState, ClanEligibility, ClanRequirements, ClanRequirementsUpdate, GameType, RuleResult,
+};
+use crate::utils::{encode_tag, get_cache_body, normalize_tag};
+
+use serde_json::Value;
+
+const SELECT_REQUIREMENTS: &str =
+    "SELECT clan_tag, min_town_hall, min_hero_levels, min_trophies, min_war_stars,
+     max_rushed_percent, notes, updated_by, updated_at FROM clan_requirements";
+
+pub async fn get_requirements(
+    data: &AppState,
+    clan_tag: &str,
+) -> Result<Option<ClanRequirements>, sqlx::Error> {
+    sqlx::query_as::<_, ClanRequirements>(&format!("{} WHERE clan_tag = $1", SELECT_REQUIREMENTS))
+        .bind(clan_tag)
+        .fetch_optional(&data.db_pool)
+        .await
+}
+
+pub async fn set_requirements(
+    data: &AppState,
+    clan_tag: &str,
+    update: &ClanRequirementsUpdate,
+    updated_by: &str,
+) -> Result<ClanRequirements, sqlx::Error> {
+    sqlx::query_as::<_, ClanRequirements>(
+        "INSERT INTO clan_requirements (clan_tag, min_town_hall, min_hero_levels, min_trophies, min_war_stars,
+             max_rushed_percent, notes, updated_by, updated_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         ON CONFLICT (clan_tag) DO UPDATE SET min_town_hall = $2, min_hero_levels = $3, min_trophies = $4,
+             min_war_stars = $5, max_rushed_percent = $6, notes = $7, updated_by = $8, updated_at = $9
+         RETURNING clan_tag, min_town_hall, min_hero_levels, min_trophies, min_war_stars,
+             max_rushed_percent, notes, updated_by, updated_at",
+    )
+    .bind(clan_tag)
+    .bind(update.min_town_hall)
+    .bind(update.min_hero_levels)
+    .bind(update.min_trophies)
+    .bind(update.min_war_stars)
+    .bind(update.max_rushed_percent)
+    .bind(&update.notes)
+    .bind(updated_by)
+    .bind(chrono::Utc::now().timestamp())
+    .fetch_one(&data.db_pool)
+    .await
+}
+
+// Check a CoC player profile against the requirements of a clan, one result per set rule
+pub fn evaluate(requirements: &ClanRequirements, profile: &Value) -> Vec<RuleResult> {
+    let int = |field: &str| profile.get(field).and_then(|v| v.as_i64());
+    let hero_levels = Metric::HeroLevels
+        .value(&Value::Null, Some(profile))
+        .and_then(|n| n.as_i64());
+    let rushed = crate::rush::upgrade_progress(profile).map(|p| p.rushed_percent);
+
+    let mut rules = Vec::new();
+    for (rule, required, actual) in [
+        (
+            "min_town_hall",
+            requirements.min_town_hall,
+            int("townHallLevel"),
+        ),
+        ("min_hero_levels", requirements.min_hero_levels, hero_levels),
+        ("min_trophies", requirements.min_trophies, int("trophies")),
+        ("min_war_stars", requirements.min_war_stars, int("warStars")),
+    ] {
+        if let Some(required) = required {
+            rules.push(RuleResult {
+                rule: rule.to_string(),
+                required: required as f64,
+                actual: actual.map(|a| a as f64),
+                passed: actual.is_some_and(|a| a >= required),
+            });
+        }
+    }
+    if let Some(max) = requirements.max_rushed_percent {
+        rules.push(RuleResult {
+            rule: "max_rushed_percent".to_string(),
+            required: max,
+            actual: rushed,
+            passed: rushed.is_some_and(|r| r <= max),
+        });
+    }
+    rules
+}
+
+// (tag, name, main clan, display index) of the clan list and all side clans. Names of
+// clans not yet synced to `side_clans` come from the cached Supercell clan.
+pub async fn family_clans(
+    data: &AppState,
+) -> Result<Vec<(String, String, Option<String>, i32)>, sqlx::Error> {
+    let tags = family_clan_tags(data, GameType::ClashOfClans)
+        .await
+        .unwrap_or_default();
+    let side_clans = sqlx::query_as::<_, (String, String, Option<String>, Option<i32>)>(
+        "SELECT clan_tag, name, belongs_to, display_index FROM side_clans",
+    )
+    .fetch_all(&data.db_pool)
+    .await?;
+
+    let mut clans = Vec::new();
+    for tag in tags {
+        let known = side_clans
+            .iter()
+            .find(|(t, ..)| normalize_tag(t) == normalize_tag(&tag));
+        let (name, belongs_to, index) = match known {
+            Some((_, name, belongs_to, index)) => {
+                (name.clone(), belongs_to.clone(), index.unwrap_or(0))
+            }
+            None => {
+                let key = format!("coc:supercell:/clans/{}", encode_tag(&tag));
+                let name = get_cache_body(data, &key)
+                    .await?
+                    .and_then(|b| serde_json::from_slice::<Value>(&b).ok())
+                    .and_then(|c| c.get("name")?.as_str().map(str::to_string))
+                    .unwrap_or_else(|| tag.clone());
+                (name, None, 0)
+            }
+        };
+        clans.push((normalize_tag(&tag), name, belongs_to, index));
+    }
+    Ok(clans)
+}
+
+// Eligibility of a profile for every family clan (main and side clans), best fit first:
+// eligible clans with the highest requirements, then the clans with the fewest failed
+// rules. Main clans come before their side clans on equal requirements. Clans without
+// requirements are open to everyone.
+pub async fn family_eligibility(
+    data: &AppState,
+    profile: &Value,
+) -> Result<Vec<ClanEligibility>, sqlx::Error> {
+    let clans = family_clans(data).await?;
+    let requirements = sqlx::query_as::<_, ClanRequirements>(SELECT_REQUIREMENTS)
+        .fetch_all(&data.db_pool)
+        .await?;
+
+    let mut ranked: Vec<(ClanEligibility, [i64; 4], bool, i32)> = clans
+        .into_iter()
+        .map(|(clan_tag, clan_name, belongs_to, display_index)| {
+            let req = requirements
+                .iter()
+                .find(|r| normalize_tag(&r.clan_tag) == normalize_tag(&clan_tag));
+            let rules = req.map(|r| evaluate(r, profile)).unwrap_or_default();
+            let strictness = req
+                .map(|r| {
+                    [
+                        r.min_town_hall,
+                        r.min_hero_levels,
+                        r.min_trophies,
+                        r.min_war_stars,
+                    ]
+                    .map(|v| v.unwrap_or(0))
+                })
+                .unwrap_or_default();
+            let eligibility = ClanEligibility {
+                eligible: rules.iter().all(|r| r.passed),
+                clan_tag,
+                clan_name,
+                belongs_to: belongs_to.clone(),
+                rules,
+            };
+            (eligibility, strictness, belongs_to.is_none(), display_index)
+        })
+        .collect();
+
+    let failed = |e: &ClanEligibility| e.rules.iter().filter(|r| !r.passed).count();
+    ranked.sort_by(
+        |(a, a_strict, a_main, a_index), (b, b_strict, b_main, b_index)| {
+            b.eligible
+                .cmp(&a.eligible)
+                .then_with(|| failed(a).cmp(&failed(b)))
+                // Eligible: the highest requirements, otherwise the lowest
+                .then_with(|| {
+                    if a.eligible {
+                        b_strict.cmp(a_strict)
+                    } else {
+                        a_strict.cmp(b_strict)
+                    }
+                })
+                .then_with(|| b_main.cmp(a_main))
+                .then_with(|| a_index.cmp(b_index))
+        },
+    );
+    Ok(ranked.into_iter().map(|(e, ..)| e).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rules_without_profile_data_fail() {
+        let requirements = ClanRequirements {
+            clan_tag: "#AAA".to_string(),
+            min_town_hall: Some(14),
+            min_hero_levels: Some(200),
+            min_trophies: None,
+            min_war_stars: Some(500),
+            max_rushed_percent: None,
+            notes: None,
+            updated_by: None,
+            updated_at: 0,
+        };
+        let profile = json!({
+            "townHallLevel": 15,
+            "heroes": [
+                { "name": "Barbarian King", "level": 90, "village": "home" },
+                { "name": "Archer Queen", "level": 90, "village": "home" },
+            ],
+        });
+        let rules = evaluate(&requirements, &profile);
+        let passed: Vec<(&str, bool)> = rules.iter().map(|r| (r.rule.as_str(), r.passed)).collect();
+        assert_eq!(
+            passed,
+            [
+                ("min_town_hall", true),
+                ("min_hero_levels", false),
+                ("min_war_stars", false)
+            ]
+        );
+        assert_eq!(rules[1].actual, Some(180.0));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::models::{UpgradeCategory, UpgradeProgress};
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
+
+// Reference table of max. levels per town hall, see `data/max_levels.toml`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaxLevels {
+    heroes: BTreeMap<String, Vec<i64>>,
+    pets: BTreeMap<String, Vec<i64>>,
+    troops: BTreeMap<String, Vec<i64>>,
+    spells: BTreeMap<String, Vec<i64>>,
+    equipment: EquipmentCaps,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EquipmentCaps {
+    common: Vec<i64>,
+    epic: Vec<i64>,
+}
+
+// Max. level of common equipment in the API, epic items go higher
+const COMMON_EQUIPMENT_MAX_LEVEL: i64 = 18;
+
+static MAX_LEVELS: LazyLock<MaxLevels> = LazyLock::new(|| {
+    toml::from_str(include_str!("../data/max_levels.toml")).expect("Invalid data/max_levels.toml")
+});
+
+// Max. level at a town hall (1-based), 0 before TH1 or if not unlocked
+fn cap(levels: &[i64], town_hall: i64) -> i64 {
+    usize::try_from(town_hall - 1)
+        .ok()
+        .and_then(|i| levels.get(i.min(levels.len().saturating_sub(1))))
+        .copied()
+        .unwrap_or(0)
+}
+
+#[derive(Default)]
+struct Sums {
+    levels: i64,
+    max_levels: i64,
+    // Levels missing to the max. of the previous town hall
+    rushed_levels: i64,
+    previous_max_levels: i64,
+}
+
+impl Sums {
+    fn add(&mut self, level: i64, max: i64, previous_max: i64) {
+        self.levels += level.min(max);
+        self.max_levels += max;
+        self.rushed_levels += (previous_max - level).max(0);
+        self.previous_max_levels += previous_max;
+    }
+
+    fn category(&self, name: &str) -> UpgradeCategory {
+        UpgradeCategory {
+            category: name.to_string(),
+            levels: self.levels,
+            max_levels: self.max_levels,
+            progress_percent: percent(self.levels, self.max_levels, 100.0),
+            rushed_percent: percent(self.rushed_levels, self.previous_max_levels, 0.0),
+        }
+    }
+}
+
+fn percent(part: i64, total: i64, if_empty: f64) -> f64 {
+    if total == 0 {
+        return if_empty;
+    }
+    (part as f64 * 1000.0 / total as f64).round() / 10.0
+}
+
+// Home village levels of a profile list (`heroes`, `troops`, ...) by name
+fn home_levels(profile: &Value, field: &str) -> HashMap<String, (i64, i64)> {
+    profile[field]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|e| e.get("village").and_then(|v| v.as_str()).unwrap_or("home") == "home")
+        .filter_map(|e| {
+            Some((
+                e.get("name")?.as_str()?.to_string(),
+                (
+                    e.get("level")?.as_i64()?,
+                    e.get("maxLevel").and_then(|v| v.as_i64()).unwrap_or(0),
+                ),
+            ))
+        })
+        .collect()
+}
+
+// Upgrade progress of a CoC profile: levels reached of the max. levels of its town hall,
+// and how many levels of the previous town hall are still missing (rushed). Unlocked
+// items the player does not have yet count as level 0, equipment only counts if owned.
+pub fn upgrade_progress(profile: &Value) -> Option<UpgradeProgress> {
+    let town_hall = profile.get("townHallLevel")?.as_i64()?;
+    let table = &*MAX_LEVELS;
+
+    let heroes = home_levels(profile, "heroes");
+    let troops = home_levels(profile, "troops");
+    let spells = home_levels(profile, "spells");
+    let equipment = home_levels(profile, "heroEquipment");
+
+    let mut total = Sums::default();
+    let mut categories = Vec::new();
+    for (name, reference, owned) in [
+        ("heroes", &table.heroes, &heroes),
+        ("pets", &table.pets, &troops),
+        ("troops", &table.troops, &troops),
+        ("spells", &table.spells, &spells),
+    ] {
+        let mut sums = Sums::default();
+        for (item, levels) in reference {
+            let max = cap(levels, town_hall);
+            if max == 0 {
+                continue;
+            }
+            let level = owned.get(item).map(|(l, _)| *l).unwrap_or(0);
+            let previous_max = cap(levels, town_hall - 1);
+            sums.add(level, max, previous_max);
+            total.add(level, max, previous_max);
+        }
+        categories.push(sums.category(name));
+    }
+
+    let mut sums = Sums::default();
+    for (level, api_max) in equipment.values() {
+        let caps = if *api_max > COMMON_EQUIPMENT_MAX_LEVEL {
+            &table.equipment.epic
+        } else {
+            &table.equipment.common
+        };
+        let (max, previous_max) = (cap(caps, town_hall), cap(caps, town_hall - 1));
+        if max == 0 {
+            continue;
+        }
+        sums.add(*level, max, previous_max);
+        total.add(*level, max, previous_max);
+    }
+    categories.push(sums.category("equipment"));
+
+    let overall = total.category("total");
+    Some(UpgradeProgress {
+        town_hall,
+        progress_percent: overall.progress_percent,
+        rushed_percent: overall.rushed_percent,
+        categories,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reference_table_has_a_level_per_town_hall() {
+        let t = &*MAX_LEVELS;
+        for levels in [&t.heroes, &t.pets, &t.troops, &t.spells]
+            .into_iter()
+            .flat_map(|c| c.values())
+            .chain([&t.equipment.common, &t.equipment.epic])
+        {
+            assert_eq!(levels.len(), 17);
+            assert!(levels.windows(2).all(|w| w[0] <= w[1]));
+        }
+    }
+
+    #[test]
+    fn missing_previous_town_hall_levels_count_as_rushed() {
+        // TH9 with a TH8 max King only: 20 of 30 + 30 + 10 (Queen, Minion Prince), nothing
+        // missing of TH8
+        let profile = json!({
+            "townHallLevel": 9,
+            "heroes": [
+                { "name": "Barbarian King", "level": 20, "village": "home" },
+                { "name": "Battle Machine", "level": 30, "village": "builderBase" },
+            ],
+        });
+        let progress = upgrade_progress(&profile).unwrap();
+        let heroes = &progress.categories[0];
+        assert_eq!((heroes.levels, heroes.max_levels), (20, 70));
+        assert_eq!(heroes.rushed_percent, 0.0);
+
+        // Troops of TH8 never upgraded are rushed
+        let troops = &progress.categories[2];
+        assert!(troops.rushed_percent > 90.0);
+        assert!(upgrade_progress(&json!({})).is_none());
+    }
+}
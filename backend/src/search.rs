@@ -0,0 +1,255 @@
+use crate::auth::has_required_role;
+use crate::background::family_clan_tags;
+use crate::models::{AppState, GameType, SearchResult};
+use crate::utils::{encode_tag, get_cache_body, get_cache_prefix, normalize_tag, record_refreshed};
+
+use log::info;
+use serde_json::Value;
+use std::collections::HashMap;
+
+struct Entry {
+    kind: &'static str,
+    tag: String,
+    name: String,
+    nickname: Option<String>,
+    clan_tag: Option<String>,
+    clan_name: Option<String>,
+    // Hidden in the bot (`isHidden`), only visible to coleaders
+    hidden: bool,
+    in_supercell: bool,
+}
+
+fn str_field(value: &Value, field: &str) -> Option<String> {
+    value
+        .get(field)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+async fn cached_json(data: &AppState, key: &str) -> Option<Value> {
+    let body = get_cache_body(data, key).await.ok()??;
+    serde_json::from_slice(&body).ok()
+}
+
+// Rebuild the search entries of a game from the cached clan, member and player data.
+// Called at the end of each clan refresh.
+pub async fn sync_search_index(data: &AppState, game: GameType) -> Result<i64, String> {
+    let prefix = get_cache_prefix(game);
+    let clan_tags = family_clan_tags(data, game).await?;
+    if clan_tags.is_empty() {
+        // Nothing cached yet, keep the previous index
+        return Ok(0);
+    }
+
+    // Clan names of the bot's clan list (fallback if Supercell data is missing)
+    let mut list_names: HashMap<String, String> = HashMap::new();
+    if let Some(Value::Array(clans)) =
+        cached_json(data, &format!("{}:upstream:/api/clans", prefix)).await
+    {
+        for clan in clans {
+            if let Some(tag) = str_field(&clan, "tag")
+                && let Some(name) = str_field(&clan, "name").or_else(|| str_field(&clan, "nameDB"))
+            {
+                list_names.insert(normalize_tag(&tag), name);
+            }
+        }
+    }
+
+    let mut entries: Vec<Entry> = Vec::new();
+    for clan_tag in &clan_tags {
+        let encoded_tag = encode_tag(clan_tag);
+        let supercell = cached_json(
+            data,
+            &format!("{}:supercell:/clans/{}", prefix, encoded_tag),
+        )
+        .await
+        .unwrap_or(Value::Null);
+        let upstream = cached_json(
+            data,
+            &format!("{}:upstream:/api/clans/{}/members", prefix, encoded_tag),
+        )
+        .await
+        .unwrap_or(Value::Null);
+
+        let clan_name = str_field(&supercell, "name")
+            .or_else(|| list_names.get(&normalize_tag(clan_tag)).cloned())
+            .unwrap_or_else(|| clan_tag.clone());
+        entries.push(Entry {
+            kind: "clan",
+            tag: normalize_tag(clan_tag),
+            name: clan_name.clone(),
+            nickname: None,
+            clan_tag: None,
+            clan_name: None,
+            hidden: false,
+            in_supercell: true,
+        });
+
+        // Supercell members first, then members only known to the bot
+        let mut members: HashMap<String, Entry> = HashMap::new();
+        for member in supercell["memberList"].as_array().into_iter().flatten() {
+            let Some(tag) = str_field(member, "tag") else {
+                continue;
+            };
+            let tag = normalize_tag(&tag);
+            members.insert(
+                tag.clone(),
+                Entry {
+                    kind: "player",
+                    name: str_field(member, "name").unwrap_or_else(|| tag.clone()),
+                    tag,
+                    nickname: None,
+                    clan_tag: Some(normalize_tag(clan_tag)),
+                    clan_name: Some(clan_name.clone()),
+                    hidden: false,
+                    in_supercell: true,
+                },
+            );
+        }
+
+        for member in upstream.as_array().into_iter().flatten() {
+            let Some(tag) = str_field(member, "tag") else {
+                continue;
+            };
+            let tag = normalize_tag(&tag);
+            let nickname = str_field(member, "nickname");
+            let hidden = member
+                .get("isHidden")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            if let Some(entry) = members.get_mut(&tag) {
+                entry.nickname = nickname;
+                entry.hidden = hidden;
+                continue;
+            }
+
+            // Left members: name from the player cache, then the bot
+            let player = cached_json(
+                data,
+                &format!("{}:supercell:/players/{}", prefix, encode_tag(&tag)),
+            )
+            .await
+            .unwrap_or(Value::Null);
+            let name = str_field(&player, "name")
+                .or_else(|| str_field(member, "name"))
+                .or_else(|| nickname.clone())
+                .unwrap_or_else(|| tag.clone());
+            members.insert(
+                tag.clone(),
+                Entry {
+                    kind: "player",
+                    tag,
+                    name,
+                    nickname,
+                    clan_tag: Some(normalize_tag(clan_tag)),
+                    clan_name: Some(clan_name.clone()),
+                    hidden,
+                    in_supercell: false,
+                },
+            );
+        }
+
+        entries.extend(members.into_values());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = data.db_pool.begin().await.map_err(|e| e.to_string())?;
+    for entry in &entries {
+        sqlx::query(
+            "INSERT INTO search_index (game, kind, tag, name, nickname, clan_tag, clan_name, hidden, in_supercell, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+             ON CONFLICT (game, kind, tag) DO UPDATE SET name = $4, nickname = $5, clan_tag = $6,
+                 clan_name = $7, hidden = $8, in_supercell = $9, updated_at = $10",
+        )
+        .bind(prefix)
+        .bind(entry.kind)
+        .bind(&entry.tag)
+        .bind(&entry.name)
+        .bind(&entry.nickname)
+        .bind(&entry.clan_tag)
+        .bind(&entry.clan_name)
+        .bind(entry.hidden)
+        .bind(entry.in_supercell)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    // Clans and members no longer in the family
+    sqlx::query("DELETE FROM search_index WHERE game = $1 AND updated_at < $2")
+        .bind(prefix)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    info!(
+        "Search index [{}]: {} entries from {} clans",
+        prefix,
+        entries.len(),
+        clan_tags.len()
+    );
+    Ok(entries.len() as i64)
+}
+
+// Escape LIKE wildcards of user input
+fn like_pattern(q: &str) -> String {
+    let escaped = q
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+// Fuzzy search over names and tags (trigram word similarity, so partial and misspelled
+// words match). Discord nicknames are only matched and returned for members, hidden
+// members only for coleaders (same rules as `filter_member_data`).
+pub async fn search(
+    data: &AppState,
+    q: &str,
+    game: Option<GameType>,
+    kind: Option<&str>,
+    limit: i64,
+    user_role: Option<&str>,
+) -> Result<Vec<SearchResult>, sqlx::Error> {
+    let tag_query = q.trim_start_matches('#').to_uppercase();
+
+    let results = sqlx::query_as::<_, SearchResult>(
+        "SELECT game, kind, tag, name, nickname, clan_tag, clan_name, updated_at,
+                GREATEST(word_similarity($1, name), similarity(tag, $7),
+                         COALESCE(word_similarity($1, nickname), 0)) AS score
+         FROM (
+             SELECT game, kind, tag, name, clan_tag, clan_name, updated_at,
+                    CASE WHEN $5 AND (NOT hidden OR $6) THEN nickname END AS nickname
+             FROM search_index
+             WHERE ($2::TEXT IS NULL OR game = $2)
+               AND ($3::TEXT IS NULL OR kind = $3)
+               AND ($6 OR NOT (hidden AND NOT in_supercell))
+         ) visible
+         WHERE $1 <% name OR name ILIKE $8 OR tag ILIKE $9 OR $1 <% nickname OR nickname ILIKE $8
+         ORDER BY score DESC, name ASC
+         LIMIT $4",
+    )
+    .bind(q)
+    .bind(game.map(get_cache_prefix))
+    .bind(kind)
+    .bind(limit)
+    .bind(has_required_role(user_role, "MEMBER"))
+    .bind(has_required_role(user_role, "COLEADER"))
+    .bind(format!("#{}", tag_query))
+    .bind(like_pattern(q))
+    .bind(like_pattern(&tag_query))
+    .fetch_all(&data.db_pool)
+    .await?;
+
+    // Entries combine Supercell names with the bot's nicknames
+    if let Some(updated_at) = results.iter().map(|r| r.updated_at).min() {
+        record_refreshed(data, "supercell", updated_at);
+        record_refreshed(data, "upstream", updated_at);
+    }
+    Ok(results)
+}
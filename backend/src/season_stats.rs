@@ -0,0 +1,220 @@
+use crate::background::{family_clan_tags, refresh_clan};
+use crate::models::{AppState, GameType, SeasonStats};
+use crate::utils::{cached_bodies, encode_tag, get_cache_prefix};
+
+use log::{error, info, warn};
+use serde_json::Value;
+use std::collections::HashSet;
+
+// Snapshot the running season of every family clan member. Values are overwritten on
+// each run, so the last snapshot before the reset is what remains in the archive.
+//
+// Only bodies fetched since the season started are used, so values from before the reset
+// never land in the new season.
+// CR: the API has no seasonal win counter, so season wins are the growth of the
+// lifetime wins since the first snapshot of the season.
+pub async fn capture_season_stats(data: &AppState) -> Result<i64, String> {
+    let mut captured = 0;
+    for game in [GameType::ClashOfClans, GameType::ClashRoyale] {
+        schedule_final_capture(data, game);
+        captured += capture_game(data, game).await?;
+    }
+    Ok(captured)
+}
+
+async fn capture_game(data: &AppState, game: GameType) -> Result<i64, String> {
+    let now = chrono::Utc::now();
+    let mut captured = 0;
+    let prefix = get_cache_prefix(game);
+    let season = crate::calendar::season(game, now);
+    let clan_tags = family_clan_tags(data, game).await?;
+
+    let clan_keys: Vec<String> = clan_tags
+        .iter()
+        .map(|t| format!("{}:supercell:/clans/{}", prefix, encode_tag(t)))
+        .collect();
+    let clans = cached_bodies(data, &clan_keys, season.start)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let player_key = |tag: &str| format!("{}:supercell:/players/{}", prefix, encode_tag(tag));
+    let player_keys: Vec<String> = clans
+        .values()
+        .flat_map(|c| c["memberList"].as_array().cloned().unwrap_or_default())
+        .filter_map(|m| m.get("tag").and_then(|t| t.as_str()).map(player_key))
+        .collect();
+    let profiles = cached_bodies(data, &player_keys, season.start)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut tx = data.db_pool.begin().await.map_err(|e| e.to_string())?;
+    for clan in clans.values() {
+        let Some(clan_tag) = clan.get("tag").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        for member in clan["memberList"].as_array().into_iter().flatten() {
+            let Some(tag) = member.get("tag").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let int = |v: Option<&Value>, field: &str| v?.get(field)?.as_i64();
+            let profile = profiles.get(&player_key(tag));
+            let (attack_wins, total_wins) = match game {
+                GameType::ClashOfClans => (int(profile, "attackWins"), None),
+                GameType::ClashRoyale => (None, int(profile, "wins")),
+            };
+
+            sqlx::query(
+                "INSERT INTO season_stats (game, season, clan_tag, player_tag, name, donations, donations_received,
+                     trophies, season_wins, wins_total_start, wins_total, captured_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8,
+                     COALESCE($9, CASE WHEN $10 IS NOT NULL THEN 0 END), $10, $10, $11)
+                 ON CONFLICT (game, season, clan_tag, player_tag) DO UPDATE SET
+                     name = $5, donations = $6, donations_received = $7, trophies = $8,
+                     season_wins = COALESCE($9, $10 - COALESCE(season_stats.wins_total_start, $10)),
+                     wins_total_start = COALESCE(season_stats.wins_total_start, $10),
+                     wins_total = $10, captured_at = $11",
+            )
+            .bind(prefix)
+            .bind(&season.key)
+            .bind(clan_tag)
+            .bind(tag)
+            .bind(member.get("name").and_then(|v| v.as_str()).unwrap_or(tag))
+            .bind(member.get("donations").and_then(|v| v.as_i64()).unwrap_or(0))
+            .bind(
+                member
+                    .get("donationsReceived")
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0),
+            )
+            .bind(member.get("trophies").and_then(|v| v.as_i64()).unwrap_or(0))
+            .bind(attack_wins)
+            .bind(total_wins)
+            .bind(now.timestamp())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+            captured += 1;
+        }
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    info!(
+        "Season stats [{}]: captured {} clans for season {}",
+        prefix,
+        clans.len(),
+        season.key
+    );
+
+    Ok(captured)
+}
+
+// The hourly snapshots miss up to an hour of the season, so one more snapshot is taken
+// shortly before the end, with freshly fetched clans.
+const FINAL_CAPTURE_LEAD_SECS: i64 = 5 * 60;
+// Scheduled by the runs within this time before the final capture (two hourly runs)
+const FINAL_CAPTURE_LOOKAHEAD_SECS: i64 = 2 * 3600;
+
+// Seasons ("coc:2026-10") whose final capture is scheduled
+static FINAL_CAPTURES: std::sync::LazyLock<std::sync::Mutex<HashSet<String>>> =
+    std::sync::LazyLock::new(Default::default);
+
+fn schedule_final_capture(data: &AppState, game: GameType) {
+    let season = crate::calendar::season(game, chrono::Utc::now());
+    let now = chrono::Utc::now().timestamp();
+    let at = season.end - FINAL_CAPTURE_LEAD_SECS;
+    if at <= now || at - now > FINAL_CAPTURE_LOOKAHEAD_SECS {
+        return;
+    }
+    let key = format!("{}:{}", get_cache_prefix(game), season.key);
+    if !FINAL_CAPTURES.lock().unwrap().insert(key.clone()) {
+        return;
+    }
+
+    info!("Season stats [{}]: final capture in {}s", key, at - now);
+    let data = data.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_secs((at - now) as u64)).await;
+        match family_clan_tags(&data, game).await {
+            Ok(tags) => {
+                for tag in tags {
+                    if let Err(e) = refresh_clan(&data, game, &tag).await {
+                        warn!("Season stats [{}]: refreshing {} failed: {}", key, tag, e);
+                    }
+                }
+            }
+            Err(e) => warn!("Season stats [{}]: loading the clans failed: {}", key, e),
+        }
+        if let Err(e) = capture_game(&data, game).await {
+            error!("Season stats [{}]: final capture failed: {}", key, e);
+        }
+    });
+}
+
+// Snapshots are taken hourly from the Supercell data. Only the running season is still
+// updated, so only its snapshots can fall behind.
+const SNAPSHOT_MAX_AGE_SECS: i64 = 2 * 3600;
+
+fn record_freshness(game: GameType, stats: &[SeasonStats]) {
+    let now = chrono::Utc::now();
+    let running = crate::calendar::season(game, now).key;
+    for (season, captured_at) in stats.iter().map(|s| (&s.season, s.captured_at)) {
+        let stale = *season == running && now.timestamp() - captured_at > SNAPSHOT_MAX_AGE_SECS;
+        crate::freshness::record("supercell", captured_at, stale);
+    }
+}
+
+const SELECT_STATS: &str =
+    "SELECT season, clan_tag, player_tag, name, donations, donations_received,
+     trophies, season_wins, captured_at FROM season_stats";
+
+// Seasons archived for a clan, newest first
+pub async fn clan_seasons(
+    data: &AppState,
+    game: GameType,
+    clan_tag: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT DISTINCT season FROM season_stats WHERE game = $1 AND clan_tag = $2 ORDER BY season DESC",
+    )
+    .bind(get_cache_prefix(game))
+    .bind(clan_tag)
+    .fetch_all(&data.db_pool)
+    .await
+}
+
+pub async fn clan_season(
+    data: &AppState,
+    game: GameType,
+    clan_tag: &str,
+    season: &str,
+) -> Result<Vec<SeasonStats>, sqlx::Error> {
+    let stats = sqlx::query_as::<_, SeasonStats>(&format!(
+        "{} WHERE game = $1 AND clan_tag = $2 AND season = $3 ORDER BY donations DESC, name ASC",
+        SELECT_STATS
+    ))
+    .bind(get_cache_prefix(game))
+    .bind(clan_tag)
+    .bind(season)
+    .fetch_all(&data.db_pool)
+    .await?;
+    record_freshness(game, &stats);
+    Ok(stats)
+}
+
+// All seasons of a player (in any family clan), newest first
+pub async fn player_seasons(
+    data: &AppState,
+    game: GameType,
+    player_tag: &str,
+) -> Result<Vec<SeasonStats>, sqlx::Error> {
+    let stats = sqlx::query_as::<_, SeasonStats>(&format!(
+        "{} WHERE game = $1 AND player_tag = $2 ORDER BY season DESC, captured_at DESC",
+        SELECT_STATS
+    ))
+    .bind(get_cache_prefix(game))
+    .bind(player_tag)
+    .fetch_all(&data.db_pool)
+    .await?;
+    record_freshness(game, &stats);
+    Ok(stats)
+}
@@ -1,9 +1,12 @@
-use crate::models::{AppState, ErrorResponse, GameType};
+use crate::errors::ApiError;
+use crate::fixtures::{self, ExternalApi};
+use crate::models::{AppState, GameType};
 use actix_web::HttpResponse;
-use actix_web::http::StatusCode;
+use actix_web::http::{StatusCode, header};
 use bytes::Bytes;
-use log::error;
+use log::{error, warn};
 use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use std::collections::HashMap;
 
 pub fn format_url(base: &str, path: &str) -> String {
     format!("{}{}", base, path)
@@ -18,24 +21,45 @@ pub fn encode_tag(tag: &str) -> String {
     utf8_percent_encode(&tag, NON_ALPHANUMERIC).to_string()
 }
 
-fn get_cache_prefix(game: GameType) -> &'static str {
+// Canonical form of a player or clan tag ("#" + upper case) for comparing and storing
+pub fn normalize_tag(tag: &str) -> String {
+    format!("#{}", tag.trim_start_matches('#').to_uppercase())
+}
+
+pub fn get_cache_prefix(game: GameType) -> &'static str {
     match game {
         GameType::ClashOfClans => "coc",
         GameType::ClashRoyale => "cr",
     }
 }
 
-fn get_supercell_api_url(game: GameType) -> &'static str {
+// Split a cache key like "coc:supercell:/players/%23ABC" into game, source and URL path
+pub fn parse_cache_key(key: &str) -> Option<(GameType, &str, &str)> {
+    let mut parts = key.splitn(3, ':');
+    let game = match parts.next()? {
+        "coc" => GameType::ClashOfClans,
+        "cr" => GameType::ClashRoyale,
+        _ => return None,
+    };
+    let source = parts.next()?;
+    let path = parts.next()?;
+    if !matches!(source, "upstream" | "supercell") || !path.starts_with('/') {
+        return None;
+    }
+    Some((game, source, path))
+}
+
+fn get_supercell_api(game: GameType) -> ExternalApi {
     match game {
-        GameType::ClashOfClans => "https://api.clashofclans.com/v1",
-        GameType::ClashRoyale => "https://api.clashroyale.com/v1",
+        GameType::ClashOfClans => ExternalApi::SupercellCoc,
+        GameType::ClashRoyale => ExternalApi::SupercellCr,
     }
 }
 
-fn get_upstream_url(data: &AppState, game: GameType) -> &str {
+fn get_upstream_api(game: GameType) -> ExternalApi {
     match game {
-        GameType::ClashOfClans => &data.upstream_coc_url,
-        GameType::ClashRoyale => &data.upstream_cr_url,
+        GameType::ClashOfClans => ExternalApi::UpstreamCoc,
+        GameType::ClashRoyale => ExternalApi::UpstreamCr,
     }
 }
 
@@ -53,6 +77,135 @@ fn get_supercell_token(data: &AppState, game: GameType) -> &str {
     }
 }
 
+// Cache bodies are stored zstd-compressed. Rows written before compression was
+// introduced hold plain JSON and are detected by the missing zstd frame magic.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const CACHE_COMPRESSION_LEVEL: i32 = 3;
+
+pub fn decode_cache_body(body: Vec<u8>) -> Bytes {
+    if body.starts_with(&ZSTD_MAGIC) {
+        match zstd::decode_all(body.as_slice()) {
+            Ok(decoded) => return Bytes::from(decoded),
+            Err(e) => error!("Failed to decompress cache body: {}", e),
+        }
+    }
+    Bytes::from(body)
+}
+
+// Record the freshness of data read outside of `get_cached_or_update`. Such data is
+// only kept up to date by the background refresh, so it counts as stale once the
+// refresh fell two intervals behind.
+pub fn record_refreshed(data: &AppState, source: &str, updated_at: i64) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let max_age = 2 * data.background_refresh_interval.max(1) as i64 * 60;
+    crate::freshness::record(source, updated_at, now - updated_at > max_age);
+}
+
+pub fn record_cache_read(data: &AppState, key: &str, updated_at: i64) {
+    if let Some((_, source, _)) = parse_cache_key(key) {
+        record_refreshed(data, source, updated_at);
+    }
+}
+
+// Read and decode a single cache body
+pub async fn get_cache_body(data: &AppState, key: &str) -> Result<Option<Bytes>, sqlx::Error> {
+    Ok(get_cache_body_dated(data, key).await?.map(|(body, _)| body))
+}
+
+// Like `get_cache_body`, with the time the body was stored
+pub async fn get_cache_body_dated(
+    data: &AppState,
+    key: &str,
+) -> Result<Option<(Bytes, i64)>, sqlx::Error> {
+    let row =
+        sqlx::query_as::<_, (Vec<u8>, i64)>("SELECT body, updated_at FROM cache WHERE key = $1")
+            .bind(key)
+            .fetch_optional(&data.db_pool)
+            .await?;
+    Ok(row.map(|(body, updated_at)| {
+        record_cache_read(data, key, updated_at);
+        (decode_cache_body(body), updated_at)
+    }))
+}
+
+// Decoded JSON bodies of the cached `keys` updated at or after `since`, by key.
+// Missing keys and bodies that are no JSON are left out.
+pub async fn cached_bodies(
+    data: &AppState,
+    keys: &[String],
+    since: i64,
+) -> Result<HashMap<String, serde_json::Value>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, Vec<u8>, i64)>(
+        "SELECT key, body, updated_at FROM cache WHERE key = ANY($1) AND updated_at >= $2",
+    )
+    .bind(keys)
+    .bind(since)
+    .fetch_all(&data.db_pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(key, body, updated_at)| {
+            let json = serde_json::from_slice(&decode_cache_body(body)).ok()?;
+            record_cache_read(data, &key, updated_at);
+            Some((key, json))
+        })
+        .collect())
+}
+
+async fn store_cache_body(pool: &sqlx::PgPool, key: &str, body: &[u8], status: i32) {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    let stored = match zstd::encode_all(body, CACHE_COMPRESSION_LEVEL) {
+        Ok(compressed) => compressed,
+        Err(e) => {
+            error!("Failed to compress cache body for {}: {}", key, e);
+            body.to_vec()
+        }
+    };
+
+    let _ = sqlx::query(
+        "INSERT INTO cache (key, body, status, updated_at) 
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (key) DO UPDATE SET 
+            body = EXCLUDED.body, 
+            status = EXCLUDED.status, 
+            updated_at = EXCLUDED.updated_at",
+    )
+    .bind(key)
+    .bind(stored)
+    .bind(status)
+    .bind(timestamp)
+    .execute(pool)
+    .await;
+}
+
+// Remember reads for the cache GC. Throttled to one write per key and minute.
+pub async fn touch_cache_key(pool: &sqlx::PgPool, key: &str) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let _ = sqlx::query(
+        "UPDATE cache SET accessed_at = $1 WHERE key = $2 AND (accessed_at IS NULL OR accessed_at < $1 - 60)",
+    )
+    .bind(now)
+    .bind(key)
+    .execute(pool)
+    .await;
+}
+
+// HTTP date for the Last-Modified header
+pub fn last_modified(updated_at: i64) -> header::HttpDate {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(updated_at.max(0) as u64);
+    header::HttpDate::from(time)
+}
+
 // Function to filter out specific fields from clan data
 pub fn filter_clan_data(body: Bytes, game: GameType, filter_fields: bool) -> Bytes {
     if let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(&body) {
@@ -66,7 +219,8 @@ pub fn filter_clan_data(body: Bytes, game: GameType, filter_fields: bool) -> Byt
         ];
 
         if let Some(clans) = value.as_array_mut() {
-            // Filter out "Warteliste" for Clash Royale (always, for everyone)
+            // Filter out "Warteliste" for Clash Royale (always, for everyone). The bots still
+            // list this pseudo-clan; the website waitlist lives in `applications`.
             if game == GameType::ClashRoyale {
                 let old_len = clans.len();
                 clans.retain(|c| {
@@ -297,103 +451,159 @@ pub async fn update_upstream_cache(
     data: &AppState,
     game: GameType,
     url_path: &str,
-) -> Result<Bytes, String> {
+) -> Result<Bytes, ApiError> {
     let prefix = get_cache_prefix(game);
-    let upstream_url = get_upstream_url(data, game);
+    let api = get_upstream_api(game);
     let token = get_upstream_token(data, game);
-    let full_url = format_url(upstream_url, url_path);
 
-    match data
-        .client
-        .get(&full_url)
-        .header("Authorization", format!("Bearer {}", token))
-        .send()
+    let res = fixtures::get(data, api, url_path, token)
         .await
-    {
-        Ok(res) => {
-            let status = res.status().as_u16();
-            let body = res.bytes().await.map_err(|e| e.to_string())?;
-
-            if status == 200 {
-                let timestamp = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs() as i64;
-
-                let cache_key = format!("{}:upstream:{}", prefix, url_path);
-
-                let _ = sqlx::query(
-                    "INSERT INTO cache (key, body, status, updated_at) 
-                 VALUES ($1, $2, $3, $4)
-                 ON CONFLICT (key) DO UPDATE SET 
-                    body = EXCLUDED.body, 
-                    status = EXCLUDED.status, 
-                    updated_at = EXCLUDED.updated_at",
-                )
-                .bind(&cache_key)
-                .bind(body.to_vec())
-                .bind(status as i32)
-                .bind(timestamp)
-                .execute(&data.db_pool)
-                .await;
-
-                Ok(body)
-            } else {
-                let err_msg = format!("Upstream {} returned status {}", full_url, status);
-                eprintln!("Background Refresh: {}", err_msg);
-                Err(err_msg)
-            }
+        .map_err(ApiError::UpstreamUnavailable)?;
+
+    if res.status == 200 {
+        let cache_key = format!("{}:upstream:{}", prefix, url_path);
+        store_cache_body(&data.db_pool, &cache_key, &res.body, res.status as i32).await;
+
+        Ok(res.body)
+    } else {
+        let err_msg = format!(
+            "Upstream {}{} returned status {}",
+            api.name(),
+            url_path,
+            res.status
+        );
+        eprintln!("Background Refresh: {}", err_msg);
+        Err(ApiError::from_upstream_status(res.status, err_msg))
+    }
+}
+
+// Key classes with their own freshness policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheClass {
+    // Clan info and member lists (kept fresh by the background refresh)
+    Clan,
+    // Player profiles
+    Player,
+    // Linked accounts of a Discord user
+    User,
+}
+
+impl CacheClass {
+    // Classify a cache URL path; clan lists, guild info etc. have no class
+    pub fn of_path(url_path: &str) -> Option<Self> {
+        let path = url_path.strip_prefix("/api").unwrap_or(url_path);
+        if path.starts_with("/players/") {
+            Some(CacheClass::Player)
+        } else if path.starts_with("/clans/") {
+            Some(CacheClass::Clan)
+        } else if path.starts_with("/users/") {
+            Some(CacheClass::User)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CacheClass::Clan => "clan",
+            CacheClass::Player => "player",
+            CacheClass::User => "user",
         }
-        Err(e) => Err(e.to_string()),
     }
 }
 
-pub async fn get_cached_or_update_supercell_cache(
-    data: &AppState,
-    game: GameType,
-    url_path: &str,
-    ttl_seconds: i64,
-) -> Result<Bytes, String> {
-    let prefix = get_cache_prefix(game);
-    let cache_key = format!("{}:supercell:{}", prefix, url_path);
+#[derive(Debug, Clone, Copy)]
+pub struct CachePolicy {
+    // Served without revalidation while younger than this
+    pub ttl_secs: i64,
+    // After the TTL, served immediately while a background refresh runs
+    pub grace_secs: i64,
+    // Evicted by the cache GC once neither updated nor accessed for this long
+    pub retention_secs: i64,
+}
 
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
+#[derive(Debug, Clone)]
+pub struct CachePolicies {
+    pub clan: CachePolicy,
+    pub player: CachePolicy,
+    pub user: CachePolicy,
+    // Retention of keys without a class
+    pub other_retention_secs: i64,
+    // How long a request waits on a hard miss before falling back
+    pub miss_deadline: std::time::Duration,
+}
 
-    let cached_result =
-        sqlx::query_as::<_, (Vec<u8>, i64)>("SELECT body, updated_at FROM cache WHERE key = $1")
-            .bind(&cache_key)
-            .fetch_optional(&data.db_pool)
-            .await;
+impl CachePolicies {
+    pub fn from_config(config: &crate::config::CacheConfig) -> Self {
+        let days = |d: i64| d * 24 * 3600;
+        let policy = |c: &crate::config::CacheClassConfig| CachePolicy {
+            ttl_secs: c.ttl_secs,
+            grace_secs: c.grace_secs,
+            retention_secs: days(c.retention_days),
+        };
 
-    if let Ok(Some((body, updated_at))) = &cached_result
-        && now - updated_at < ttl_seconds
-    {
-        return Ok(Bytes::from(body.clone()));
+        CachePolicies {
+            clan: policy(&config.clan),
+            player: policy(&config.player),
+            user: policy(&config.user),
+            other_retention_secs: days(config.other_retention_days),
+            miss_deadline: std::time::Duration::from_secs(config.miss_deadline_secs.max(1) as u64),
+        }
     }
 
-    match update_supercell_cache(data, game, url_path).await {
-        Ok(body) => Ok(body),
-        Err(e) => {
-            // Fallback to expired cache on error
-            if let Ok(Some((body, _))) = cached_result {
-                return Ok(Bytes::from(body));
-            }
-            Err(e)
+    pub fn get(&self, class: CacheClass) -> CachePolicy {
+        match class {
+            CacheClass::Clan => self.clan,
+            CacheClass::Player => self.player,
+            CacheClass::User => self.user,
         }
     }
 }
 
-pub async fn get_cached_or_update_upstream_cache(
+// Keys with a background revalidation in flight (one refresh per key at a time)
+static REVALIDATING: std::sync::LazyLock<std::sync::Mutex<std::collections::HashSet<String>>> =
+    std::sync::LazyLock::new(Default::default);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CacheSource {
+    Supercell,
+    Upstream,
+}
+
+impl CacheSource {
+    fn name(self) -> &'static str {
+        match self {
+            CacheSource::Supercell => "supercell",
+            CacheSource::Upstream => "upstream",
+        }
+    }
+
+    async fn update(
+        self,
+        data: &AppState,
+        game: GameType,
+        url_path: &str,
+    ) -> Result<Bytes, ApiError> {
+        match self {
+            CacheSource::Supercell => update_supercell_cache(data, game, url_path).await,
+            CacheSource::Upstream => update_upstream_cache(data, game, url_path).await,
+        }
+    }
+}
+
+// Stale-while-revalidate read: fresh entries are served directly, entries within the
+// grace window are served while a background refresh runs, and only hard misses wait
+// for the fetch (bounded by the miss deadline, falling back to the expired body).
+async fn get_cached_or_update(
     data: &AppState,
     game: GameType,
+    source: CacheSource,
     url_path: &str,
-    ttl_seconds: i64,
-) -> Result<Bytes, String> {
+    class: CacheClass,
+) -> Result<(Bytes, i64), ApiError> {
+    let policy = data.cache_policies.get(class);
     let prefix = get_cache_prefix(game);
-    let cache_key = format!("{}:upstream:{}", prefix, url_path);
+    let cache_key = format!("{}:{}:{}", prefix, source.name(), url_path);
 
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -404,52 +614,159 @@ pub async fn get_cached_or_update_upstream_cache(
         sqlx::query_as::<_, (Vec<u8>, i64)>("SELECT body, updated_at FROM cache WHERE key = $1")
             .bind(&cache_key)
             .fetch_optional(&data.db_pool)
-            .await;
-
-    if let Ok(Some((body, updated_at))) = &cached_result
-        && now - updated_at < ttl_seconds
-    {
-        return Ok(Bytes::from(body.clone()));
+            .await
+            .map(|row| row.map(|(body, updated_at)| (decode_cache_body(body), updated_at)));
+
+    if let Ok(Some((body, updated_at))) = &cached_result {
+        touch_cache_key(&data.db_pool, &cache_key).await;
+        let age = now - updated_at;
+        if age < policy.ttl_secs {
+            crate::freshness::record(source.name(), *updated_at, false);
+            return Ok((body.clone(), *updated_at));
+        }
+        if age < policy.ttl_secs + policy.grace_secs {
+            revalidate_in_background(data, game, source, url_path, cache_key);
+            crate::freshness::record(source.name(), *updated_at, true);
+            return Ok((body.clone(), *updated_at));
+        }
     }
 
-    match update_upstream_cache(data, game, url_path).await {
-        Ok(body) => Ok(body),
+    // Run the fetch in its own task so it still fills the cache if we stop waiting
+    let fetch = {
+        let data = data.clone();
+        let url_path = url_path.to_string();
+        tokio::spawn(async move { source.update(&data, game, &url_path).await })
+    };
+
+    let result = match tokio::time::timeout(data.cache_policies.miss_deadline, fetch).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => Err(ApiError::Internal(e.to_string())),
+        Err(_) => Err(ApiError::UpstreamUnavailable(format!(
+            "Timed out after {}s fetching {}",
+            data.cache_policies.miss_deadline.as_secs(),
+            cache_key
+        ))),
+    };
+
+    match result {
+        Ok(body) => {
+            crate::freshness::record(source.name(), now, false);
+            Ok((body, now))
+        }
         Err(e) => {
             // Fallback to expired cache on error
-            if let Ok(Some((body, _))) = cached_result {
-                return Ok(Bytes::from(body));
+            if let Ok(Some((body, updated_at))) = cached_result {
+                crate::freshness::record(source.name(), updated_at, true);
+                return Ok((body, updated_at));
             }
             Err(e)
         }
     }
 }
 
-pub async fn forward_request(data: &AppState, game: GameType, url_path: &str) -> HttpResponse {
-    forward_request_with_filter(data, game, url_path, None, &[]).await
+fn revalidate_in_background(
+    data: &AppState,
+    game: GameType,
+    source: CacheSource,
+    url_path: &str,
+    cache_key: String,
+) {
+    if !REVALIDATING.lock().unwrap().insert(cache_key.clone()) {
+        return;
+    }
+
+    let data = data.clone();
+    let url_path = url_path.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = source.update(&data, game, &url_path).await {
+            error!("Background revalidation of {} failed: {}", cache_key, e);
+        }
+        REVALIDATING.lock().unwrap().remove(&cache_key);
+    });
 }
 
-pub async fn forward_request_with_filter(
+pub async fn get_cached_or_update_supercell_cache(
+    data: &AppState,
+    game: GameType,
+    url_path: &str,
+    class: CacheClass,
+) -> Result<Bytes, ApiError> {
+    get_cached_or_update_supercell_cache_dated(data, game, url_path, class)
+        .await
+        .map(|(body, _)| body)
+}
+
+pub async fn get_cached_or_update_upstream_cache(
+    data: &AppState,
+    game: GameType,
+    url_path: &str,
+    class: CacheClass,
+) -> Result<Bytes, ApiError> {
+    get_cached_or_update_upstream_cache_dated(data, game, url_path, class)
+        .await
+        .map(|(body, _)| body)
+}
+
+// The `_dated` variants also return when the body was stored (for Last-Modified)
+pub async fn get_cached_or_update_supercell_cache_dated(
+    data: &AppState,
+    game: GameType,
+    url_path: &str,
+    class: CacheClass,
+) -> Result<(Bytes, i64), ApiError> {
+    get_cached_or_update(data, game, CacheSource::Supercell, url_path, class).await
+}
+
+pub async fn get_cached_or_update_upstream_cache_dated(
+    data: &AppState,
+    game: GameType,
+    url_path: &str,
+    class: CacheClass,
+) -> Result<(Bytes, i64), ApiError> {
+    get_cached_or_update(data, game, CacheSource::Upstream, url_path, class).await
+}
+
+pub async fn forward_request<T>(
+    data: &AppState,
+    game: GameType,
+    url_path: &str,
+) -> Result<HttpResponse, ApiError>
+where
+    T: serde::de::DeserializeOwned + serde::Serialize,
+{
+    forward_request_with_filter::<T>(data, game, url_path, None, &[]).await
+}
+
+// Serve a bot body from the cache, filtered for the user's role, as `T`. Bodies that do
+// not match `T` (the bots' formats are not ours) are served as they are.
+pub async fn forward_request_with_filter<T>(
     data: &AppState,
     game: GameType,
     url_path: &str,
     user_role: Option<&str>,
     exempt_tags: &[String],
-) -> HttpResponse {
+) -> Result<HttpResponse, ApiError>
+where
+    T: serde::de::DeserializeOwned + serde::Serialize,
+{
     let prefix = get_cache_prefix(game);
     // Map /members-lite request to /members cache key
     let stripped_path = url_path.replace("/members-lite", "/members");
     let cache_key = format!("{}:upstream:{}", prefix, stripped_path);
 
     // Serve from cache ONLY
-    let result =
-        sqlx::query_as::<_, (Vec<u8>, i32)>("SELECT body, status FROM cache WHERE key = $1")
-            .bind(&cache_key)
-            .fetch_optional(&data.db_pool)
-            .await;
+    let result = sqlx::query_as::<_, (Vec<u8>, i32, i64)>(
+        "SELECT body, status, updated_at FROM cache WHERE key = $1",
+    )
+    .bind(&cache_key)
+    .fetch_optional(&data.db_pool)
+    .await?;
 
     match result {
-        Ok(Some((body, status))) => {
-            let mut body = Bytes::from(body);
+        Some((body, status, updated_at)) => {
+            touch_cache_key(&data.db_pool, &cache_key).await;
+            let mut body = decode_cache_body(body);
+            record_cache_read(data, &cache_key, updated_at);
 
             let parts: Vec<&str> = url_path.split('/').collect();
             let is_clan_path = url_path == "/api/clans"
@@ -475,19 +792,17 @@ pub async fn forward_request_with_filter(
             }
 
             let status = StatusCode::from_u16(status as u16).unwrap_or(StatusCode::OK);
-            HttpResponse::build(status)
-                .content_type("application/json")
-                .body(body)
-        }
-        Ok(None) => HttpResponse::ServiceUnavailable().json(ErrorResponse {
-            error: "Data not yet available in cache. Background refresh is in progress.".into(),
-        }),
-        Err(e) => {
-            error!("Database error: {}", e);
-            HttpResponse::InternalServerError().json(ErrorResponse {
-                error: "Internal Database Error".into(),
-            })
+            let mut res = HttpResponse::build(status);
+            res.insert_header(header::LastModified(last_modified(updated_at)));
+            if status.is_success() {
+                match serde_json::from_slice::<T>(&body) {
+                    Ok(typed) => return Ok(res.json(typed)),
+                    Err(e) => warn!("Unexpected format of {}: {}", cache_key, e),
+                }
+            }
+            Ok(res.content_type("application/json").body(body))
         }
+        None => Err(ApiError::NotYetCached),
     }
 }
 
@@ -496,53 +811,37 @@ pub async fn update_supercell_cache(
     data: &AppState,
     game: GameType,
     url_path: &str,
-) -> Result<Bytes, String> {
+) -> Result<Bytes, ApiError> {
     let prefix = get_cache_prefix(game);
-    let api_url = get_supercell_api_url(game);
+    let api = get_supercell_api(game);
     let token = get_supercell_token(data, game);
-    let full_url = format!("{}{}", api_url, url_path);
 
-    match data
-        .client
-        .get(&full_url)
-        .header("Authorization", format!("Bearer {}", token))
-        .send()
+    let res = fixtures::get(data, api, url_path, token)
         .await
-    {
-        Ok(res) => {
-            let status = res.status().as_u16();
-            let body = res.bytes().await.map_err(|e| e.to_string())?;
-
-            if status == 200 {
-                let timestamp = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs() as i64;
-
-                let cache_key = format!("{}:supercell:{}", prefix, url_path);
-
-                let _ = sqlx::query(
-                    "INSERT INTO cache (key, body, status, updated_at) 
-                 VALUES ($1, $2, $3, $4)
-                 ON CONFLICT (key) DO UPDATE SET 
-                    body = EXCLUDED.body, 
-                    status = EXCLUDED.status, 
-                    updated_at = EXCLUDED.updated_at",
-                )
-                .bind(&cache_key)
-                .bind(body.to_vec())
-                .bind(status as i32)
-                .bind(timestamp)
-                .execute(&data.db_pool)
-                .await;
-
-                Ok(body)
-            } else {
-                let err_msg = format!("Supercell {} returned status {}", full_url, status);
-                eprintln!("Background Refresh: {}", err_msg);
-                Err(err_msg)
-            }
+        .map_err(ApiError::UpstreamUnavailable)?;
+
+    if res.status == 200 {
+        let cache_key = format!("{}:supercell:{}", prefix, url_path);
+        store_cache_body(&data.db_pool, &cache_key, &res.body, res.status as i32).await;
+
+        // Player profiles (not their sub-resources like the battle log)
+        if url_path
+            .strip_prefix("/players/")
+            .is_some_and(|rest| !rest.contains('/'))
+            && let Err(e) = crate::activity::record_player_fetch(data, game, &res.body).await
+        {
+            warn!("Error recording activity of {}: {}", url_path, e);
         }
-        Err(e) => Err(e.to_string()),
+
+        Ok(res.body)
+    } else {
+        let err_msg = format!(
+            "Supercell {}{} returned status {}",
+            api.name(),
+            url_path,
+            res.status
+        );
+        eprintln!("Background Refresh: {}", err_msg);
+        Err(ApiError::from_upstream_status(res.status, err_msg))
     }
 }
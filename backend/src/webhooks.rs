@@ -0,0 +1,200 @@
+use crate::errors::{ApiError, validate_tag};
+use crate::models::{AppState, BotEvent, GameType};
+use crate::utils::{encode_tag, get_cache_prefix, normalize_tag, update_upstream_cache};
+
+use hmac::{Hmac, Mac};
+use log::warn;
+use sha2::Sha256;
+
+// Change events pushed by the bots. Requests carry `X-Bot-Timestamp` (unix seconds) and
+// `X-Bot-Signature`, the hex HMAC-SHA256 of "{timestamp}.{body}" with the bot's webhook
+// secret (optionally prefixed with "sha256=").
+
+// Older (or future) timestamps are rejected to prevent replays
+const MAX_CLOCK_SKEW_SECS: i64 = 300;
+
+// Whether a signed unix `timestamp` is recent enough. Shared with the Discord interactions.
+pub fn timestamp_within_skew(timestamp: &str, now: i64) -> bool {
+    timestamp
+        .parse::<i64>()
+        .is_ok_and(|sent_at| (now - sent_at).abs() <= MAX_CLOCK_SKEW_SECS)
+}
+
+pub fn verify_signature(
+    secret: &str,
+    timestamp: &str,
+    body: &[u8],
+    signature: &str,
+    now: i64,
+) -> bool {
+    if !timestamp_within_skew(timestamp, now) {
+        return false;
+    }
+    let Ok(signature) = hex::decode(signature.trim_start_matches("sha256=")) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+// Upstream paths whose cached responses change with the event. Linked accounts of a
+// Discord user are re-synced separately (`sync_user_accounts`).
+pub fn affected_paths(event: &BotEvent) -> Result<Vec<String>, ApiError> {
+    let tag = |tag: &Option<String>, name: &str| match tag {
+        Some(tag) => validate_tag(tag).map(|_| encode_tag(&normalize_tag(tag))),
+        None => Err(ApiError::BadRequest(format!(
+            "'{}' needs {}",
+            event.event, name
+        ))),
+    };
+
+    match event.event.as_str() {
+        "player_updated" => Ok(vec![format!(
+            "/api/players/{}",
+            tag(&event.player_tag, "player_tag")?
+        )]),
+        "kickpoint_added" => {
+            let mut paths = vec![format!(
+                "/api/players/{}",
+                tag(&event.player_tag, "player_tag")?
+            )];
+            // Every member list of the clan carries the kickpoints of its members
+            if event.clan_tag.is_some() {
+                let clan = tag(&event.clan_tag, "clan_tag")?;
+                for list in [
+                    "members",
+                    "members-lite",
+                    "war-members",
+                    "raid-members",
+                    "cwl-members",
+                ] {
+                    paths.push(format!("/api/clans/{}/{}", clan, list));
+                }
+            }
+            Ok(paths)
+        }
+        "link_changed" => {
+            if !event
+                .discord_id
+                .as_deref()
+                .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+            {
+                return Err(ApiError::BadRequest(
+                    "'link_changed' needs a numeric discord_id".to_string(),
+                ));
+            }
+            match &event.player_tag {
+                Some(_) => Ok(vec![format!(
+                    "/api/players/{}",
+                    tag(&event.player_tag, "player_tag")?
+                )]),
+                None => Ok(vec![]),
+            }
+        }
+        "clan_config_changed" => {
+            let clan = tag(&event.clan_tag, "clan_tag")?;
+            Ok(vec![
+                format!("/api/clans/{}", clan),
+                format!("/api/clans/{}/kickpoint-reasons", clan),
+            ])
+        }
+        other => Err(ApiError::BadRequest(format!("Unknown event '{}'", other))),
+    }
+}
+
+// Refresh the cached keys among `paths`; keys the bot no longer serves are removed so
+// the next read fetches them. Uncached paths are left alone.
+pub async fn refresh_paths(
+    data: &AppState,
+    game: GameType,
+    paths: &[String],
+) -> Result<(Vec<String>, Vec<String>), sqlx::Error> {
+    let prefix = get_cache_prefix(game);
+    let mut refreshed = Vec::new();
+    let mut invalidated = Vec::new();
+    for path in paths {
+        let key = format!("{}:upstream:{}", prefix, path);
+        let cached: Option<i32> = sqlx::query_scalar("SELECT 1 FROM cache WHERE key = $1")
+            .bind(&key)
+            .fetch_optional(&data.db_pool)
+            .await?;
+        if cached.is_none() {
+            continue;
+        }
+        match update_upstream_cache(data, game, path).await {
+            Ok(_) => refreshed.push(key),
+            Err(e) => {
+                warn!("Webhook refresh of {} failed, invalidating: {}", key, e);
+                sqlx::query("DELETE FROM cache WHERE key = $1")
+                    .bind(&key)
+                    .execute(&data.db_pool)
+                    .await?;
+                invalidated.push(key);
+            }
+        }
+    }
+    Ok((refreshed, invalidated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_outside_the_clock_skew_are_rejected() {
+        assert!(timestamp_within_skew("1700000000", 1700000300));
+        assert!(timestamp_within_skew("1700000300", 1700000000));
+        // Replayed after the allowed clock skew, or sent from the future
+        assert!(!timestamp_within_skew("1700000000", 1700000301));
+        assert!(!timestamp_within_skew("1700000301", 1700000000));
+        assert!(!timestamp_within_skew("", 1700000000));
+        assert!(!timestamp_within_skew("17e8", 1700000000));
+    }
+
+    #[test]
+    fn signatures_are_bound_to_secret_timestamp_and_body() {
+        let body = br##"{"event":"player_updated","player_tag":"#ABC"}"##;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(b"1700000000.");
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert!(verify_signature(
+            "secret",
+            "1700000000",
+            body,
+            &signature,
+            1700000100
+        ));
+        assert!(!verify_signature(
+            "other",
+            "1700000000",
+            body,
+            &signature,
+            1700000100
+        ));
+        assert!(!verify_signature(
+            "secret",
+            "1700000000",
+            b"{}",
+            &signature,
+            1700000100
+        ));
+
+        let event: BotEvent = serde_json::from_slice(body).unwrap();
+        assert_eq!(affected_paths(&event).unwrap(), ["/api/players/%23ABC"]);
+
+        let event: BotEvent = serde_json::from_str(
+            r##"{"event":"kickpoint_added","player_tag":"#ABC","clan_tag":"#c1"}"##,
+        )
+        .unwrap();
+        let paths = affected_paths(&event).unwrap();
+        assert_eq!(paths.len(), 6);
+        assert!(paths.contains(&"/api/clans/%23C1/raid-members".to_string()));
+    }
+}